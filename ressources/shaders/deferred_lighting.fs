@@ -0,0 +1,205 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+
+#define MAX_DIR_LIGHTS 4
+#define MAX_SPOT_LIGHTS 8
+#define MAX_CASCADES 4
+
+struct DirLight {
+    vec3 direction;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+struct SpotLight {
+    vec3 position;
+    vec3 direction;
+    // cosines of the cone's angles
+    float cutOff;
+    float outerCutOff;
+    float constant;
+    float linear;
+    float quadratic;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+// written by gbuffer.fs
+uniform sampler2D gAlbedo;
+uniform sampler2D gNormal;
+uniform sampler2D gPosition;
+uniform sampler2D gMaterial;
+uniform sampler2D gAmbient;
+
+// the point lights are added by light_volume.fs
+uniform DirLight dirLights[MAX_DIR_LIGHTS];
+uniform int dirLightCount;
+uniform SpotLight spotLights[MAX_SPOT_LIGHTS];
+uniform int spotLightCount;
+
+// depth seen from the first directional light
+uniform bool shadowsEnabled;
+uniform sampler2D shadowMap;
+uniform mat4 lightSpaceMatrix;
+uniform float shadowBias;
+uniform float shadowSlopeBias;
+uniform int shadowPcfRadius;
+
+// cascades of the first directional light, used instead of shadowMap when enabled
+uniform bool cascadesEnabled;
+uniform bool cascadeDebug;
+uniform sampler2DArray cascadeShadowMap;
+uniform int cascadeCount;
+// view space distance where each cascade ends
+uniform float cascadeSplits[MAX_CASCADES];
+uniform mat4 cascadeLightSpaces[MAX_CASCADES];
+uniform float cascadeBias;
+uniform float cascadeSlopeBias;
+uniform int cascadePcfRadius;
+
+uniform vec3 viewPos;
+
+// read from the G-buffer before lighting
+vec3 FragPos;
+float viewDepth;
+float shininess;
+
+vec3 blinnPhong(vec3 N, vec3 V, vec3 L, vec3 diffuse, vec3 specular, vec3 albedo, vec3 specularColor)
+{
+    vec3 H = normalize(L + V);
+    float diff = max(dot(N, L), 0.0);
+    // no highlight on faces turned away from the light
+    float spec = diff > 0.0 ? pow(max(dot(N, H), 0.0), shininess) : 0.0;
+    return diffuse * diff * albedo + specular * spec * specularColor;
+}
+
+float shadow(vec3 N, vec3 L)
+{
+    vec4 lightSpacePos = lightSpaceMatrix * vec4(FragPos, 1.0);
+    vec3 coords = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+    if (coords.z > 1.0)
+        return 0.0;
+
+    float cosTheta = clamp(dot(N, L), 0.0, 1.0);
+    float tanTheta = sqrt(1.0 - cosTheta * cosTheta) / max(cosTheta, 0.05);
+    float bias = min(shadowBias + shadowSlopeBias * tanTheta, 0.01);
+
+    vec2 texelSize = 1.0 / vec2(textureSize(shadowMap, 0));
+    float blocked = 0.0;
+    for (int x = -shadowPcfRadius; x <= shadowPcfRadius; x++) {
+        for (int y = -shadowPcfRadius; y <= shadowPcfRadius; y++) {
+            float depth = texture(shadowMap, coords.xy + vec2(x, y) * texelSize).r;
+            blocked += coords.z - bias > depth ? 1.0 : 0.0;
+        }
+    }
+    float samples = float((2 * shadowPcfRadius + 1) * (2 * shadowPcfRadius + 1));
+    return blocked / samples;
+}
+
+// The view depth is in the G-buffer already
+int cascadeIndex()
+{
+    for (int i = 0; i < cascadeCount - 1; i++) {
+        if (viewDepth < cascadeSplits[i])
+            return i;
+    }
+    return cascadeCount - 1;
+}
+
+float cascadeShadow(int cascade, vec3 N, vec3 L)
+{
+    vec4 lightSpacePos = cascadeLightSpaces[cascade] * vec4(FragPos, 1.0);
+    vec3 coords = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+    if (coords.z > 1.0)
+        return 0.0;
+
+    float cosTheta = clamp(dot(N, L), 0.0, 1.0);
+    float tanTheta = sqrt(1.0 - cosTheta * cosTheta) / max(cosTheta, 0.05);
+    float bias = min(cascadeBias + cascadeSlopeBias * tanTheta, 0.01);
+
+    vec2 texelSize = 1.0 / vec2(textureSize(cascadeShadowMap, 0).xy);
+    float blocked = 0.0;
+    for (int x = -cascadePcfRadius; x <= cascadePcfRadius; x++) {
+        for (int y = -cascadePcfRadius; y <= cascadePcfRadius; y++) {
+            vec2 offset = vec2(x, y) * texelSize;
+            float depth = texture(cascadeShadowMap, vec3(coords.xy + offset, cascade)).r;
+            blocked += coords.z - bias > depth ? 1.0 : 0.0;
+        }
+    }
+    float samples = float((2 * cascadePcfRadius + 1) * (2 * cascadePcfRadius + 1));
+    return blocked / samples;
+}
+
+const vec3 cascadeColors[MAX_CASCADES] = vec3[](
+    vec3(1.0, 0.3, 0.3), vec3(0.3, 1.0, 0.3), vec3(0.3, 0.3, 1.0), vec3(1.0, 1.0, 0.3)
+);
+
+void main()
+{
+    vec4 position = texture(gPosition, TexCoords);
+    // nothing was drawn there, the clear color stays
+    if (position.w == 0.0)
+        discard;
+
+    FragPos = position.xyz;
+    viewDepth = position.w;
+    vec3 albedo = texture(gAlbedo, TexCoords).rgb;
+    vec4 normal = texture(gNormal, TexCoords);
+    if (normal.w == 0.0) {
+        FragColor = vec4(albedo, 1.0);
+        return;
+    }
+
+    vec4 material = texture(gMaterial, TexCoords);
+    vec3 specularColor = material.rgb;
+    shininess = material.a;
+    vec4 baked = texture(gAmbient, TexCoords);
+
+    vec3 N = normalize(normal.xyz);
+    vec3 V = normalize(viewPos - FragPos);
+
+    vec3 lit = vec3(0.0);
+    vec3 ambient = vec3(0.0);
+
+    for (int i = 0; i < min(dirLightCount, MAX_DIR_LIGHTS); i++) {
+        vec3 L = normalize(-dirLights[i].direction);
+        float visibility = 1.0;
+        if (i == 0 && cascadesEnabled)
+            visibility = 1.0 - cascadeShadow(cascadeIndex(), N, L);
+        else if (i == 0 && shadowsEnabled)
+            visibility = 1.0 - shadow(N, L);
+        lit += visibility * blinnPhong(N, V, L, dirLights[i].diffuse, dirLights[i].specular, albedo, specularColor);
+        ambient += dirLights[i].ambient;
+    }
+
+    for (int i = 0; i < min(spotLightCount, MAX_SPOT_LIGHTS); i++) {
+        vec3 toLight = spotLights[i].position - FragPos;
+        float distance = length(toLight);
+        vec3 L = toLight / distance;
+        float attenuation = 1.0 / (spotLights[i].constant + spotLights[i].linear * distance
+            + spotLights[i].quadratic * distance * distance);
+
+        // soft edge between the inner and the outer cone
+        float theta = dot(L, normalize(-spotLights[i].direction));
+        float epsilon = max(spotLights[i].cutOff - spotLights[i].outerCutOff, 0.0001);
+        float intensity = clamp((theta - spotLights[i].outerCutOff) / epsilon, 0.0, 1.0);
+
+        lit += attenuation * intensity * blinnPhong(N, V, L, spotLights[i].diffuse,
+            spotLights[i].specular, albedo, specularColor);
+        ambient += spotLights[i].ambient * attenuation;
+    }
+
+    // Baked lighting replaces the ambient term of the lights
+    if (baked.a > 0.0)
+        ambient = baked.rgb;
+
+    vec3 result = lit + ambient * albedo;
+    if (cascadesEnabled && cascadeDebug)
+        result *= cascadeColors[cascadeIndex()];
+
+    FragColor = vec4(result, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,58 @@
+#version 330 core
+layout (location = 0) out vec4 gAlbedo;
+layout (location = 1) out vec4 gNormal;
+layout (location = 2) out vec4 gPosition;
+layout (location = 3) out vec4 gMaterial;
+layout (location = 4) out vec4 gAmbient;
+
+in vec2 TexCoords;
+in vec2 TexCoords2;
+in vec4 VertexColor;
+in vec4 InstanceColor;
+in vec3 FragPos;
+in vec3 Normal;
+in mat3 TBN;
+
+struct Material {
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+    float shininess;
+    float dissolve;
+    int illum;
+    bool hasLightmap;
+    bool hasSpecularMap;
+    bool hasNormalMap;
+};
+
+uniform Material material;
+uniform sampler2D texture_diffuse1;
+uniform sampler2D texture_specular1;
+uniform sampler2D texture_lightmap1;
+uniform sampler2D texture_normal1;
+
+uniform mat4 view;
+// for surfaces showing an image as is, like portals
+uniform bool unlit;
+
+// Same surface as model_loading.fs, stored for the lighting passes instead of being lit
+void main()
+{
+    vec4 color = InstanceColor * VertexColor * vec4(material.diffuse, material.dissolve) * texture(texture_diffuse1, TexCoords);
+    vec3 specularColor = material.specular;
+    if (material.hasSpecularMap)
+        specularColor *= texture(texture_specular1, TexCoords).rgb;
+
+    vec3 normal = material.hasNormalMap
+        ? normalize(TBN * (texture(texture_normal1, TexCoords).rgb * 2.0 - 1.0))
+        : normalize(Normal);
+
+    gAlbedo = vec4(color.rgb, 1.0);
+    gNormal = vec4(normal, unlit ? 0.0 : 1.0);
+    // the view depth is never zero in front of the camera, the lighting passes skip those pixels
+    gPosition = vec4(FragPos, -(view * vec4(FragPos, 1.0)).z);
+    gMaterial = vec4(specularColor, max(material.shininess, 1.0));
+    gAmbient = material.hasLightmap
+        ? vec4(texture(texture_lightmap1, TexCoords2).rgb, 1.0)
+        : vec4(0.0);
+}
\ No newline at end of file
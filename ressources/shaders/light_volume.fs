@@ -0,0 +1,123 @@
+#version 330 core
+out vec4 FragColor;
+
+#define MAX_POINT_SHADOWS 4
+
+struct PointLight {
+    vec3 position;
+    float constant;
+    float linear;
+    float quadratic;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+    // index in pointShadowMaps, -1 without shadows
+    int shadowSlot;
+};
+
+// written by gbuffer.fs
+uniform sampler2D gAlbedo;
+uniform sampler2D gNormal;
+uniform sampler2D gPosition;
+uniform sampler2D gMaterial;
+uniform sampler2D gAmbient;
+uniform vec2 screenSize;
+
+// the light whose volume is being drawn, added to what is already on screen
+uniform PointLight light;
+uniform float lightRadius;
+
+// distance to the point lights over pointShadowFarPlane
+uniform samplerCube pointShadowMaps[MAX_POINT_SHADOWS];
+uniform float pointShadowFarPlane;
+uniform float pointShadowBias;
+
+uniform vec3 viewPos;
+
+// read from the G-buffer before lighting
+vec3 FragPos;
+float shininess;
+
+vec3 blinnPhong(vec3 N, vec3 V, vec3 L, vec3 diffuse, vec3 specular, vec3 albedo, vec3 specularColor)
+{
+    vec3 H = normalize(L + V);
+    float diff = max(dot(N, L), 0.0);
+    // no highlight on faces turned away from the light
+    float spec = diff > 0.0 ? pow(max(dot(N, H), 0.0), shininess) : 0.0;
+    return diffuse * diff * albedo + specular * spec * specularColor;
+}
+
+// Sampler arrays can only be indexed with constants in GLSL 3.30
+float closestPointDepth(int slot, vec3 direction)
+{
+    if (slot == 0)
+        return texture(pointShadowMaps[0], direction).r;
+    if (slot == 1)
+        return texture(pointShadowMaps[1], direction).r;
+    if (slot == 2)
+        return texture(pointShadowMaps[2], direction).r;
+    return texture(pointShadowMaps[3], direction).r;
+}
+
+const vec3 pointShadowOffsets[20] = vec3[](
+    vec3(1, 1, 1), vec3(1, -1, 1), vec3(-1, -1, 1), vec3(-1, 1, 1),
+    vec3(1, 1, -1), vec3(1, -1, -1), vec3(-1, -1, -1), vec3(-1, 1, -1),
+    vec3(1, 1, 0), vec3(1, -1, 0), vec3(-1, -1, 0), vec3(-1, 1, 0),
+    vec3(1, 0, 1), vec3(-1, 0, 1), vec3(1, 0, -1), vec3(-1, 0, -1),
+    vec3(0, 1, 1), vec3(0, -1, 1), vec3(0, -1, -1), vec3(0, 1, -1)
+);
+
+float pointShadow(int slot, vec3 lightPos, vec3 N, vec3 L)
+{
+    vec3 fromLight = FragPos - lightPos;
+    float distance = length(fromLight);
+    float bias = pointShadowBias * (2.0 - clamp(dot(N, L), 0.0, 1.0));
+    // wider filtering further away from the viewer
+    float radius = (1.0 + length(viewPos - FragPos) / pointShadowFarPlane) / 50.0;
+
+    float blocked = 0.0;
+    for (int i = 0; i < 20; i++) {
+        float closest = closestPointDepth(slot, fromLight + pointShadowOffsets[i] * radius) * pointShadowFarPlane;
+        blocked += distance - bias > closest ? 1.0 : 0.0;
+    }
+    return blocked / 20.0;
+}
+
+void main()
+{
+    vec2 texCoords = gl_FragCoord.xy / screenSize;
+    vec4 position = texture(gPosition, texCoords);
+    vec4 normal = texture(gNormal, texCoords);
+    // the pixel is empty or unlit
+    if (position.w == 0.0 || normal.w == 0.0)
+        discard;
+
+    FragPos = position.xyz;
+    vec3 toLight = light.position - FragPos;
+    float distance = length(toLight);
+    // surfaces in front of the volume pass the depth test too
+    if (distance > lightRadius)
+        discard;
+
+    vec3 albedo = texture(gAlbedo, texCoords).rgb;
+    vec4 material = texture(gMaterial, texCoords);
+    shininess = material.a;
+    bool baked = texture(gAmbient, texCoords).a > 0.0;
+
+    vec3 N = normalize(normal.xyz);
+    vec3 V = normalize(viewPos - FragPos);
+    vec3 L = toLight / distance;
+    float attenuation = 1.0 / (light.constant + light.linear * distance
+        + light.quadratic * distance * distance);
+
+    float visibility = light.shadowSlot >= 0
+        ? 1.0 - pointShadow(light.shadowSlot, light.position, N, L)
+        : 1.0;
+    vec3 result = attenuation * visibility * blinnPhong(N, V, L, light.diffuse, light.specular,
+        albedo, material.rgb);
+    // baked lighting replaces the ambient term of the lights
+    if (!baked)
+        result += light.ambient * attenuation * albedo;
+
+    FragColor = vec4(result, 1.0);
+}
\ No newline at end of file
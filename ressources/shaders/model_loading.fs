@@ -1,11 +1,163 @@
-#version 330 core
-out vec4 FragColor;
-
-in vec2 TexCoords;
-
-uniform sampler2D texture_diffuse1;
-
-void main()
-{
-    FragColor = texture(texture_diffuse1, TexCoords);
-}
\ No newline at end of file
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+in vec2 TexCoords2;
+in vec4 VertexColor;
+in vec4 InstanceColor;
+in vec3 FragPos;
+in vec3 Normal;
+in mat3 TBN;
+
+#define MAX_DIR_LIGHTS 4
+#define MAX_POINT_LIGHTS 16
+#define MAX_SPOT_LIGHTS 8
+
+struct Material {
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+    float shininess;
+    float dissolve;
+    int illum;
+    bool hasLightmap;
+    bool hasSpecularMap;
+    bool hasNormalMap;
+};
+
+struct DirLight {
+    vec3 direction;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+struct PointLight {
+    vec3 position;
+    float constant;
+    float linear;
+    float quadratic;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+    // index in pointShadowMaps, -1 without shadows
+    int shadowSlot;
+};
+
+struct SpotLight {
+    vec3 position;
+    vec3 direction;
+    // cosines of the cone's angles
+    float cutOff;
+    float outerCutOff;
+    float constant;
+    float linear;
+    float quadratic;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+uniform Material material;
+uniform sampler2D texture_diffuse1;
+uniform sampler2D texture_specular1;
+uniform sampler2D texture_lightmap1;
+uniform sampler2D texture_normal1;
+
+uniform DirLight dirLights[MAX_DIR_LIGHTS];
+uniform int dirLightCount;
+uniform PointLight pointLights[MAX_POINT_LIGHTS];
+uniform int pointLightCount;
+uniform SpotLight spotLights[MAX_SPOT_LIGHTS];
+uniform int spotLightCount;
+
+uniform vec3 viewPos;
+// for surfaces showing an image as is, like portals
+uniform bool unlit;
+
+#include "shadows.glsl"
+
+// Blinn-Phong diffuse and specular terms of a single light direction
+vec3 blinnPhong(vec3 N, vec3 V, vec3 L, vec3 diffuse, vec3 specular, vec3 albedo, vec3 specularColor)
+{
+    vec3 H = normalize(L + V);
+    float diff = max(dot(N, L), 0.0);
+    // no highlight on faces turned away from the light
+    float spec = diff > 0.0 ? pow(max(dot(N, H), 0.0), max(material.shininess, 1.0)) : 0.0;
+    return diffuse * diff * albedo + specular * spec * specularColor;
+}
+
+void main()
+{
+    vec4 color = InstanceColor * VertexColor * vec4(material.diffuse, material.dissolve) * texture(texture_diffuse1, TexCoords);
+    if (unlit) {
+        FragColor = color;
+        return;
+    }
+
+    vec3 albedo = color.rgb;
+    vec3 specularColor = material.specular;
+    if (material.hasSpecularMap)
+        specularColor *= texture(texture_specular1, TexCoords).rgb;
+
+    vec3 N = material.hasNormalMap
+        ? normalize(TBN * (texture(texture_normal1, TexCoords).rgb * 2.0 - 1.0))
+        : normalize(Normal);
+    vec3 V = normalize(viewPos - FragPos);
+
+    vec3 lit = vec3(0.0);
+    vec3 ambient = vec3(0.0);
+
+    for (int i = 0; i < min(dirLightCount, MAX_DIR_LIGHTS); i++) {
+        vec3 L = normalize(-dirLights[i].direction);
+        float visibility = 1.0;
+        if (i == 0 && cascadesEnabled)
+            visibility = 1.0 - cascadeShadow(cascadeIndex(), N, L);
+        else if (i == 0 && shadowsEnabled)
+            visibility = 1.0 - shadow(N, L);
+        lit += visibility * blinnPhong(N, V, L, dirLights[i].diffuse, dirLights[i].specular, albedo, specularColor);
+        ambient += dirLights[i].ambient;
+    }
+
+    for (int i = 0; i < min(pointLightCount, MAX_POINT_LIGHTS); i++) {
+        vec3 toLight = pointLights[i].position - FragPos;
+        float distance = length(toLight);
+        float attenuation = 1.0 / (pointLights[i].constant + pointLights[i].linear * distance
+            + pointLights[i].quadratic * distance * distance);
+
+        vec3 L = toLight / distance;
+        float visibility = pointLights[i].shadowSlot >= 0
+            ? 1.0 - pointShadow(pointLights[i].shadowSlot, pointLights[i].position, N, L)
+            : 1.0;
+        lit += attenuation * visibility * blinnPhong(N, V, L, pointLights[i].diffuse,
+            pointLights[i].specular, albedo, specularColor);
+        ambient += pointLights[i].ambient * attenuation;
+    }
+
+    for (int i = 0; i < min(spotLightCount, MAX_SPOT_LIGHTS); i++) {
+        vec3 toLight = spotLights[i].position - FragPos;
+        float distance = length(toLight);
+        vec3 L = toLight / distance;
+        float attenuation = 1.0 / (spotLights[i].constant + spotLights[i].linear * distance
+            + spotLights[i].quadratic * distance * distance);
+
+        // soft edge between the inner and the outer cone
+        float theta = dot(L, normalize(-spotLights[i].direction));
+        float epsilon = max(spotLights[i].cutOff - spotLights[i].outerCutOff, 0.0001);
+        float intensity = clamp((theta - spotLights[i].outerCutOff) / epsilon, 0.0, 1.0);
+
+        lit += attenuation * intensity * blinnPhong(N, V, L, spotLights[i].diffuse,
+            spotLights[i].specular, albedo, specularColor);
+        ambient += spotLights[i].ambient * attenuation;
+    }
+
+    // Baked lighting replaces the ambient term of the lights
+    if (material.hasLightmap)
+        ambient = texture(texture_lightmap1, TexCoords2).rgb;
+
+    vec3 result = lit + ambient * albedo;
+    if (cascadesEnabled && cascadeDebug)
+        result *= cascadeColors[cascadeIndex()];
+
+    FragColor = vec4(result, color.a);
+}
@@ -1,16 +1,48 @@
-#version 330 core
-layout (location = 0) in vec3 aPos;
-layout (location = 1) in vec3 aNormal;
-layout (location = 2) in vec2 aTexCoords;
-
-out vec2 TexCoords;
-
-uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
-
-void main()
-{
-    TexCoords = aTexCoords;
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
-}
\ No newline at end of file
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec2 aTexCoords;
+layout (location = 3) in vec3 aTangent;
+layout (location = 4) in vec3 aBitangent;
+layout (location = 5) in vec4 aColor;
+layout (location = 6) in vec2 aTexCoords2;
+layout (location = 7) in vec4 aJoints;
+layout (location = 8) in vec4 aWeights;
+layout (location = 10) in mat4 aInstanceModel;
+layout (location = 14) in vec4 aInstanceColor;
+
+out vec2 TexCoords;
+out vec2 TexCoords2;
+out vec4 VertexColor;
+out vec4 InstanceColor;
+out vec3 FragPos;
+out vec3 Normal;
+out mat3 TBN;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+uniform bool instanced;
+
+#include "skinning.glsl"
+
+void main()
+{
+    mat4 modelMatrix = skin(instanced ? aInstanceModel : model);
+
+    TexCoords = aTexCoords;
+    TexCoords2 = aTexCoords2;
+    VertexColor = aColor;
+    InstanceColor = instanced ? aInstanceColor : vec4(1.0);
+    // lighting happens in world space
+    FragPos = vec3(modelMatrix * vec4(aPos, 1.0));
+    Normal = transpose(inverse(mat3(modelMatrix))) * aNormal;
+    // only used with a normal map, meshes without tangents don't have one
+    vec3 N = normalize(Normal);
+    vec3 T = normalize(mat3(modelMatrix) * aTangent);
+    // re-orthogonalize after a non uniform scale
+    T = normalize(T - dot(T, N) * N);
+    vec3 B = normalize(mat3(modelMatrix) * aBitangent);
+    TBN = mat3(T, B, N);
+    gl_Position = projection * view * vec4(FragPos, 1.0);
+}
@@ -0,0 +1,197 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+in vec2 TexCoords2;
+in vec4 VertexColor;
+in vec3 FragPos;
+in mat3 TBN;
+
+#define MAX_DIR_LIGHTS 4
+#define MAX_POINT_LIGHTS 16
+#define MAX_SPOT_LIGHTS 8
+
+const float PI = 3.14159265359;
+
+struct Material {
+    vec4 baseColor;
+    float metallic;
+    float roughness;
+    float occlusionStrength;
+    vec3 emissive;
+    bool hasLightmap;
+};
+
+struct DirLight {
+    vec3 direction;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+struct PointLight {
+    vec3 position;
+    float constant;
+    float linear;
+    float quadratic;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+    // index in pointShadowMaps, -1 without shadows
+    int shadowSlot;
+};
+
+struct SpotLight {
+    vec3 position;
+    vec3 direction;
+    // cosines of the cone's angles
+    float cutOff;
+    float outerCutOff;
+    float constant;
+    float linear;
+    float quadratic;
+    vec3 ambient;
+    vec3 diffuse;
+    vec3 specular;
+};
+
+uniform Material material;
+
+// base color
+uniform sampler2D texture_diffuse1;
+uniform sampler2D texture_normal1;
+uniform sampler2D texture_emission1;
+uniform sampler2D texture_metallic1;
+uniform sampler2D texture_roughness1;
+uniform sampler2D texture_metallic_roughness1;
+uniform sampler2D texture_occlusion1;
+uniform sampler2D texture_lightmap1;
+
+uniform DirLight dirLights[MAX_DIR_LIGHTS];
+uniform int dirLightCount;
+uniform PointLight pointLights[MAX_POINT_LIGHTS];
+uniform int pointLightCount;
+uniform SpotLight spotLights[MAX_SPOT_LIGHTS];
+uniform int spotLightCount;
+
+uniform vec3 viewPos;
+
+#include "shadows.glsl"
+
+// Trowbridge-Reitz GGX normal distribution
+float distributionGGX(vec3 N, vec3 H, float roughness)
+{
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float NdotH = max(dot(N, H), 0.0);
+    float denom = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+float geometrySchlickGGX(float NdotV, float roughness)
+{
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+    return NdotV / (NdotV * (1.0 - k) + k);
+}
+
+float geometrySmith(vec3 N, vec3 V, vec3 L, float roughness)
+{
+    return geometrySchlickGGX(max(dot(N, V), 0.0), roughness)
+        * geometrySchlickGGX(max(dot(N, L), 0.0), roughness);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 F0)
+{
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+// Cook-Torrance BRDF times the incoming radiance for a single light direction
+vec3 cookTorrance(vec3 N, vec3 V, vec3 L, vec3 radiance, vec3 albedo, float metallic, float roughness)
+{
+    vec3 H = normalize(V + L);
+    vec3 F0 = mix(vec3(0.04), albedo, metallic);
+
+    float NDF = distributionGGX(N, H, roughness);
+    float G = geometrySmith(N, V, L, roughness);
+    vec3 F = fresnelSchlick(max(dot(H, V), 0.0), F0);
+
+    vec3 specular = (NDF * G * F) / (4.0 * max(dot(N, V), 0.0) * max(dot(N, L), 0.0) + 0.0001);
+    vec3 kD = (vec3(1.0) - F) * (1.0 - metallic);
+
+    return (kD * albedo / PI + specular) * radiance * max(dot(N, L), 0.0);
+}
+
+void main()
+{
+    vec4 baseColor = VertexColor * material.baseColor * texture(texture_diffuse1, TexCoords);
+    vec3 albedo = baseColor.rgb;
+
+    vec4 metallicRoughness = texture(texture_metallic_roughness1, TexCoords);
+    float metallic = material.metallic * metallicRoughness.b * texture(texture_metallic1, TexCoords).r;
+    float roughness = material.roughness * metallicRoughness.g * texture(texture_roughness1, TexCoords).r;
+    roughness = clamp(roughness, 0.04, 1.0);
+
+    float occlusion = mix(1.0, texture(texture_occlusion1, TexCoords).r, material.occlusionStrength);
+    vec3 emissive = material.emissive * texture(texture_emission1, TexCoords).rgb;
+
+    vec3 N = normalize(TBN * (texture(texture_normal1, TexCoords).rgb * 2.0 - 1.0));
+    vec3 V = normalize(viewPos - FragPos);
+
+    vec3 color = vec3(0.0);
+    vec3 ambient = vec3(0.0);
+
+    for (int i = 0; i < min(dirLightCount, MAX_DIR_LIGHTS); i++) {
+        vec3 L = normalize(-dirLights[i].direction);
+        float visibility = 1.0;
+        if (i == 0 && cascadesEnabled)
+            visibility = 1.0 - cascadeShadow(cascadeIndex(), N, L);
+        else if (i == 0 && shadowsEnabled)
+            visibility = 1.0 - shadow(N, L);
+        color += visibility * cookTorrance(N, V, L, dirLights[i].diffuse, albedo, metallic, roughness);
+        ambient += dirLights[i].ambient;
+    }
+
+    for (int i = 0; i < min(pointLightCount, MAX_POINT_LIGHTS); i++) {
+        vec3 toLight = pointLights[i].position - FragPos;
+        float distance = length(toLight);
+        float attenuation = 1.0 / (pointLights[i].constant + pointLights[i].linear * distance
+            + pointLights[i].quadratic * distance * distance);
+
+        vec3 L = toLight / distance;
+        float visibility = pointLights[i].shadowSlot >= 0
+            ? 1.0 - pointShadow(pointLights[i].shadowSlot, pointLights[i].position, N, L)
+            : 1.0;
+        color += visibility * cookTorrance(N, V, L, pointLights[i].diffuse * attenuation, albedo, metallic, roughness);
+        ambient += pointLights[i].ambient * attenuation;
+    }
+
+    for (int i = 0; i < min(spotLightCount, MAX_SPOT_LIGHTS); i++) {
+        vec3 toLight = spotLights[i].position - FragPos;
+        float distance = length(toLight);
+        vec3 L = toLight / distance;
+        float attenuation = 1.0 / (spotLights[i].constant + spotLights[i].linear * distance
+            + spotLights[i].quadratic * distance * distance);
+
+        // soft edge between the inner and the outer cone
+        float theta = dot(L, normalize(-spotLights[i].direction));
+        float epsilon = max(spotLights[i].cutOff - spotLights[i].outerCutOff, 0.0001);
+        float intensity = clamp((theta - spotLights[i].outerCutOff) / epsilon, 0.0, 1.0);
+
+        color += cookTorrance(N, V, L, spotLights[i].diffuse * attenuation * intensity, albedo, metallic, roughness);
+        ambient += spotLights[i].ambient * attenuation;
+    }
+
+    // Baked lighting replaces the ambient term of the lights
+    if (material.hasLightmap)
+        ambient = texture(texture_lightmap1, TexCoords2).rgb;
+
+    color += ambient * albedo * occlusion + emissive;
+    if (cascadesEnabled && cascadeDebug)
+        color *= cascadeColors[cascadeIndex()];
+
+    // Reinhard tone mapping, the sRGB framebuffer does the gamma correction
+    color = color / (color + vec3(1.0));
+
+    FragColor = vec4(color, baseColor.a);
+}
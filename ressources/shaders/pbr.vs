@@ -0,0 +1,34 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec2 aTexCoords;
+layout (location = 3) in vec3 aTangent;
+layout (location = 4) in vec3 aBitangent;
+layout (location = 5) in vec4 aColor;
+layout (location = 6) in vec2 aTexCoords2;
+
+out vec2 TexCoords;
+out vec2 TexCoords2;
+out vec4 VertexColor;
+out vec3 FragPos;
+out mat3 TBN;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+void main()
+{
+    mat3 normalMatrix = transpose(inverse(mat3(model)));
+    vec3 N = normalize(normalMatrix * aNormal);
+    vec3 T = normalize(mat3(model) * aTangent);
+    T = normalize(T - dot(T, N) * N);
+    vec3 B = normalize(mat3(model) * aBitangent);
+
+    TBN = mat3(T, B, N);
+    TexCoords = aTexCoords;
+    TexCoords2 = aTexCoords2;
+    VertexColor = aColor;
+    FragPos = vec3(model * vec4(aPos, 1.0));
+    gl_Position = projection * view * vec4(FragPos, 1.0);
+}
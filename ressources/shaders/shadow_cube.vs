@@ -0,0 +1,18 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 7) in vec4 aJoints;
+layout (location = 8) in vec4 aWeights;
+layout (location = 10) in mat4 aInstanceModel;
+
+uniform mat4 model;
+uniform bool instanced;
+
+#include "skinning.glsl"
+
+void main()
+{
+    mat4 modelMatrix = skin(instanced ? aInstanceModel : model);
+
+    // the geometry shader projects the world position on each face
+    gl_Position = modelMatrix * vec4(aPos, 1.0);
+}
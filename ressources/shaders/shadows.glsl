@@ -0,0 +1,132 @@
+// Included by the lit fragment shaders, after FragPos and viewPos
+
+#define MAX_POINT_SHADOWS 4
+#define MAX_CASCADES 4
+
+// depth seen from the first directional light
+uniform bool shadowsEnabled;
+uniform sampler2D shadowMap;
+uniform mat4 lightSpaceMatrix;
+uniform float shadowBias;
+uniform float shadowSlopeBias;
+uniform int shadowPcfRadius;
+
+// cascades of the first directional light, used instead of shadowMap when enabled
+uniform bool cascadesEnabled;
+uniform bool cascadeDebug;
+uniform sampler2DArray cascadeShadowMap;
+uniform int cascadeCount;
+// view space distance where each cascade ends
+uniform float cascadeSplits[MAX_CASCADES];
+uniform mat4 cascadeLightSpaces[MAX_CASCADES];
+uniform float cascadeBias;
+uniform float cascadeSlopeBias;
+uniform int cascadePcfRadius;
+uniform mat4 view;
+
+// distance to the point lights over pointShadowFarPlane
+uniform samplerCube pointShadowMaps[MAX_POINT_SHADOWS];
+uniform float pointShadowFarPlane;
+uniform float pointShadowBias;
+
+// Share of the light blocked in front of the fragment, averaged over neighbouring texels
+float shadow(vec3 N, vec3 L)
+{
+    vec4 lightSpacePos = lightSpaceMatrix * vec4(FragPos, 1.0);
+    vec3 coords = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+    // past the far plane of the light, nothing can be in the way
+    if (coords.z > 1.0)
+        return 0.0;
+
+    // grazing surfaces need a larger offset to avoid shadow acne
+    float cosTheta = clamp(dot(N, L), 0.0, 1.0);
+    float tanTheta = sqrt(1.0 - cosTheta * cosTheta) / max(cosTheta, 0.05);
+    float bias = min(shadowBias + shadowSlopeBias * tanTheta, 0.01);
+
+    vec2 texelSize = 1.0 / vec2(textureSize(shadowMap, 0));
+    float blocked = 0.0;
+    for (int x = -shadowPcfRadius; x <= shadowPcfRadius; x++) {
+        for (int y = -shadowPcfRadius; y <= shadowPcfRadius; y++) {
+            float depth = texture(shadowMap, coords.xy + vec2(x, y) * texelSize).r;
+            blocked += coords.z - bias > depth ? 1.0 : 0.0;
+        }
+    }
+    float samples = float((2 * shadowPcfRadius + 1) * (2 * shadowPcfRadius + 1));
+    return blocked / samples;
+}
+
+// First cascade reaching past the fragment, the last one covers whatever is further
+int cascadeIndex()
+{
+    float depth = -(view * vec4(FragPos, 1.0)).z;
+    for (int i = 0; i < cascadeCount - 1; i++) {
+        if (depth < cascadeSplits[i])
+            return i;
+    }
+    return cascadeCount - 1;
+}
+
+float cascadeShadow(int cascade, vec3 N, vec3 L)
+{
+    vec4 lightSpacePos = cascadeLightSpaces[cascade] * vec4(FragPos, 1.0);
+    vec3 coords = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+    if (coords.z > 1.0)
+        return 0.0;
+
+    float cosTheta = clamp(dot(N, L), 0.0, 1.0);
+    float tanTheta = sqrt(1.0 - cosTheta * cosTheta) / max(cosTheta, 0.05);
+    float bias = min(cascadeBias + cascadeSlopeBias * tanTheta, 0.01);
+
+    vec2 texelSize = 1.0 / vec2(textureSize(cascadeShadowMap, 0).xy);
+    float blocked = 0.0;
+    for (int x = -cascadePcfRadius; x <= cascadePcfRadius; x++) {
+        for (int y = -cascadePcfRadius; y <= cascadePcfRadius; y++) {
+            vec2 offset = vec2(x, y) * texelSize;
+            float depth = texture(cascadeShadowMap, vec3(coords.xy + offset, cascade)).r;
+            blocked += coords.z - bias > depth ? 1.0 : 0.0;
+        }
+    }
+    float samples = float((2 * cascadePcfRadius + 1) * (2 * cascadePcfRadius + 1));
+    return blocked / samples;
+}
+
+const vec3 cascadeColors[MAX_CASCADES] = vec3[](
+    vec3(1.0, 0.3, 0.3), vec3(0.3, 1.0, 0.3), vec3(0.3, 0.3, 1.0), vec3(1.0, 1.0, 0.3)
+);
+
+// Sampler arrays can only be indexed with constants in GLSL 3.30
+float closestPointDepth(int slot, vec3 direction)
+{
+    if (slot == 0)
+        return texture(pointShadowMaps[0], direction).r;
+    if (slot == 1)
+        return texture(pointShadowMaps[1], direction).r;
+    if (slot == 2)
+        return texture(pointShadowMaps[2], direction).r;
+    return texture(pointShadowMaps[3], direction).r;
+}
+
+const vec3 pointShadowOffsets[20] = vec3[](
+    vec3(1, 1, 1), vec3(1, -1, 1), vec3(-1, -1, 1), vec3(-1, 1, 1),
+    vec3(1, 1, -1), vec3(1, -1, -1), vec3(-1, -1, -1), vec3(-1, 1, -1),
+    vec3(1, 1, 0), vec3(1, -1, 0), vec3(-1, -1, 0), vec3(-1, 1, 0),
+    vec3(1, 0, 1), vec3(-1, 0, 1), vec3(1, 0, -1), vec3(-1, 0, -1),
+    vec3(0, 1, 1), vec3(0, -1, 1), vec3(0, -1, -1), vec3(0, 1, -1)
+);
+
+// Share of a point light blocked in front of the fragment, comparing distances to the light
+float pointShadow(int slot, vec3 lightPos, vec3 N, vec3 L)
+{
+    vec3 fromLight = FragPos - lightPos;
+    float distance = length(fromLight);
+    float bias = pointShadowBias * (2.0 - clamp(dot(N, L), 0.0, 1.0));
+    // wider filtering further away from the viewer
+    float radius = (1.0 + length(viewPos - FragPos) / pointShadowFarPlane) / 50.0;
+
+    float blocked = 0.0;
+    for (int i = 0; i < 20; i++) {
+        float closest = closestPointDepth(slot, fromLight + pointShadowOffsets[i] * radius) * pointShadowFarPlane;
+        blocked += distance - bias > closest ? 1.0 : 0.0;
+    }
+    return blocked / 20.0;
+}
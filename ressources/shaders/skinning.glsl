@@ -0,0 +1,19 @@
+// Included by the vertex shaders of skinned models, after aJoints and aWeights
+
+#define MAX_JOINTS 128
+
+uniform bool skinned;
+uniform mat4 jointMatrices[MAX_JOINTS];
+
+// Applies the joints of the vertex to the model matrix. Vertices without weights belong to
+// meshes that aren't skinned and keep the model matrix.
+mat4 skin(mat4 modelMatrix)
+{
+    if (!skinned || aWeights == vec4(0.0)) {
+        return modelMatrix;
+    }
+    return modelMatrix * (aWeights.x * jointMatrices[int(aJoints.x)]
+        + aWeights.y * jointMatrices[int(aJoints.y)]
+        + aWeights.z * jointMatrices[int(aJoints.z)]
+        + aWeights.w * jointMatrices[int(aJoints.w)]);
+}
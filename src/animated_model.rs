@@ -0,0 +1,180 @@
+use glm::Mat4;
+
+use crate::{
+    draw::{Draw, DrawOptions},
+    keyframes::Interpolate,
+    model::Model,
+    shader_program::ShaderProgram,
+};
+
+#[derive(Clone, Copy)]
+struct Playback {
+    clip: usize,
+    time: f32,
+}
+
+/// Plays the animation clips of a skinned or morphed model. Shaders have to check the
+/// `skinned` uniform to move the vertices with the `jointMatrices` array, morph targets are
+/// applied to the vertex buffers directly.
+pub struct AnimatedModel {
+    model: Model,
+    current: Option<Playback>,
+    /// Clip fading out while `current` fades in
+    previous: Option<Playback>,
+    fade_duration: f32,
+    fade_elapsed: f32,
+    /// Multiplies the time given to `update`
+    pub speed: f32,
+    /// Empty without a skeleton
+    joint_matrices: Vec<Mat4>,
+    /// Morph target weights of each mesh when no clip animates them
+    rest_morph_weights: Vec<Vec<f32>>,
+}
+
+impl AnimatedModel {
+    pub fn new(model: Model) -> Self {
+        let joint_matrices = model
+            .skeleton
+            .as_ref()
+            .map(|skeleton| skeleton.joint_matrices(&skeleton.rest_pose()))
+            .unwrap_or_default();
+        let rest_morph_weights = model
+            .meshes
+            .iter()
+            .map(|mesh| mesh.morph_weights().to_vec())
+            .collect();
+
+        Self {
+            model,
+            current: None,
+            previous: None,
+            fade_duration: 0.0,
+            fade_elapsed: 0.0,
+            speed: 1.0,
+            joint_matrices,
+            rest_morph_weights,
+        }
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn clip_names(&self) -> Vec<&str> {
+        self.model
+            .animations
+            .iter()
+            .map(|clip| clip.name.as_str())
+            .collect()
+    }
+
+    fn clip_index(&self, name: &str) -> usize {
+        self.model
+            .animations
+            .iter()
+            .position(|clip| clip.name == name)
+            .unwrap_or_else(|| panic!("no animation named \"{}\"", name))
+    }
+
+    /// Starts `clip` from its beginning, replacing whatever was playing
+    pub fn play(&mut self, clip: &str) {
+        self.current = Some(Playback {
+            clip: self.clip_index(clip),
+            time: 0.0,
+        });
+        self.previous = None;
+    }
+
+    /// Starts `clip` and blends it over the current one during `duration` seconds
+    pub fn crossfade(&mut self, clip: &str, duration: f32) {
+        self.previous = self.current;
+        self.current = Some(Playback {
+            clip: self.clip_index(clip),
+            time: 0.0,
+        });
+        self.fade_duration = duration;
+        self.fade_elapsed = 0.0;
+    }
+
+    /// Advances the clips by `delta` seconds, looping them, and evaluates the pose and the
+    /// morph target weights
+    pub fn update(&mut self, delta: f32) {
+        let delta = delta * self.speed;
+        let animations = &self.model.animations;
+        let advance = |playback: &mut Playback| {
+            let duration = animations[playback.clip].duration();
+            playback.time += delta;
+            if duration > 0.0 {
+                playback.time %= duration;
+            }
+        };
+
+        if let Some(current) = &mut self.current {
+            advance(current);
+        }
+        if let Some(previous) = &mut self.previous {
+            advance(previous);
+            self.fade_elapsed += delta;
+            if self.fade_elapsed >= self.fade_duration {
+                self.previous = None;
+            }
+        }
+
+        let fade = self.fade_elapsed / self.fade_duration;
+
+        if let Some(skeleton) = &self.model.skeleton {
+            let sample =
+                |playback: &Playback| animations[playback.clip].sample(skeleton, playback.time);
+            let pose = match (&self.previous, &self.current) {
+                (Some(previous), Some(current)) => sample(previous).blend(&sample(current), fade),
+                (None, Some(current)) => sample(current),
+                _ => skeleton.rest_pose(),
+            };
+
+            self.joint_matrices = skeleton.joint_matrices(&pose);
+        }
+
+        let sample_weights = |playback: &Playback| {
+            let mut weights = self.rest_morph_weights.clone();
+            for (mesh, mesh_weights) in
+                animations[playback.clip].sample_morph_weights(playback.time)
+            {
+                weights[mesh] = mesh_weights;
+            }
+            weights
+        };
+        let weights = match (&self.previous, &self.current) {
+            (Some(previous), Some(current)) => sample_weights(previous)
+                .iter()
+                .zip(sample_weights(current))
+                .map(|(a, b)| Interpolate::lerp(a, &b, fade))
+                .collect(),
+            (None, Some(current)) => sample_weights(current),
+            _ => self.rest_morph_weights.clone(),
+        };
+
+        for (mesh, weights) in self.model.meshes.iter_mut().zip(weights) {
+            if !mesh.morph_targets().is_empty() {
+                mesh.set_morph_weights(&weights);
+            }
+        }
+    }
+}
+
+impl Draw for AnimatedModel {
+    fn draw(&self, shader: &ShaderProgram) {
+        self.draw_with(shader, &DrawOptions::default());
+    }
+
+    fn draw_with(&self, shader: &ShaderProgram, options: &DrawOptions) {
+        if self.joint_matrices.is_empty() {
+            self.model.draw_with(shader, options);
+            return;
+        }
+
+        shader.set_int("skinned", 1);
+        shader.set_mat4_array("jointMatrices", &self.joint_matrices);
+        self.model.draw_with(shader, options);
+        shader.set_int("skinned", 0);
+    }
+}
@@ -0,0 +1,78 @@
+use glm::{Qua, TVec3};
+
+use crate::{
+    keyframes::Keyframes,
+    skeleton::{Pose, Skeleton},
+};
+
+/// Animated properties of a joint, the ones left out keep their rest value
+pub struct JointChannel {
+    pub joint: usize,
+    pub translation: Option<Keyframes<TVec3<f32>>>,
+    pub rotation: Option<Keyframes<Qua<f32>>>,
+    pub scale: Option<Keyframes<TVec3<f32>>>,
+}
+
+/// Weights of the morph targets of the meshes made from a single source mesh
+pub struct MorphChannel {
+    pub meshes: Vec<usize>,
+    pub weights: Keyframes<Vec<f32>>,
+}
+
+pub struct AnimationClip {
+    pub name: String,
+    pub channels: Vec<JointChannel>,
+    pub morph_channels: Vec<MorphChannel>,
+}
+
+impl AnimationClip {
+    /// Time of the last keyframe of any channel
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .flat_map(|c| {
+                [
+                    c.translation.as_ref().map(Keyframes::duration),
+                    c.rotation.as_ref().map(Keyframes::duration),
+                    c.scale.as_ref().map(Keyframes::duration),
+                ]
+            })
+            .flatten()
+            .chain(self.morph_channels.iter().map(|c| c.weights.duration()))
+            .fold(0.0, f32::max)
+    }
+
+    /// Pose of the skeleton `time` seconds into the clip
+    pub fn sample(&self, skeleton: &Skeleton, time: f32) -> Pose {
+        let mut pose = skeleton.rest_pose();
+
+        for channel in &self.channels {
+            let joint = &mut pose.joints[channel.joint];
+            if let Some(translation) = &channel.translation {
+                joint.translation = translation.sample(time);
+            }
+            if let Some(rotation) = &channel.rotation {
+                joint.rotation = rotation.sample(time);
+            }
+            if let Some(scale) = &channel.scale {
+                joint.scale = scale.sample(time);
+            }
+        }
+
+        pose
+    }
+
+    /// Morph target weights of every animated mesh `time` seconds into the clip
+    pub fn sample_morph_weights(&self, time: f32) -> Vec<(usize, Vec<f32>)> {
+        self.morph_channels
+            .iter()
+            .flat_map(|channel| {
+                let weights = channel.weights.sample(time);
+                channel
+                    .meshes
+                    .iter()
+                    .map(move |&mesh| (mesh, weights.clone()))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,237 @@
+use glm::{vec3, vec4, Mat4, TVec3};
+
+/// Axis-aligned bounding box, empty boxes have `min > max`
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: TVec3<f32>,
+    pub max: TVec3<f32>,
+}
+
+impl Aabb {
+    pub fn empty() -> Self {
+        Self {
+            min: vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: vec3(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a TVec3<f32>>) -> Self {
+        points.into_iter().fold(Aabb::empty(), |aabb, p| Self {
+            min: glm::min2(&aabb.min, p),
+            max: glm::max2(&aabb.max, p),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self {
+            min: glm::min2(&self.min, &other.min),
+            max: glm::max2(&self.max, &other.max),
+        }
+    }
+
+    pub fn center(&self) -> TVec3<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Half the size of the box along each axis
+    pub fn half_extents(&self) -> TVec3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    pub fn corners(&self) -> [TVec3<f32>; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            vec3(a.x, a.y, a.z),
+            vec3(b.x, a.y, a.z),
+            vec3(a.x, b.y, a.z),
+            vec3(b.x, b.y, a.z),
+            vec3(a.x, a.y, b.z),
+            vec3(b.x, a.y, b.z),
+            vec3(a.x, b.y, b.z),
+            vec3(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Smallest axis-aligned box containing this box once transformed
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        self.to_obb(matrix).to_aabb()
+    }
+
+    /// Exact bounds of this box once transformed, as long as the matrix doesn't shear
+    pub fn to_obb(self, matrix: &Mat4) -> Obb {
+        let half_extents = self.half_extents();
+        let mut axes = [vec3(0.0, 0.0, 0.0); 3];
+        let mut scaled_extents = vec3(0.0, 0.0, 0.0);
+
+        for i in 0..3 {
+            let column: TVec3<f32> = matrix.column(i).xyz();
+            let length = column.norm();
+            axes[i] = if length > 0.0 {
+                column / length
+            } else {
+                vec3(0.0, 0.0, 0.0)
+            };
+            scaled_extents[i] = half_extents[i] * length;
+        }
+
+        let center = self.center();
+        Obb {
+            center: (matrix * vec4(center.x, center.y, center.z, 1.0)).xyz(),
+            axes,
+            half_extents: scaled_extents,
+        }
+    }
+}
+
+/// Oriented bounding box, `axes` are unit vectors
+#[derive(Clone, Copy, Debug)]
+pub struct Obb {
+    pub center: TVec3<f32>,
+    pub axes: [TVec3<f32>; 3],
+    pub half_extents: TVec3<f32>,
+}
+
+impl Obb {
+    pub fn to_aabb(self) -> Aabb {
+        let extent = self
+            .axes
+            .iter()
+            .zip(self.half_extents.iter())
+            .fold(vec3(0.0, 0.0, 0.0), |sum, (axis, half)| {
+                sum + glm::abs(axis) * *half
+            });
+
+        Aabb {
+            min: self.center - extent,
+            max: self.center + extent,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BoundingSphere {
+    pub center: TVec3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Centered on the bounding box of the points, which is not the tightest sphere but stays
+    /// close for usual meshes
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a TVec3<f32>> + Clone) -> Self {
+        let aabb = Aabb::from_points(points.clone());
+        if aabb.is_empty() {
+            return Self {
+                center: vec3(0.0, 0.0, 0.0),
+                radius: 0.0,
+            };
+        }
+
+        let center = aabb.center();
+        let radius = points
+            .into_iter()
+            .map(|p| glm::distance(&center, p))
+            .fold(0.0, f32::max);
+
+        Self { center, radius }
+    }
+
+    pub fn union(&self, other: &BoundingSphere) -> BoundingSphere {
+        let offset = other.center - self.center;
+        let distance = offset.norm();
+
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+
+        let radius = (distance + self.radius + other.radius) / 2.0;
+        Self {
+            center: self.center + offset * ((radius - self.radius) / distance),
+            radius,
+        }
+    }
+
+    /// The radius grows with the largest scale factor of the matrix
+    pub fn transformed(&self, matrix: &Mat4) -> BoundingSphere {
+        let scale = (0..3)
+            .map(|i| matrix.column(i).xyz().norm())
+            .fold(0.0, f32::max);
+        let c = self.center;
+
+        Self {
+            center: (matrix * vec4(c.x, c.y, c.z, 1.0)).xyz(),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &TVec3<f32>, b: &TVec3<f32>) {
+        assert!((a - b).norm() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn aabb_from_points() {
+        let points = [
+            vec3(1.0, -2.0, 0.5),
+            vec3(-1.0, 3.0, 0.0),
+            vec3(0.0, 0.0, 2.0),
+        ];
+        let aabb = Aabb::from_points(&points);
+
+        assert_close(&aabb.min, &vec3(-1.0, -2.0, 0.0));
+        assert_close(&aabb.max, &vec3(1.0, 3.0, 2.0));
+        assert!(Aabb::from_points(&[]).is_empty());
+    }
+
+    #[test]
+    fn rotated_aabb_swaps_its_extents() {
+        let aabb = Aabb {
+            min: vec3(-1.0, -0.5, -2.0),
+            max: vec3(1.0, 0.5, 2.0),
+        };
+        let matrix = glm::translation(&vec3(3.0, 0.0, 0.0))
+            * glm::rotation(std::f32::consts::FRAC_PI_2, &vec3(0.0, 1.0, 0.0));
+
+        let transformed = aabb.transformed(&matrix);
+        assert_close(&transformed.min, &vec3(1.0, -0.5, -1.0));
+        assert_close(&transformed.max, &vec3(5.0, 0.5, 1.0));
+        assert!(Aabb::empty().transformed(&matrix).is_empty());
+    }
+
+    #[test]
+    fn sphere_union_contains_both() {
+        let a = BoundingSphere {
+            center: vec3(0.0, 0.0, 0.0),
+            radius: 1.0,
+        };
+        let b = BoundingSphere {
+            center: vec3(4.0, 0.0, 0.0),
+            radius: 2.0,
+        };
+
+        let union = a.union(&b);
+        assert_close(&union.center, &vec3(2.5, 0.0, 0.0));
+        assert!((union.radius - 3.5).abs() < 1e-5);
+
+        // a sphere already inside the other one doesn't grow it
+        let inner = BoundingSphere {
+            center: vec3(4.5, 0.0, 0.0),
+            radius: 0.5,
+        };
+        assert_close(&b.union(&inner).center, &b.center);
+        assert_eq!(b.union(&inner).radius, b.radius);
+    }
+}
@@ -0,0 +1,760 @@
+use {
+    glm::{TVec3, TVec4},
+    memmap2::Mmap,
+    std::{
+        borrow::Cow,
+        fs::{self, File},
+        io::{self, ErrorKind},
+        mem::{size_of, size_of_val},
+        path::{Path, PathBuf},
+        slice,
+    },
+};
+
+use crate::{
+    bounds::{Aabb, BoundingSphere},
+    import_options::ImportOptions,
+    material::{Material, PbrParameters},
+    mesh::{Lod, Mesh, Vertex},
+    model::Model,
+    texture::{Texture2D, TextureType},
+};
+
+// Cooked models are written next to their source as `<source>.<options hash>.cooked`, so that
+// loading a model with different import options doesn't replace the file of the others.
+// Everything is little endian, strings are a u32 length followed by UTF-8 bytes.
+//
+// header:   magic, version (u32), source checksum (u64), payload checksum (u64)
+// payload:  vertex layout, material count (u32), materials, mesh count (u32), meshes
+// layout:   vertex size (u32), attribute count (u32), (location, components, offset) as u32s
+// material: name, ambient, diffuse, specular, shininess, dissolve, illumination model (u8),
+//           PBR flag (u8) followed by base color, metallic, roughness, occlusion strength and
+//           emissive when set, texture count (u32), (type (u8), path) per texture
+// mesh:     material index (u32), AABB min and max, bounding sphere center and radius,
+//           LOD count (u32), (offset, count, ratio) per LOD, vertex count (u32),
+//           index count (u32), vertices as the floats of their in-memory layout, indices
+//           as u32s
+
+const MAGIC: &[u8; 4] = b"CKMD";
+/// Bumped on every change of the format
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 24;
+
+pub fn cooked_path(source: &Path, options: &ImportOptions) -> PathBuf {
+    let mut path = source.as_os_str().to_owned();
+    path.push(format!(".{:016x}.cooked", options_hash(options)));
+    PathBuf::from(path)
+}
+
+/// Hashes the options that change the import result
+fn options_hash(options: &ImportOptions) -> u64 {
+    let mut hash = fnv1a(FNV_OFFSET_BASIS, &options.crease_angle.to_le_bytes());
+    for ratio in &options.lod_ratios {
+        hash = fnv1a(hash, &ratio.to_le_bytes());
+    }
+    fnv1a(hash, &[options.optimize as u8])
+}
+
+/// Hashes the source file, the material libraries and buffers next to it, and the options
+/// that change the import result
+pub fn source_checksum(source: &Path, options: &ImportOptions) -> io::Result<u64> {
+    let mut hash = fnv1a(FNV_OFFSET_BASIS, &fs::read(source)?);
+
+    let directory = source.parent().unwrap_or(Path::new(""));
+    let mut companions: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("mtl") | Some("bin")
+            )
+        })
+        .collect();
+    companions.sort();
+    for path in companions {
+        hash = fnv1a(hash, &fs::read(path)?);
+    }
+
+    Ok(fnv1a(hash, &options_hash(options).to_le_bytes()))
+}
+
+/// Cooks every model found under `root`, returns how many were cooked
+pub fn cook_tree(root: &Path, options: &ImportOptions) -> io::Result<usize> {
+    let mut cooked = 0;
+
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            cooked += cook_tree(&path, options)?;
+            continue;
+        }
+
+        if let Some("obj") | Some("gltf") | Some("glb") = path.extension().and_then(|e| e.to_str())
+        {
+            let model = Model::import(&path, options);
+            let checksum = source_checksum(&path, options)?;
+            match model.write_cooked(&cooked_path(&path, options), checksum) {
+                Ok(()) => {
+                    println!("Cooked \"{}\"", path.to_str().unwrap());
+                    cooked += 1;
+                }
+                Err(e) => println!(
+                    "Warning: couldn't cook \"{}\" ({})",
+                    path.to_str().unwrap(),
+                    e
+                ),
+            }
+        }
+    }
+
+    Ok(cooked)
+}
+
+impl Model {
+    pub fn write_cooked(&self, path: &Path, source_checksum: u64) -> io::Result<()> {
+        if self.skeleton.is_some() || self.meshes.iter().any(|m| !m.morph_targets().is_empty()) {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "animated models can't be cooked",
+            ));
+        }
+
+        let meshes: Vec<CookedMesh> = self.meshes.iter().map(CookedMesh::from).collect();
+        fs::write(
+            path,
+            encode(&meshes, Path::new(&self.directory), source_checksum)?,
+        )
+    }
+
+    /// Fails when the file is stale, corrupted or from another version of the format
+    pub fn read_cooked(path: &Path, source_checksum: u64) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safe as long as nothing truncates the file while it's mapped
+        let bytes = unsafe { Mmap::map(&file)? };
+
+        let mut model = Model {
+            directory: path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_str()
+                .unwrap()
+                .into(),
+            ..Default::default()
+        };
+
+        let meshes = decode(&bytes, source_checksum, &mut |path, ty| {
+            model.cooked_texture(path, ty)
+        })?;
+        model.meshes = meshes
+            .into_iter()
+            .map(|mesh| {
+                Mesh::from_parts(
+                    mesh.vertices.into_owned(),
+                    mesh.indices.into_owned(),
+                    mesh.lods.into_owned(),
+                    mesh.material.into_owned(),
+                    mesh.aabb,
+                    mesh.bounding_sphere,
+                )
+            })
+            .collect();
+
+        Ok(model)
+    }
+
+    /// Reloads a texture from the path it was cached under
+    fn cooked_texture(&mut self, path: &str, ty: TextureType) -> io::Result<Texture2D> {
+        if let Some(color) = path.strip_prefix("#color") {
+            let color: Vec<u8> = color
+                .trim_matches(|c| c == '[' || c == ']')
+                .split(", ")
+                .filter_map(|c| c.parse().ok())
+                .collect();
+            let color: [u8; 4] = color
+                .try_into()
+                .map_err(|_| invalid_data("invalid color texture"))?;
+            Ok(self.color_texture(color, ty))
+        } else if path.starts_with('#') {
+            Err(invalid_data("embedded texture"))
+        } else if !Path::new(&self.directory).join(path).is_file() {
+            Err(invalid_data("missing texture"))
+        } else {
+            Ok(self.load_material(path, ty))
+        }
+    }
+}
+
+/// What gets cooked of a mesh, borrowed from the mesh when writing and owned when reading
+struct CookedMesh<'a> {
+    vertices: Cow<'a, [Vertex]>,
+    /// Indices of every level of detail, one after the other
+    indices: Cow<'a, [u32]>,
+    lods: Cow<'a, [Lod]>,
+    material: Cow<'a, Material>,
+    aabb: Aabb,
+    bounding_sphere: BoundingSphere,
+}
+
+impl<'a> From<&'a Mesh> for CookedMesh<'a> {
+    fn from(mesh: &'a Mesh) -> Self {
+        let (indices, lods) = mesh.index_buffer();
+        Self {
+            vertices: Cow::Borrowed(mesh.vertices()),
+            indices: Cow::Borrowed(indices),
+            lods: Cow::Borrowed(lods),
+            material: Cow::Borrowed(&mesh.material),
+            aabb: mesh.aabb,
+            bounding_sphere: mesh.bounding_sphere,
+        }
+    }
+}
+
+/// Lays out the whole file, textures are stored relative to `directory`
+fn encode(meshes: &[CookedMesh], directory: &Path, source_checksum: u64) -> io::Result<Vec<u8>> {
+    let mut payload = Writer::default();
+
+    let attributes = Vertex::attributes();
+    payload.u32(size_of::<Vertex>() as u32);
+    payload.u32(attributes.len() as u32);
+    for (location, components, offset) in attributes {
+        payload.u32(location);
+        payload.u32(components as u32);
+        payload.u32(offset as u32);
+    }
+
+    // Meshes sharing a material in the source end up with identical copies of it
+    let mut materials: Vec<Vec<u8>> = Vec::new();
+    let mut material_indices = Vec::with_capacity(meshes.len());
+    for mesh in meshes {
+        let mut material = Writer::default();
+        material.material(&mesh.material, directory)?;
+
+        let index = match materials.iter().position(|m| *m == material.bytes) {
+            Some(index) => index,
+            None => {
+                materials.push(material.bytes);
+                materials.len() - 1
+            }
+        };
+        material_indices.push(index as u32);
+    }
+
+    payload.u32(materials.len() as u32);
+    for material in &materials {
+        payload.bytes.extend_from_slice(material);
+    }
+
+    payload.u32(meshes.len() as u32);
+    for (mesh, material_index) in meshes.iter().zip(material_indices) {
+        payload.mesh(mesh, material_index);
+    }
+
+    let mut file = Vec::with_capacity(HEADER_SIZE + payload.bytes.len());
+    file.extend_from_slice(MAGIC);
+    file.extend_from_slice(&VERSION.to_le_bytes());
+    file.extend_from_slice(&source_checksum.to_le_bytes());
+    file.extend_from_slice(&fnv1a(FNV_OFFSET_BASIS, &payload.bytes).to_le_bytes());
+    file.extend_from_slice(&payload.bytes);
+
+    Ok(file)
+}
+
+/// Reads back the meshes of `encode`, the textures being loaded by `load_texture` from their
+/// stored path
+fn decode(
+    bytes: &[u8],
+    source_checksum: u64,
+    load_texture: &mut dyn FnMut(&str, TextureType) -> io::Result<Texture2D>,
+) -> io::Result<Vec<CookedMesh<'static>>> {
+    if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC {
+        return Err(invalid_data("not a cooked model"));
+    }
+    let mut header = Reader::new(&bytes[4..HEADER_SIZE]);
+    if header.u32()? != VERSION {
+        return Err(invalid_data("written by another version"));
+    }
+    if header.u64()? != source_checksum {
+        return Err(invalid_data("the source changed since it was cooked"));
+    }
+    let payload = &bytes[HEADER_SIZE..];
+    if header.u64()? != fnv1a(FNV_OFFSET_BASIS, payload) {
+        return Err(invalid_data("corrupted file"));
+    }
+
+    let mut reader = Reader::new(payload);
+
+    let attributes = Vertex::attributes();
+    let vertex_size = reader.u32()? as usize;
+    let layout = (0..reader.u32()?)
+        .map(|_| Ok((reader.u32()?, reader.u32()? as i32, reader.u32()? as usize)))
+        .collect::<io::Result<Vec<_>>>()?;
+    if vertex_size != size_of::<Vertex>() || layout != attributes {
+        return Err(invalid_data("vertex layout changed"));
+    }
+
+    let materials = (0..reader.u32()?)
+        .map(|_| reader.material(load_texture))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    (0..reader.u32()?)
+        .map(|_| reader.mesh(&materials))
+        .collect()
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn floats(&mut self, v: &[f32]) {
+        for &f in v {
+            self.f32(f);
+        }
+    }
+
+    fn string(&mut self, v: &str) {
+        self.u32(v.len() as u32);
+        self.bytes.extend_from_slice(v.as_bytes());
+    }
+
+    /// Textures are stored as the path they get reloaded from, relative to `directory`
+    fn material(&mut self, material: &Material, directory: &Path) -> io::Result<()> {
+        self.string(&material.name);
+        self.floats(material.ambient.as_slice());
+        self.floats(material.diffuse.as_slice());
+        self.floats(material.specular.as_slice());
+        self.f32(material.shininess);
+        self.f32(material.dissolve);
+        self.u8(material.illumination_model);
+
+        match &material.pbr {
+            Some(pbr) => {
+                self.u8(1);
+                self.floats(pbr.base_color.as_slice());
+                self.f32(pbr.metallic);
+                self.f32(pbr.roughness);
+                self.f32(pbr.occlusion_strength);
+                self.floats(pbr.emissive.as_slice());
+            }
+            None => self.u8(0),
+        }
+
+        self.u32(material.textures.len() as u32);
+        for texture in &material.textures {
+            // Images embedded in glTF files are only known by their index or their data URI,
+            // and percent-encoded URIs don't name the file they were read from
+            let plain_file = !texture.path.contains('%') && directory.join(&texture.path).is_file();
+            if !texture.path.starts_with("#color") && !plain_file {
+                return Err(io::Error::new(
+                    ErrorKind::Unsupported,
+                    format!("texture \"{}\" isn't a plain file", texture.path),
+                ));
+            }
+            self.u8(texture_type_id(&texture.ty));
+            self.string(&texture.path);
+        }
+
+        Ok(())
+    }
+
+    fn mesh(&mut self, mesh: &CookedMesh, material_index: u32) {
+        self.u32(material_index);
+        self.floats(mesh.aabb.min.as_slice());
+        self.floats(mesh.aabb.max.as_slice());
+        self.floats(mesh.bounding_sphere.center.as_slice());
+        self.f32(mesh.bounding_sphere.radius);
+
+        self.u32(mesh.lods.len() as u32);
+        for lod in mesh.lods.iter() {
+            self.u32(lod.offset as u32);
+            self.u32(lod.count as u32);
+            self.f32(lod.ratio);
+        }
+
+        self.u32(mesh.vertices.len() as u32);
+        self.u32(mesh.indices.len() as u32);
+
+        // Vertices are plain floats, written one by one to keep the file little endian
+        let vertices: &[Vertex] = &mesh.vertices;
+        let floats = unsafe {
+            slice::from_raw_parts(
+                vertices.as_ptr().cast::<f32>(),
+                size_of_val(vertices) / size_of::<f32>(),
+            )
+        };
+        self.floats(floats);
+        for &index in mesh.indices.iter() {
+            self.u32(index);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| invalid_data("truncated file"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn vec3(&mut self) -> io::Result<TVec3<f32>> {
+        Ok(glm::vec3(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn vec4(&mut self) -> io::Result<TVec4<f32>> {
+        Ok(glm::vec4(
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+        ))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.u32()? as usize;
+        String::from_utf8(self.bytes(length)?.to_vec()).map_err(|_| invalid_data("invalid string"))
+    }
+
+    fn material(
+        &mut self,
+        load_texture: &mut dyn FnMut(&str, TextureType) -> io::Result<Texture2D>,
+    ) -> io::Result<Material> {
+        let mut material = Material {
+            name: self.string()?,
+            ambient: self.vec3()?,
+            diffuse: self.vec3()?,
+            specular: self.vec3()?,
+            shininess: self.f32()?,
+            dissolve: self.f32()?,
+            illumination_model: self.u8()?,
+            ..Default::default()
+        };
+
+        if self.u8()? != 0 {
+            material.pbr = Some(PbrParameters {
+                base_color: self.vec4()?,
+                metallic: self.f32()?,
+                roughness: self.f32()?,
+                occlusion_strength: self.f32()?,
+                emissive: self.vec3()?,
+            });
+        }
+
+        for _ in 0..self.u32()? {
+            let ty = texture_type_from_id(self.u8()?)?;
+            let path = self.string()?;
+            material.textures.push(load_texture(&path, ty)?);
+        }
+
+        Ok(material)
+    }
+
+    fn mesh(&mut self, materials: &[Material]) -> io::Result<CookedMesh<'static>> {
+        let material = materials
+            .get(self.u32()? as usize)
+            .ok_or_else(|| invalid_data("invalid material index"))?
+            .clone();
+        let aabb = Aabb {
+            min: self.vec3()?,
+            max: self.vec3()?,
+        };
+        let bounding_sphere = BoundingSphere {
+            center: self.vec3()?,
+            radius: self.f32()?,
+        };
+
+        let lods = (0..self.u32()?)
+            .map(|_| {
+                Ok(Lod {
+                    offset: self.u32()? as usize,
+                    count: self.u32()? as usize,
+                    ratio: self.f32()?,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let vertex_count = self.u32()? as usize;
+        let index_count = self.u32()? as usize;
+        if lods.is_empty() || lods.iter().any(|lod| lod.offset + lod.count > index_count) {
+            return Err(invalid_data("invalid levels of detail"));
+        }
+
+        let vertex_bytes = self.bytes(vertex_count * size_of::<Vertex>())?;
+        let mut vertices = vec![Vertex::default(); vertex_count];
+        // Vertex is only made of floats, any value is valid for each of them
+        let floats = unsafe {
+            slice::from_raw_parts_mut(
+                vertices.as_mut_ptr().cast::<f32>(),
+                vertex_bytes.len() / size_of::<f32>(),
+            )
+        };
+        for (float, bytes) in floats.iter_mut().zip(vertex_bytes.chunks_exact(4)) {
+            *float = f32::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        let indices: Vec<u32> = self
+            .bytes(index_count * size_of::<u32>())?
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        if indices.iter().any(|&i| i as usize >= vertex_count) {
+            return Err(invalid_data("index out of bounds"));
+        }
+
+        Ok(CookedMesh {
+            vertices: Cow::Owned(vertices),
+            indices: Cow::Owned(indices),
+            lods: Cow::Owned(lods),
+            material: Cow::Owned(material),
+            aabb,
+            bounding_sphere,
+        })
+    }
+}
+
+fn texture_type_id(ty: &TextureType) -> u8 {
+    match ty {
+        TextureType::Diffuse => 0,
+        TextureType::Specular => 1,
+        TextureType::Emission => 2,
+        TextureType::Ambient => 3,
+        TextureType::Normal => 4,
+        TextureType::Metallic => 5,
+        TextureType::Roughness => 6,
+        TextureType::MetallicRoughness => 7,
+        TextureType::Occlusion => 8,
+        TextureType::Lightmap => 9,
+    }
+}
+
+fn texture_type_from_id(id: u8) -> io::Result<TextureType> {
+    Ok(match id {
+        0 => TextureType::Diffuse,
+        1 => TextureType::Specular,
+        2 => TextureType::Emission,
+        3 => TextureType::Ambient,
+        4 => TextureType::Normal,
+        5 => TextureType::Metallic,
+        6 => TextureType::Roughness,
+        7 => TextureType::MetallicRoughness,
+        8 => TextureType::Occlusion,
+        9 => TextureType::Lightmap,
+        _ => return Err(invalid_data("unknown texture type")),
+    })
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitives, simplify::simplify};
+
+    const CHECKSUM: u64 = 0x1234_5678_9abc_def0;
+
+    /// A sphere with two levels of detail and colored, skinned vertices
+    fn mesh(material: Material) -> CookedMesh<'static> {
+        let (mut vertices, mut indices) = primitives::uv_sphere(16, 8);
+        for (i, vertex) in vertices.iter_mut().enumerate() {
+            vertex.color = glm::vec4(0.5, 0.25, i as f32 / 100.0, 1.0);
+            vertex.tex_coords2 = glm::vec2(0.125, -3.5);
+            vertex.joints = glm::vec4(1.0, 2.0, 0.0, 0.0);
+            vertex.weights = glm::vec4(0.75, 0.25, 0.0, 0.0);
+        }
+
+        let full_count = indices.len();
+        let simplified = simplify(&vertices, &indices, 0.5);
+        let lods = vec![
+            Lod {
+                offset: 0,
+                count: full_count,
+                ratio: 1.0,
+            },
+            Lod {
+                offset: full_count,
+                count: simplified.len(),
+                ratio: 0.5,
+            },
+        ];
+        indices.extend(simplified);
+
+        let positions = vertices.iter().map(|v| &v.position);
+        CookedMesh {
+            aabb: Aabb::from_points(positions.clone()),
+            bounding_sphere: BoundingSphere::from_points(positions),
+            vertices: Cow::Owned(vertices),
+            indices: Cow::Owned(indices),
+            lods: Cow::Owned(lods),
+            material: Cow::Owned(material),
+        }
+    }
+
+    fn materials() -> [Material; 2] {
+        [
+            Material {
+                name: "painted metal".to_owned(),
+                diffuse: glm::vec3(0.8, 0.1, 0.1),
+                shininess: 96.0,
+                dissolve: 0.5,
+                illumination_model: 3,
+                ..Default::default()
+            },
+            Material {
+                name: "gold".to_owned(),
+                pbr: Some(PbrParameters {
+                    base_color: glm::vec4(1.0, 0.8, 0.3, 1.0),
+                    metallic: 1.0,
+                    roughness: 0.25,
+                    occlusion_strength: 0.5,
+                    emissive: glm::vec3(0.1, 0.0, 0.0),
+                }),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn no_textures(path: &str, _: TextureType) -> io::Result<Texture2D> {
+        panic!("unexpected texture \"{}\"", path)
+    }
+
+    fn assert_same_material(a: &Material, b: &Material) {
+        assert_eq!(a.name, b.name);
+        assert_eq!(
+            (a.ambient, a.diffuse, a.specular),
+            (b.ambient, b.diffuse, b.specular)
+        );
+        assert_eq!((a.shininess, a.dissolve), (b.shininess, b.dissolve));
+        assert_eq!(a.illumination_model, b.illumination_model);
+        assert_eq!(a.textures.len(), b.textures.len());
+        match (&a.pbr, &b.pbr) {
+            (Some(a), Some(b)) => {
+                assert_eq!(a.base_color, b.base_color);
+                assert_eq!(
+                    (a.metallic, a.roughness, a.occlusion_strength),
+                    (b.metallic, b.roughness, b.occlusion_strength)
+                );
+                assert_eq!(a.emissive, b.emissive);
+            }
+            (None, None) => (),
+            _ => panic!("PBR parameters differ"),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let [painted, gold] = materials();
+        // the first two meshes share their material, it is only written once
+        let meshes = [mesh(painted.clone()), mesh(painted), mesh(gold)];
+
+        let bytes = encode(&meshes, Path::new(""), CHECKSUM).unwrap();
+        let loaded = decode(&bytes, CHECKSUM, &mut no_textures).unwrap();
+
+        assert_eq!(loaded.len(), meshes.len());
+        for (mesh, loaded) in meshes.iter().zip(&loaded) {
+            assert_eq!(mesh.vertices.len(), loaded.vertices.len());
+            for (a, b) in mesh.vertices.iter().zip(loaded.vertices.iter()) {
+                assert_eq!(
+                    (a.position, a.normal, a.tex_coords),
+                    (b.position, b.normal, b.tex_coords)
+                );
+                assert_eq!((a.tangent, a.bitangent), (b.tangent, b.bitangent));
+                assert_eq!((a.color, a.tex_coords2), (b.color, b.tex_coords2));
+                assert_eq!((a.joints, a.weights), (b.joints, b.weights));
+            }
+            assert_eq!(mesh.indices, loaded.indices);
+
+            assert_eq!(mesh.lods.len(), loaded.lods.len());
+            for (a, b) in mesh.lods.iter().zip(loaded.lods.iter()) {
+                assert_eq!((a.offset, a.count, a.ratio), (b.offset, b.count, b.ratio));
+            }
+
+            assert_same_material(&mesh.material, &loaded.material);
+            assert_eq!(
+                (mesh.aabb.min, mesh.aabb.max),
+                (loaded.aabb.min, loaded.aabb.max)
+            );
+            assert_eq!(mesh.bounding_sphere.center, loaded.bounding_sphere.center);
+            assert_eq!(mesh.bounding_sphere.radius, loaded.bounding_sphere.radius);
+        }
+    }
+
+    #[test]
+    fn stale_and_corrupted_files_are_rejected() {
+        let [material, _] = materials();
+        let mut bytes = encode(&[mesh(material)], Path::new(""), CHECKSUM).unwrap();
+
+        assert!(decode(&bytes, CHECKSUM + 1, &mut no_textures).is_err());
+        *bytes.last_mut().unwrap() ^= 1;
+        assert!(decode(&bytes, CHECKSUM, &mut no_textures).is_err());
+        assert!(decode(&bytes[..HEADER_SIZE - 1], CHECKSUM, &mut no_textures).is_err());
+    }
+
+    #[test]
+    fn options_get_their_own_file() {
+        let source = Path::new("ressources/models/backpack/backpack.obj");
+        let lods = ImportOptions {
+            lod_ratios: vec![0.5, 0.25],
+            ..Default::default()
+        };
+
+        let default_path = cooked_path(source, &ImportOptions::default());
+        assert_ne!(default_path, cooked_path(source, &lods));
+        assert_eq!(default_path, cooked_path(source, &ImportOptions::default()));
+        let name = default_path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("backpack.obj.") && name.ends_with(".cooked"));
+    }
+}
@@ -0,0 +1,165 @@
+use gl33::{global_loader::*, *};
+use glm::{vec2, vec4};
+
+use crate::{
+    draw::Draw, framebuffer::Framebuffer, lights::Lights, material::Material, mesh::Mesh,
+    primitives, quad::Quad, shader_program::ShaderProgram, utils::usize_to_glenum,
+};
+
+/// Render targets of the G-buffer, in the order of the outputs of `gbuffer.fs`:
+/// albedo, normal and lit flag, position and view depth, specular color and shininess,
+/// baked ambient and whether there is any. The albedo is linear, sRGB keeps its dark shades
+/// precise in 8 bits.
+const GBUFFER_FORMATS: [GLenum; 5] = [
+    GL_SRGB8_ALPHA8,
+    GL_RGBA16F,
+    GL_RGBA32F,
+    GL_RGBA16F,
+    GL_RGBA16F,
+];
+/// The G-buffer textures are read from the first units, the lighting passes don't have
+/// materials of their own
+const GBUFFER_SAMPLERS: [&str; 5] = ["gAlbedo", "gNormal", "gPosition", "gMaterial", "gAmbient"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RenderPath {
+    /// Every light is evaluated for every fragment of every object, see `model_loading.fs`
+    Forward,
+    /// Surfaces are written to a G-buffer and lit afterwards, see `DeferredRenderer`
+    Deferred,
+}
+
+impl RenderPath {
+    pub fn toggled(self) -> Self {
+        match self {
+            RenderPath::Forward => RenderPath::Deferred,
+            RenderPath::Deferred => RenderPath::Forward,
+        }
+    }
+}
+
+/// Deferred shading: objects are drawn once into the G-buffer between `begin` and `end`, then
+/// `light_screen` runs the directional and spot lights over the whole screen and
+/// `light_volumes` only shades the pixels inside of each point light's reach, which keeps the
+/// cost of a point light proportional to the area it covers.
+pub struct DeferredRenderer {
+    gbuffer: Framebuffer,
+    width: i32,
+    height: i32,
+    screen: Quad,
+    /// Unit sphere scaled to each point light's radius
+    volume: Mesh,
+    /// Radius of the lights that never fade out, they would otherwise cover everything
+    pub max_light_radius: f32,
+}
+
+impl DeferredRenderer {
+    pub fn new(width: i32, height: i32) -> Self {
+        let (vertices, indices) = primitives::icosphere(2);
+        Self {
+            gbuffer: Framebuffer::multiple_targets(width, height, &GBUFFER_FORMATS),
+            width,
+            height,
+            screen: Quad::new(vec2(-1.0, 1.0), 1.0, 1.0, vec4(1.0, 1.0, 1.0, 1.0)),
+            volume: Mesh::new(vertices, indices, Material::default()),
+            max_light_radius: 100.0,
+        }
+    }
+
+    /// Recreates the G-buffer at the new size of the window
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.gbuffer = Framebuffer::multiple_targets(width, height, &GBUFFER_FORMATS);
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Binds and clears the G-buffer, objects drawn with `shader` until `end` are written into
+    /// it instead of being lit. Blending would mix the data of the targets, it is turned off.
+    pub fn begin(&self, shader: &ShaderProgram) {
+        self.gbuffer.bind();
+        unsafe {
+            glDisable(GL_BLEND);
+            // a view depth of zero marks the pixels nothing was drawn on
+            let zero = [0.0f32; 4];
+            for i in 0..GBUFFER_FORMATS.len() {
+                glClearBufferfv(GL_COLOR, i as i32, zero.as_ptr());
+            }
+            glClear(GL_DEPTH_BUFFER_BIT);
+        }
+        shader.use_program();
+    }
+
+    /// Goes back to the default framebuffer and gives it the depth of the G-buffer, for the
+    /// light volumes and for whatever is drawn forward on top of the lit scene
+    pub fn end(&self) {
+        self.gbuffer.blit_depth(self.width, self.height);
+    }
+
+    fn bind_gbuffer(&self, shader: &ShaderProgram) {
+        for (i, name) in GBUFFER_SAMPLERS.iter().enumerate() {
+            unsafe {
+                glActiveTexture(usize_to_glenum(0x84c0 + i));
+            }
+            self.gbuffer.bind_attachment(i);
+            shader.set_int(name, i as i32);
+        }
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+        }
+    }
+
+    /// Lights every pixel of the G-buffer with the directional and spot lights and their
+    /// ambient terms, `shader` being in use with the lights and shadows already set
+    pub fn light_screen(&self, shader: &ShaderProgram) {
+        self.bind_gbuffer(shader);
+
+        unsafe {
+            glDisable(GL_DEPTH_TEST);
+        }
+        self.screen.draw(shader);
+        unsafe {
+            glEnable(GL_DEPTH_TEST);
+        }
+    }
+
+    /// Adds the point lights by drawing a sphere around each of them, `shader` being in use
+    /// with the view, projection and point light shadows already set.
+    /// Only the back faces are drawn and they only pass in front of the scene's depth, so that
+    /// the volumes still work with the camera inside of them.
+    pub fn light_volumes(&self, shader: &ShaderProgram, lights: &Lights) {
+        self.bind_gbuffer(shader);
+        shader.set_vec2("screenSize", vec2(self.width as f32, self.height as f32));
+
+        unsafe {
+            glEnable(GL_BLEND);
+            glBlendFunc(GL_ONE, GL_ONE);
+            glDepthMask(0);
+            glDepthFunc(GL_GEQUAL);
+            glEnable(GL_CULL_FACE);
+            glCullFace(GL_FRONT);
+        }
+
+        for (i, light) in lights.points.iter().enumerate() {
+            // the sphere's faces cut slightly inside of its radius
+            let radius = light.radius().min(self.max_light_radius);
+            let scale = radius * 1.05;
+            let model = glm::scale(
+                &glm::translation(&light.position),
+                &glm::vec3(scale, scale, scale),
+            );
+            shader.set_mat4("model", &model);
+            shader.set_float("lightRadius", radius);
+            light.set_into_shader(shader, "light");
+            shader.set_int("light.shadowSlot", lights.shadow_slot(i));
+            self.volume.draw(shader);
+        }
+
+        unsafe {
+            glCullFace(GL_BACK);
+            glDisable(GL_CULL_FACE);
+            glDepthFunc(GL_LESS);
+            glDepthMask(1);
+            glDisable(GL_BLEND);
+        }
+    }
+}
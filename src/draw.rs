@@ -1,5 +1,18 @@
-use crate::shader_program::ShaderProgram;
-
-pub trait Draw {
-    fn draw(&self, shader: &ShaderProgram);
-}
+use crate::{material::Material, shader_program::ShaderProgram};
+
+#[derive(Default)]
+pub struct DrawOptions<'a> {
+    /// Replaces the materials the object would otherwise be drawn with
+    pub material: Option<&'a Material>,
+    /// Share of the screen height covered by the object, used to pick its level of detail.
+    /// Drawn at full detail when not set.
+    pub screen_size: Option<f32>,
+}
+
+pub trait Draw {
+    fn draw(&self, shader: &ShaderProgram);
+
+    fn draw_with(&self, shader: &ShaderProgram, _options: &DrawOptions) {
+        self.draw(shader)
+    }
+}
@@ -0,0 +1,511 @@
+use std::{
+    collections::HashSet,
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    material::Material,
+    mesh::{Mesh, Vertex},
+    model::Model,
+    texture::TextureType,
+};
+
+/// Encoding of the vertex and face elements of a PLY file
+#[derive(Clone, Copy)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// What gets written of a mesh: its full detail triangles and its material
+struct ExportedMesh<'a> {
+    vertices: &'a [Vertex],
+    indices: &'a [u32],
+    material: &'a Material,
+    /// Directory the texture paths of the material are relative to
+    directory: &'a Path,
+}
+
+impl<'a> From<&'a Mesh> for ExportedMesh<'a> {
+    fn from(mesh: &'a Mesh) -> Self {
+        Self {
+            vertices: mesh.vertices(),
+            indices: mesh.indices(),
+            material: &mesh.material,
+            directory: Path::new(""),
+        }
+    }
+}
+
+impl Mesh {
+    /// Writes the full detail mesh and its material, see `Model::write_obj`. A lone mesh
+    /// doesn't know where its textures come from, their paths are taken as relative to the
+    /// working directory.
+    pub fn write_obj(&self, path: &Path) -> io::Result<()> {
+        write_obj(&[self.into()], path)
+    }
+
+    pub fn write_ply(&self, path: &Path, format: PlyFormat) -> io::Result<()> {
+        write_ply(&[self.into()], path, format)
+    }
+}
+
+impl Model {
+    /// Writes every mesh as an object of the OBJ file and their materials into an MTL library
+    /// next to it. Texture paths are rewritten relative to the OBJ file, and the generated
+    /// single color textures are left out.
+    pub fn write_obj(&self, path: &Path) -> io::Result<()> {
+        write_obj(&self.exported_meshes(), path)
+    }
+
+    /// PLY has no notion of objects or materials, the meshes are merged into one
+    pub fn write_ply(&self, path: &Path, format: PlyFormat) -> io::Result<()> {
+        write_ply(&self.exported_meshes(), path, format)
+    }
+
+    fn exported_meshes(&self) -> Vec<ExportedMesh<'_>> {
+        self.meshes
+            .iter()
+            .map(|mesh| ExportedMesh {
+                directory: Path::new(&self.directory),
+                ..mesh.into()
+            })
+            .collect()
+    }
+}
+
+fn write_obj(meshes: &[ExportedMesh], path: &Path) -> io::Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path.file_name().unwrap().to_str().unwrap();
+    let mtl_directory = path.parent().unwrap_or(Path::new(""));
+
+    let mut obj = BufWriter::new(File::create(path)?);
+    let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+
+    writeln!(obj, "mtllib {}", mtl_name)?;
+
+    // Vertex colors are a common extension appended to the positions, readers expect them on
+    // every position of the file or none
+    let has_colors = meshes
+        .iter()
+        .flat_map(|m| m.vertices)
+        .any(|v| v.color != glm::vec4(1.0, 1.0, 1.0, 1.0));
+
+    let mut written_materials: HashSet<String> = HashSet::new();
+    // OBJ indices are 1-based and global to the file
+    let mut first_index = 1;
+
+    for (i, mesh) in meshes.iter().enumerate() {
+        let material_name = if mesh.material.name.is_empty() {
+            format!("material{}", i)
+        } else {
+            mesh.material.name.replace(char::is_whitespace, "_")
+        };
+        if written_materials.insert(material_name.clone()) {
+            write_mtl_material(&mut mtl, &material_name, mesh, mtl_directory)?;
+        }
+
+        writeln!(obj, "o mesh{}", i)?;
+        for vertex in mesh.vertices {
+            let (p, c) = (vertex.position, vertex.color);
+            if has_colors {
+                writeln!(obj, "v {} {} {} {} {} {}", p.x, p.y, p.z, c.x, c.y, c.z)?;
+            } else {
+                writeln!(obj, "v {} {} {}", p.x, p.y, p.z)?;
+            }
+        }
+        for vertex in mesh.vertices {
+            let t = vertex.tex_coords;
+            writeln!(obj, "vt {} {}", t.x, t.y)?;
+        }
+        for vertex in mesh.vertices {
+            let n = vertex.normal;
+            writeln!(obj, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+
+        writeln!(obj, "usemtl {}", material_name)?;
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| triangle[k] as usize + first_index);
+            writeln!(obj, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c)?;
+        }
+
+        first_index += mesh.vertices.len();
+    }
+
+    obj.flush()?;
+    mtl.flush()
+}
+
+fn write_mtl_material<W: Write>(
+    mtl: &mut W,
+    name: &str,
+    mesh: &ExportedMesh,
+    mtl_directory: &Path,
+) -> io::Result<()> {
+    let material = mesh.material;
+    let (a, d, s) = (material.ambient, material.diffuse, material.specular);
+
+    writeln!(mtl, "newmtl {}", name)?;
+    writeln!(mtl, "Ka {} {} {}", a.x, a.y, a.z)?;
+    writeln!(mtl, "Kd {} {} {}", d.x, d.y, d.z)?;
+    writeln!(mtl, "Ks {} {} {}", s.x, s.y, s.z)?;
+    writeln!(mtl, "Ns {}", material.shininess)?;
+    writeln!(mtl, "d {}", material.dissolve)?;
+    writeln!(mtl, "illum {}", material.illumination_model)?;
+
+    if let Some(pbr) = &material.pbr {
+        let e = pbr.emissive;
+        writeln!(mtl, "Pm {}", pbr.metallic)?;
+        writeln!(mtl, "Pr {}", pbr.roughness)?;
+        writeln!(mtl, "Ke {} {} {}", e.x, e.y, e.z)?;
+    }
+
+    for texture in &material.textures {
+        if texture.path.starts_with('#') {
+            continue;
+        }
+
+        // The glTF only maps have no MTL statement
+        let statement = match texture.ty {
+            TextureType::Diffuse => "map_Kd",
+            TextureType::Specular => "map_Ks",
+            TextureType::Emission => "map_Ke",
+            TextureType::Ambient => "map_Ka",
+            TextureType::Normal => "map_Bump",
+            TextureType::Metallic => "map_Pm",
+            TextureType::Roughness => "map_Pr",
+            TextureType::Lightmap => "map_lightmap",
+            TextureType::MetallicRoughness | TextureType::Occlusion => continue,
+        };
+        let texture_path = relative_path(&mesh.directory.join(&texture.path), mtl_directory);
+        // MTL readers expect forward slashes whatever the platform
+        let texture_path = texture_path.to_str().unwrap().replace('\\', "/");
+        writeln!(mtl, "{} {}", statement, texture_path)?;
+    }
+
+    writeln!(mtl)
+}
+
+/// Path of `target` seen from the `base` directory, `target` stays absolute when they don't
+/// share a root
+fn relative_path(target: &Path, base: &Path) -> PathBuf {
+    let absolute = |path: &Path| {
+        path.canonicalize()
+            .unwrap_or_else(|_| env::current_dir().unwrap().join(path))
+    };
+    let (target, base) = (absolute(target), absolute(base));
+
+    let common = target
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return target;
+    }
+
+    base.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(target.components().skip(common))
+        .collect()
+}
+
+fn write_ply(meshes: &[ExportedMesh], path: &Path, format: PlyFormat) -> io::Result<()> {
+    let vertex_count: usize = meshes.iter().map(|m| m.vertices.len()).sum();
+    let face_count: usize = meshes.iter().map(|m| m.indices.len() / 3).sum();
+
+    let mut ply = BufWriter::new(File::create(path)?);
+
+    writeln!(ply, "ply")?;
+    match format {
+        PlyFormat::Ascii => writeln!(ply, "format ascii 1.0")?,
+        PlyFormat::BinaryLittleEndian => writeln!(ply, "format binary_little_endian 1.0")?,
+    }
+    writeln!(ply, "element vertex {}", vertex_count)?;
+    for property in ["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+        writeln!(ply, "property float {}", property)?;
+    }
+    for property in ["red", "green", "blue", "alpha"] {
+        writeln!(ply, "property uchar {}", property)?;
+    }
+    writeln!(ply, "element face {}", face_count)?;
+    writeln!(ply, "property list uchar uint vertex_indices")?;
+    writeln!(ply, "end_header")?;
+
+    for vertex in meshes.iter().flat_map(|m| m.vertices) {
+        let (values, color) = ply_vertex(vertex);
+        match format {
+            PlyFormat::Ascii => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(f32::to_string)
+                    .chain(color.iter().map(u8::to_string))
+                    .collect();
+                writeln!(ply, "{}", values.join(" "))?;
+            }
+            PlyFormat::BinaryLittleEndian => {
+                for value in values {
+                    ply.write_all(&value.to_le_bytes())?;
+                }
+                ply.write_all(&color)?;
+            }
+        }
+    }
+
+    // Indices are offset by the vertices of the meshes before
+    let mut first_index = 0;
+    for mesh in meshes {
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| triangle[k] + first_index);
+            match format {
+                PlyFormat::Ascii => writeln!(ply, "3 {} {} {}", a, b, c)?,
+                PlyFormat::BinaryLittleEndian => {
+                    ply.write_all(&[3])?;
+                    for index in [a, b, c] {
+                        ply.write_all(&index.to_le_bytes())?;
+                    }
+                }
+            }
+        }
+
+        first_index += mesh.vertices.len() as u32;
+    }
+
+    ply.flush()
+}
+
+/// The float properties and the color of a vertex
+fn ply_vertex(vertex: &Vertex) -> ([f32; 8], [u8; 4]) {
+    let (p, n, t) = (vertex.position, vertex.normal, vertex.tex_coords);
+    let color = [0, 1, 2, 3].map(|k| (vertex.color[k].clamp(0.0, 1.0) * 255.0).round() as u8);
+
+    ([p.x, p.y, p.z, n.x, n.y, n.z, t.x, t.y], color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{vec2, vec3, vec4};
+    use std::{env, fs, path::PathBuf};
+
+    /// Two triangles of a tetrahedron corner with distinct attributes on every vertex
+    fn mesh() -> (Vec<Vertex>, Vec<u32>) {
+        let positions = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.5, 0.0, 0.0),
+            vec3(0.0, 2.25, 0.0),
+            vec3(0.0, 0.0, -0.125),
+        ];
+        let vertices = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| {
+                let i = i as f32;
+                Vertex {
+                    position,
+                    normal: glm::normalize(&vec3(1.0, i, -i)),
+                    tex_coords: vec2(i * 0.25, 1.0 - i * 0.125),
+                    color: vec4(0.2 * i, 1.0 - 0.2 * i, 0.6, 1.0),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        (vertices, vec![0, 1, 2, 0, 2, 3])
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("{}_{}", std::process::id(), name))
+    }
+
+    fn assert_close(a: &[f32], b: &[f32], epsilon: f32) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() <= epsilon, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn obj_round_trip() {
+        let (vertices, indices) = mesh();
+        let material = Material {
+            name: "painted metal".to_string(),
+            diffuse: vec3(0.8, 0.4, 0.2),
+            shininess: 64.0,
+            ..Default::default()
+        };
+        let exported = ExportedMesh {
+            vertices: &vertices,
+            indices: &indices,
+            material: &material,
+            directory: Path::new(""),
+        };
+        let path = temp_path("round_trip.obj");
+        write_obj(&[exported], &path).unwrap();
+
+        let (models, materials) = tobj::load_obj(&path, &tobj::GPU_LOAD_OPTIONS).unwrap();
+        let materials = materials.unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("mtl")).unwrap();
+
+        assert_eq!(models.len(), 1);
+        let loaded = &models[0].mesh;
+        assert_eq!(loaded.indices.len(), indices.len());
+
+        // Indices may be renumbered by the loader, compare the vertices of each corner instead
+        for (&original, &index) in indices.iter().zip(&loaded.indices) {
+            let (v, i) = (&vertices[original as usize], index as usize);
+            assert_close(
+                &loaded.positions[i * 3..i * 3 + 3],
+                v.position.as_slice(),
+                0.0,
+            );
+            assert_close(&loaded.normals[i * 3..i * 3 + 3], v.normal.as_slice(), 0.0);
+            assert_close(
+                &loaded.texcoords[i * 2..i * 2 + 2],
+                v.tex_coords.as_slice(),
+                0.0,
+            );
+            assert_close(
+                &loaded.vertex_color[i * 3..i * 3 + 3],
+                &v.color.as_slice()[..3],
+                0.0,
+            );
+        }
+
+        let loaded_material = &materials[loaded.material_id.unwrap()];
+        assert_eq!(loaded_material.name, "painted_metal");
+        assert_close(&loaded_material.diffuse, material.diffuse.as_slice(), 0.0);
+        assert_eq!(loaded_material.shininess, material.shininess);
+    }
+
+    /// Two triangles in separate meshes, so that the second one's indices get offset
+    fn ply_meshes() -> [(Vec<Vertex>, Vec<u32>); 2] {
+        let triangle = |z: f32, color| {
+            let vertices = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]
+                .map(|(x, y)| Vertex {
+                    position: vec3(x, y, z),
+                    normal: vec3(0.0, 0.0, 1.0),
+                    tex_coords: vec2(x, y),
+                    color,
+                    ..Default::default()
+                })
+                .to_vec();
+            (vertices, vec![0, 1, 2])
+        };
+        [
+            triangle(0.0, vec4(1.0, 0.0, 0.0, 1.0)),
+            triangle(0.5, vec4(0.0, 0.5, 1.0, 1.0)),
+        ]
+    }
+
+    fn write_ply_meshes(format: PlyFormat, name: &str) -> Vec<u8> {
+        let meshes = ply_meshes();
+        let material = Material::default();
+        let exported: Vec<ExportedMesh> = meshes
+            .iter()
+            .map(|(vertices, indices)| ExportedMesh {
+                vertices,
+                indices,
+                material: &material,
+                directory: Path::new(""),
+            })
+            .collect();
+
+        let path = temp_path(name);
+        write_ply(&exported, &path, format).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    fn ply_header(format: &str) -> String {
+        format!(
+            "ply\n\
+             format {} 1.0\n\
+             element vertex 6\n\
+             property float x\n\
+             property float y\n\
+             property float z\n\
+             property float nx\n\
+             property float ny\n\
+             property float nz\n\
+             property float s\n\
+             property float t\n\
+             property uchar red\n\
+             property uchar green\n\
+             property uchar blue\n\
+             property uchar alpha\n\
+             element face 2\n\
+             property list uchar uint vertex_indices\n\
+             end_header\n",
+            format
+        )
+    }
+
+    #[test]
+    fn ascii_ply() {
+        let expected = ply_header("ascii")
+            + "0 0 0 0 0 1 0 0 255 0 0 255\n\
+               1 0 0 0 0 1 1 0 255 0 0 255\n\
+               0 1 0 0 0 1 0 1 255 0 0 255\n\
+               0 0 0.5 0 0 1 0 0 0 128 255 255\n\
+               1 0 0.5 0 0 1 1 0 0 128 255 255\n\
+               0 1 0.5 0 0 1 0 1 0 128 255 255\n\
+               3 0 1 2\n\
+               3 3 4 5\n";
+
+        let written = write_ply_meshes(PlyFormat::Ascii, "ascii.ply");
+        assert_eq!(String::from_utf8(written).unwrap(), expected);
+    }
+
+    #[test]
+    fn binary_ply() {
+        const ZERO: [u8; 4] = [0x00, 0x00, 0x00, 0x00];
+        const HALF: [u8; 4] = [0x00, 0x00, 0x00, 0x3f];
+        const ONE: [u8; 4] = [0x00, 0x00, 0x80, 0x3f];
+        let red = [255, 0, 0, 255];
+        let blue = [0, 128, 255, 255];
+
+        let mut expected = ply_header("binary_little_endian").into_bytes();
+        for (x, y, z, color) in [
+            (ZERO, ZERO, ZERO, red),
+            (ONE, ZERO, ZERO, red),
+            (ZERO, ONE, ZERO, red),
+            (ZERO, ZERO, HALF, blue),
+            (ONE, ZERO, HALF, blue),
+            (ZERO, ONE, HALF, blue),
+        ] {
+            expected.extend([x, y, z, ZERO, ZERO, ONE, x, y, color].concat());
+        }
+        expected.extend([3, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0]);
+        expected.extend([3, 3, 0, 0, 0, 4, 0, 0, 0, 5, 0, 0, 0]);
+
+        let written = write_ply_meshes(PlyFormat::BinaryLittleEndian, "binary.ply");
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn texture_paths_follow_the_output_file() {
+        let root = temp_path("relative");
+        let (textures, output) = (root.join("model").join("textures"), root.join("exported"));
+        fs::create_dir_all(&textures).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(textures.join("diffuse.png"), []).unwrap();
+
+        let relative = relative_path(&root.join("model").join("textures/diffuse.png"), &output);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            relative,
+            Path::new("..")
+                .join("model")
+                .join("textures")
+                .join("diffuse.png")
+        );
+    }
+}
@@ -1,101 +1,471 @@
-use gl33::{global_loader::*, *};
-
-use crate::{glenum_to_i32, Texture2D};
-
-pub struct Framebuffer {
-    fbo: u32,
-    texture: u32,
-    rbo: u32,
-}
-
-impl Framebuffer {
-    pub fn new(window_width: i32, window_height: i32) -> Self {
-        let mut ret = Self {
-            fbo: 0,
-            texture: 0,
-            rbo: 0,
-        };
-
-        unsafe {
-            glGenFramebuffers(1, &mut ret.fbo);
-            glBindFramebuffer(GL_FRAMEBUFFER, ret.fbo);
-            glGenTextures(1, &mut ret.texture);
-            glBindTexture(GL_TEXTURE_2D, ret.texture);
-
-            glTexImage2D(
-                GL_TEXTURE_2D,
-                0,
-                glenum_to_i32(GL_RGB),
-                window_width,
-                window_height,
-                0,
-                GL_RGB,
-                GL_UNSIGNED_BYTE,
-                0 as *const _,
-            );
-
-            glTexParameteri(
-                GL_TEXTURE_2D,
-                GL_TEXTURE_MIN_FILTER,
-                glenum_to_i32(GL_LINEAR),
-            );
-            glTexParameteri(
-                GL_TEXTURE_2D,
-                GL_TEXTURE_MAG_FILTER,
-                glenum_to_i32(GL_LINEAR),
-            );
-
-            glFramebufferTexture2D(
-                GL_FRAMEBUFFER,
-                GL_COLOR_ATTACHMENT0,
-                GL_TEXTURE_2D,
-                ret.texture,
-                0,
-            );
-            Texture2D::clear_binding();
-
-            glGenRenderbuffers(1, &mut ret.rbo);
-            glBindRenderbuffer(GL_RENDERBUFFER, ret.rbo);
-            glRenderbufferStorage(
-                GL_RENDERBUFFER,
-                GL_DEPTH24_STENCIL8,
-                window_width.try_into().unwrap(),
-                window_height.try_into().unwrap(),
-            );
-            glBindRenderbuffer(GL_RENDERBUFFER, 0);
-
-            glFramebufferRenderbuffer(
-                GL_FRAMEBUFFER,
-                GL_DEPTH_STENCIL_ATTACHMENT,
-                GL_RENDERBUFFER,
-                ret.rbo,
-            );
-
-            match glCheckFramebufferStatus(GL_FRAMEBUFFER) {
-                GL_FRAMEBUFFER_COMPLETE => (),
-                _ => println!("Framebuffer is not complete"),
-            };
-            glBindFramebuffer(GL_FRAMEBUFFER, 0);
-        }
-
-        ret
-    }
-
-    pub fn bind(&self) {
-        unsafe {
-            glBindFramebuffer(GL_FRAMEBUFFER, self.fbo);
-        }
-    }
-
-    pub fn bind_texture(&self) {
-        unsafe {
-            glBindTexture(GL_TEXTURE_2D, self.texture);
-        }
-    }
-
-    pub fn clear_binding() {
-        unsafe {
-            glBindFramebuffer(GL_FRAMEBUFFER, 0);
-        }
-    }
-}
+use gl33::{global_loader::*, *};
+
+use crate::{glenum_to_i32, usize_to_glenum, Texture2D};
+
+pub struct Framebuffer {
+    fbo: u32,
+    texture: u32,
+    /// `GL_TEXTURE_2D`, `GL_TEXTURE_CUBE_MAP` or `GL_TEXTURE_2D_ARRAY` for layered depth
+    texture_target: GLenum,
+    /// Color attachments past the first one, for multiple render targets
+    extra_textures: Vec<u32>,
+    rbo: u32,
+}
+
+impl Framebuffer {
+    /// Framebuffer with an sRGB color texture, shaders write linear colors into it like into
+    /// the window's, and a depth-stencil buffer
+    pub fn new(window_width: i32, window_height: i32) -> Self {
+        let mut ret = Self {
+            fbo: 0,
+            texture: 0,
+            texture_target: GL_TEXTURE_2D,
+            extra_textures: Vec::new(),
+            rbo: 0,
+        };
+
+        unsafe {
+            glGenFramebuffers(1, &mut ret.fbo);
+            glBindFramebuffer(GL_FRAMEBUFFER, ret.fbo);
+            glGenTextures(1, &mut ret.texture);
+            glBindTexture(GL_TEXTURE_2D, ret.texture);
+
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                glenum_to_i32(GL_SRGB8),
+                window_width,
+                window_height,
+                0,
+                GL_RGB,
+                GL_UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_MIN_FILTER,
+                glenum_to_i32(GL_LINEAR),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_MAG_FILTER,
+                glenum_to_i32(GL_LINEAR),
+            );
+
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_COLOR_ATTACHMENT0,
+                GL_TEXTURE_2D,
+                ret.texture,
+                0,
+            );
+            Texture2D::clear_binding();
+
+            glGenRenderbuffers(1, &mut ret.rbo);
+            glBindRenderbuffer(GL_RENDERBUFFER, ret.rbo);
+            glRenderbufferStorage(
+                GL_RENDERBUFFER,
+                GL_DEPTH24_STENCIL8,
+                window_width,
+                window_height,
+            );
+            glBindRenderbuffer(GL_RENDERBUFFER, 0);
+
+            glFramebufferRenderbuffer(
+                GL_FRAMEBUFFER,
+                GL_DEPTH_STENCIL_ATTACHMENT,
+                GL_RENDERBUFFER,
+                ret.rbo,
+            );
+
+            match glCheckFramebufferStatus(GL_FRAMEBUFFER) {
+                GL_FRAMEBUFFER_COMPLETE => (),
+                _ => println!("Framebuffer is not complete"),
+            };
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+
+        ret
+    }
+
+    /// Framebuffer with a single depth texture and no color, for depth-only passes such as
+    /// shadow maps. Depth outside of the texture reads as the far plane.
+    pub fn depth_only(width: i32, height: i32) -> Self {
+        let mut ret = Self {
+            fbo: 0,
+            texture: 0,
+            texture_target: GL_TEXTURE_2D,
+            extra_textures: Vec::new(),
+            rbo: 0,
+        };
+
+        unsafe {
+            glGenFramebuffers(1, &mut ret.fbo);
+            glBindFramebuffer(GL_FRAMEBUFFER, ret.fbo);
+            glGenTextures(1, &mut ret.texture);
+            glBindTexture(GL_TEXTURE_2D, ret.texture);
+
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                glenum_to_i32(GL_DEPTH_COMPONENT24),
+                width,
+                height,
+                0,
+                GL_DEPTH_COMPONENT,
+                GL_FLOAT,
+                std::ptr::null(),
+            );
+
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_MIN_FILTER,
+                glenum_to_i32(GL_NEAREST),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_MAG_FILTER,
+                glenum_to_i32(GL_NEAREST),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_WRAP_S,
+                glenum_to_i32(GL_CLAMP_TO_BORDER),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_WRAP_T,
+                glenum_to_i32(GL_CLAMP_TO_BORDER),
+            );
+            let border = [1.0f32, 1.0, 1.0, 1.0];
+            glTexParameterfv(GL_TEXTURE_2D, GL_TEXTURE_BORDER_COLOR, border.as_ptr());
+
+            glFramebufferTexture2D(
+                GL_FRAMEBUFFER,
+                GL_DEPTH_ATTACHMENT,
+                GL_TEXTURE_2D,
+                ret.texture,
+                0,
+            );
+            Texture2D::clear_binding();
+
+            glDrawBuffer(GL_NONE);
+            glReadBuffer(GL_NONE);
+
+            match glCheckFramebufferStatus(GL_FRAMEBUFFER) {
+                GL_FRAMEBUFFER_COMPLETE => (),
+                _ => println!("Framebuffer is not complete"),
+            };
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+
+        ret
+    }
+
+    /// Framebuffer with a depth cubemap attached as a layered texture, the six faces are
+    /// rendered in a single pass by a geometry shader writing `gl_Layer`
+    pub fn depth_cubemap(resolution: i32) -> Self {
+        let mut ret = Self {
+            fbo: 0,
+            texture: 0,
+            texture_target: GL_TEXTURE_CUBE_MAP,
+            extra_textures: Vec::new(),
+            rbo: 0,
+        };
+
+        unsafe {
+            glGenFramebuffers(1, &mut ret.fbo);
+            glBindFramebuffer(GL_FRAMEBUFFER, ret.fbo);
+            glGenTextures(1, &mut ret.texture);
+            glBindTexture(GL_TEXTURE_CUBE_MAP, ret.texture);
+
+            for face in 0..6 {
+                glTexImage2D(
+                    usize_to_glenum(0x8515 + face),
+                    0,
+                    glenum_to_i32(GL_DEPTH_COMPONENT24),
+                    resolution,
+                    resolution,
+                    0,
+                    GL_DEPTH_COMPONENT,
+                    GL_FLOAT,
+                    std::ptr::null(),
+                );
+            }
+
+            glTexParameteri(
+                GL_TEXTURE_CUBE_MAP,
+                GL_TEXTURE_MIN_FILTER,
+                glenum_to_i32(GL_NEAREST),
+            );
+            glTexParameteri(
+                GL_TEXTURE_CUBE_MAP,
+                GL_TEXTURE_MAG_FILTER,
+                glenum_to_i32(GL_NEAREST),
+            );
+            for wrap in [GL_TEXTURE_WRAP_S, GL_TEXTURE_WRAP_T, GL_TEXTURE_WRAP_R] {
+                glTexParameteri(GL_TEXTURE_CUBE_MAP, wrap, glenum_to_i32(GL_CLAMP_TO_EDGE));
+            }
+
+            glFramebufferTexture(GL_FRAMEBUFFER, GL_DEPTH_ATTACHMENT, ret.texture, 0);
+            glBindTexture(GL_TEXTURE_CUBE_MAP, 0);
+
+            glDrawBuffer(GL_NONE);
+            glReadBuffer(GL_NONE);
+
+            match glCheckFramebufferStatus(GL_FRAMEBUFFER) {
+                GL_FRAMEBUFFER_COMPLETE => (),
+                _ => println!("Framebuffer is not complete"),
+            };
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+
+        ret
+    }
+
+    /// Framebuffer rendering into one layer at a time of a depth texture array, see
+    /// `bind_layer`. Depth outside of the texture reads as the far plane.
+    pub fn depth_array(width: i32, height: i32, layers: i32) -> Self {
+        let mut ret = Self {
+            fbo: 0,
+            texture: 0,
+            texture_target: GL_TEXTURE_2D_ARRAY,
+            extra_textures: Vec::new(),
+            rbo: 0,
+        };
+
+        unsafe {
+            glGenFramebuffers(1, &mut ret.fbo);
+            glBindFramebuffer(GL_FRAMEBUFFER, ret.fbo);
+            glGenTextures(1, &mut ret.texture);
+            glBindTexture(GL_TEXTURE_2D_ARRAY, ret.texture);
+
+            glTexImage3D(
+                GL_TEXTURE_2D_ARRAY,
+                0,
+                glenum_to_i32(GL_DEPTH_COMPONENT24),
+                width,
+                height,
+                layers,
+                0,
+                GL_DEPTH_COMPONENT,
+                GL_FLOAT,
+                std::ptr::null(),
+            );
+
+            glTexParameteri(
+                GL_TEXTURE_2D_ARRAY,
+                GL_TEXTURE_MIN_FILTER,
+                glenum_to_i32(GL_NEAREST),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D_ARRAY,
+                GL_TEXTURE_MAG_FILTER,
+                glenum_to_i32(GL_NEAREST),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D_ARRAY,
+                GL_TEXTURE_WRAP_S,
+                glenum_to_i32(GL_CLAMP_TO_BORDER),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D_ARRAY,
+                GL_TEXTURE_WRAP_T,
+                glenum_to_i32(GL_CLAMP_TO_BORDER),
+            );
+            let border = [1.0f32, 1.0, 1.0, 1.0];
+            glTexParameterfv(
+                GL_TEXTURE_2D_ARRAY,
+                GL_TEXTURE_BORDER_COLOR,
+                border.as_ptr(),
+            );
+
+            glFramebufferTextureLayer(GL_FRAMEBUFFER, GL_DEPTH_ATTACHMENT, ret.texture, 0, 0);
+            glBindTexture(GL_TEXTURE_2D_ARRAY, 0);
+
+            glDrawBuffer(GL_NONE);
+            glReadBuffer(GL_NONE);
+
+            match glCheckFramebufferStatus(GL_FRAMEBUFFER) {
+                GL_FRAMEBUFFER_COMPLETE => (),
+                _ => println!("Framebuffer is not complete"),
+            };
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+
+        ret
+    }
+
+    /// Framebuffer with one color texture per internal format of `formats`, all written at
+    /// once by the fragment shader's outputs in the same order, and a depth-stencil buffer.
+    /// The textures aren't filtered, they are meant to be read back pixel for pixel.
+    pub fn multiple_targets(width: i32, height: i32, formats: &[GLenum]) -> Self {
+        assert!(!formats.is_empty(), "Framebuffer needs at least one target");
+
+        let mut ret = Self {
+            fbo: 0,
+            texture: 0,
+            texture_target: GL_TEXTURE_2D,
+            extra_textures: Vec::new(),
+            rbo: 0,
+        };
+
+        unsafe {
+            glGenFramebuffers(1, &mut ret.fbo);
+            glBindFramebuffer(GL_FRAMEBUFFER, ret.fbo);
+
+            let mut textures = vec![0; formats.len()];
+            glGenTextures(formats.len().try_into().unwrap(), textures.as_mut_ptr());
+            for (i, (&texture, &format)) in textures.iter().zip(formats).enumerate() {
+                glBindTexture(GL_TEXTURE_2D, texture);
+                glTexImage2D(
+                    GL_TEXTURE_2D,
+                    0,
+                    glenum_to_i32(format),
+                    width,
+                    height,
+                    0,
+                    GL_RGBA,
+                    GL_FLOAT,
+                    std::ptr::null(),
+                );
+
+                glTexParameteri(
+                    GL_TEXTURE_2D,
+                    GL_TEXTURE_MIN_FILTER,
+                    glenum_to_i32(GL_NEAREST),
+                );
+                glTexParameteri(
+                    GL_TEXTURE_2D,
+                    GL_TEXTURE_MAG_FILTER,
+                    glenum_to_i32(GL_NEAREST),
+                );
+                glTexParameteri(
+                    GL_TEXTURE_2D,
+                    GL_TEXTURE_WRAP_S,
+                    glenum_to_i32(GL_CLAMP_TO_EDGE),
+                );
+                glTexParameteri(
+                    GL_TEXTURE_2D,
+                    GL_TEXTURE_WRAP_T,
+                    glenum_to_i32(GL_CLAMP_TO_EDGE),
+                );
+
+                glFramebufferTexture2D(
+                    GL_FRAMEBUFFER,
+                    usize_to_glenum(0x8CE0 + i),
+                    GL_TEXTURE_2D,
+                    texture,
+                    0,
+                );
+            }
+            Texture2D::clear_binding();
+
+            let attachments: Vec<GLenum> = (0..formats.len())
+                .map(|i| usize_to_glenum(0x8CE0 + i))
+                .collect();
+            glDrawBuffers(attachments.len().try_into().unwrap(), attachments.as_ptr());
+
+            ret.texture = textures[0];
+            ret.extra_textures = textures[1..].to_vec();
+
+            glGenRenderbuffers(1, &mut ret.rbo);
+            glBindRenderbuffer(GL_RENDERBUFFER, ret.rbo);
+            glRenderbufferStorage(GL_RENDERBUFFER, GL_DEPTH24_STENCIL8, width, height);
+            glBindRenderbuffer(GL_RENDERBUFFER, 0);
+
+            glFramebufferRenderbuffer(
+                GL_FRAMEBUFFER,
+                GL_DEPTH_STENCIL_ATTACHMENT,
+                GL_RENDERBUFFER,
+                ret.rbo,
+            );
+
+            match glCheckFramebufferStatus(GL_FRAMEBUFFER) {
+                GL_FRAMEBUFFER_COMPLETE => (),
+                _ => println!("Framebuffer is not complete"),
+            };
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+
+        ret
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, self.fbo);
+        }
+    }
+
+    /// Binds the framebuffer with `layer` of its texture array as the depth attachment
+    pub fn bind_layer(&self, layer: i32) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, self.fbo);
+            glFramebufferTextureLayer(GL_FRAMEBUFFER, GL_DEPTH_ATTACHMENT, self.texture, 0, layer);
+        }
+    }
+
+    pub fn bind_texture(&self) {
+        unsafe {
+            glBindTexture(self.texture_target, self.texture);
+        }
+    }
+
+    /// Binds the texture of the `index`th color attachment, see `multiple_targets`
+    pub fn bind_attachment(&self, index: usize) {
+        let texture = match index {
+            0 => self.texture,
+            _ => self.extra_textures[index - 1],
+        };
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, texture);
+        }
+    }
+
+    /// Copies the depth buffer into the default framebuffer's, both being `width` by `height`,
+    /// so that what is drawn there afterwards is hidden by what was drawn here
+    pub fn blit_depth(&self, width: i32, height: i32) {
+        unsafe {
+            glBindFramebuffer(GL_READ_FRAMEBUFFER, self.fbo);
+            glBindFramebuffer(GL_DRAW_FRAMEBUFFER, 0);
+            glBlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                GL_DEPTH_BUFFER_BIT,
+                GL_NEAREST,
+            );
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+
+    pub fn clear_binding() {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, 0);
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            glDeleteFramebuffers(1, &self.fbo);
+            glDeleteTextures(1, &self.texture);
+            if !self.extra_textures.is_empty() {
+                glDeleteTextures(
+                    self.extra_textures.len().try_into().unwrap(),
+                    self.extra_textures.as_ptr(),
+                );
+            }
+            if self.rbo != 0 {
+                glDeleteRenderbuffers(1, &self.rbo);
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use glm::{vec3, vec4, Mat4, TVec3, TVec4};
+
+use crate::bounds::{Aabb, BoundingSphere};
+
+/// The six planes of a camera's view volume, in world space
+pub struct Frustum {
+    /// `(normal, distance)` with normals pointing inside: left, right, bottom, top, near, far
+    planes: [TVec4<f32>; 6],
+    view_projection: Mat4,
+}
+
+impl Frustum {
+    /// Extracts the planes from a `projection * view` matrix (Gribb & Hartmann)
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| -> TVec4<f32> {
+            let r = view_projection.row(i);
+            vec4(r[0], r[1], r[2], r[3])
+        };
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+        for plane in planes.iter_mut() {
+            let length = plane.xyz().norm();
+            if length > 0.0 {
+                *plane /= length;
+            }
+        }
+
+        Self {
+            planes,
+            view_projection: *view_projection,
+        }
+    }
+
+    pub fn new(projection: &Mat4, view: &Mat4) -> Self {
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    /// Share of the viewport height covered by the sphere, infinite when the camera is inside
+    pub fn projected_size(&self, sphere: &BoundingSphere) -> f32 {
+        let c = sphere.center;
+        let depth = (self.view_projection * vec4(c.x, c.y, c.z, 1.0)).w;
+        if depth <= sphere.radius {
+            return f32::INFINITY;
+        }
+
+        // The view matrix doesn't scale, so the length of the second row is the vertical
+        // focal length of the projection
+        let row = self.view_projection.row(1);
+        let focal_length = vec3(row[0], row[1], row[2]).norm();
+        sphere.radius * focal_length / depth
+    }
+
+    fn distance(plane: &TVec4<f32>, point: &TVec3<f32>) -> f32 {
+        plane.x * point.x + plane.y * point.y + plane.z * point.z + plane.w
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        // written so that NaN planes, from a degenerate view matrix, never cull anything
+        !self
+            .planes
+            .iter()
+            .any(|plane| Frustum::distance(plane, &sphere.center) < -sphere.radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+
+        // only the corner furthest along the plane normal needs to be tested
+        !self.planes.iter().any(|plane| {
+            let corner = vec3(
+                if plane.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+            Frustum::distance(plane, &corner) < 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Camera at the origin looking down -z, with a 90 degree field of view
+    fn frustum() -> Frustum {
+        let projection = glm::perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0);
+        Frustum::new(&projection, &glm::identity())
+    }
+
+    fn cube_at(center: TVec3<f32>) -> Aabb {
+        Aabb {
+            min: center - vec3(0.5, 0.5, 0.5),
+            max: center + vec3(0.5, 0.5, 0.5),
+        }
+    }
+
+    #[test]
+    fn boxes_outside_are_rejected() {
+        let frustum = frustum();
+
+        assert!(frustum.intersects_aabb(&cube_at(vec3(0.0, 0.0, -5.0))));
+        // behind the camera, beside the view and past the far plane
+        assert!(!frustum.intersects_aabb(&cube_at(vec3(0.0, 0.0, 5.0))));
+        assert!(!frustum.intersects_aabb(&cube_at(vec3(20.0, 0.0, -5.0))));
+        assert!(!frustum.intersects_aabb(&cube_at(vec3(0.0, 0.0, -150.0))));
+        // straddling the left plane
+        assert!(frustum.intersects_aabb(&cube_at(vec3(-5.2, 0.0, -5.0))));
+        assert!(!frustum.intersects_aabb(&Aabb::empty()));
+    }
+
+    #[test]
+    fn spheres_outside_are_rejected() {
+        let frustum = frustum();
+        let sphere = |center, radius| BoundingSphere { center, radius };
+
+        assert!(frustum.intersects_sphere(&sphere(vec3(0.0, 0.0, -5.0), 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(vec3(0.0, 10.0, -5.0), 1.0)));
+        assert!(frustum.intersects_sphere(&sphere(vec3(0.0, 10.0, -5.0), 5.0)));
+    }
+
+    #[test]
+    fn projected_size_shrinks_with_distance() {
+        let frustum = frustum();
+        let size = |z| {
+            frustum.projected_size(&BoundingSphere {
+                center: vec3(0.0, 0.0, z),
+                radius: 1.0,
+            })
+        };
+
+        // the focal length of a 90 degree field of view is 1
+        assert!((size(-10.0) - 0.1).abs() < 1e-5);
+        assert!((size(-20.0) - 0.05).abs() < 1e-5);
+        assert_eq!(size(-0.5), f32::INFINITY);
+    }
+}
@@ -0,0 +1,624 @@
+use {
+    gl33::*,
+    glm::{vec2, vec3, Mat3, Mat4, Qua, TVec3},
+    gltf::{
+        animation::util::ReadOutputs,
+        image::{Data, Format, Source},
+        mesh::Mode,
+        Node, Primitive,
+    },
+    std::{borrow::Cow, path::Path},
+};
+
+use crate::{
+    animation_clip::{AnimationClip, JointChannel, MorphChannel},
+    import_options::ImportOptions,
+    keyframes::{Interpolation, Keyframes},
+    material::{Material, PbrParameters},
+    mesh::{Mesh, Vertex},
+    model::Model,
+    morph::MorphTarget,
+    normals::generate_normals,
+    optimize::{optimize_mesh, optimize_triangle_order},
+    skeleton::{Joint, JointTransform, Skeleton},
+    tangents::generate_tangents,
+    texture::{Texture2D, TextureType},
+    utils::IDENTITY_MAT4,
+};
+
+impl Model {
+    pub(crate) fn load_gltf(&mut self, path: &str, options: &ImportOptions) {
+        let path = Path::new(path);
+
+        self.directory = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_str()
+            .unwrap()
+            .into();
+
+        let (document, buffers, images) = gltf::import(path)
+            .unwrap_or_else(|e| panic!("filename: \"{}\": {}", path.to_str().unwrap(), e));
+
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .unwrap_or_else(|| panic!("no scene in \"{}\"", path.to_str().unwrap()));
+
+        let mut node_meshes: Vec<Vec<usize>> = vec![Vec::new(); document.nodes().len()];
+        for node in scene.nodes() {
+            self.load_gltf_node(
+                &node,
+                &IDENTITY_MAT4,
+                &document,
+                &buffers,
+                &images,
+                options,
+                &mut node_meshes,
+            );
+        }
+
+        let joint_nodes = self.load_gltf_skins(&document, &buffers);
+        self.load_gltf_animations(&document, &buffers, &joint_nodes, &node_meshes);
+    }
+
+    /// Every skin goes into the one skeleton of the model, one after the other, the joints
+    /// keeping the order of their skin which is what the vertices refer to (see
+    /// `skin_joint_offset`). Returns the node of each joint, none without skins.
+    fn load_gltf_skins(
+        &mut self,
+        document: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+    ) -> Vec<usize> {
+        let mut parents: Vec<Option<usize>> = vec![None; document.nodes().len()];
+        for node in document.nodes() {
+            for child in node.children() {
+                parents[child.index()] = Some(node.index());
+            }
+        }
+        let local_transform =
+            |node: usize| Mat4::from(document.nodes().nth(node).unwrap().transform().matrix());
+
+        let mut joints = Vec::new();
+        let mut joint_nodes = Vec::new();
+        for skin in document.skins() {
+            let first_joint = joints.len();
+            let skin_nodes: Vec<usize> = skin.joints().map(|node| node.index()).collect();
+
+            let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+            let inverse_bind_matrices: Vec<Mat4> = reader
+                .read_inverse_bind_matrices()
+                .map(|m| m.map(Mat4::from).collect())
+                .unwrap_or_else(|| vec![*IDENTITY_MAT4; skin_nodes.len()]);
+
+            for (node, inverse_bind_matrix) in skin.joints().zip(inverse_bind_matrices) {
+                // The nodes between a joint and its parent joint, or above a root joint, still
+                // move it
+                let mut offset = *IDENTITY_MAT4;
+                let mut parent = None;
+                let mut ancestor = parents[node.index()];
+                while let Some(a) = ancestor {
+                    parent = skin_nodes.iter().position(|&j| j == a);
+                    if parent.is_some() {
+                        break;
+                    }
+                    offset = local_transform(a) * offset;
+                    ancestor = parents[a];
+                }
+
+                let (translation, rotation, scale) = node.transform().decomposed();
+                joints.push(Joint {
+                    parent: parent.map(|parent| first_joint + parent),
+                    offset,
+                    rest: JointTransform {
+                        translation: translation.into(),
+                        rotation: quat_from_xyzw(rotation),
+                        scale: scale.into(),
+                    },
+                    inverse_bind_matrix,
+                });
+            }
+            joint_nodes.extend(skin_nodes);
+        }
+        if !joints.is_empty() {
+            self.skeleton = Some(Skeleton::new(joints));
+        }
+
+        joint_nodes
+    }
+
+    /// Keeps the channels animating joints of the skeleton or morph target weights,
+    /// `node_meshes` being the meshes made from each node.
+    fn load_gltf_animations(
+        &mut self,
+        document: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        joint_nodes: &[usize],
+        node_meshes: &[Vec<usize>],
+    ) {
+        for animation in document.animations() {
+            let mut clip = AnimationClip {
+                name: animation.name().unwrap_or("").to_owned(),
+                channels: Vec::new(),
+                morph_channels: Vec::new(),
+            };
+
+            for channel in animation.channels() {
+                let node = channel.target().node().index();
+                let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+                let (times, outputs) = match (reader.read_inputs(), reader.read_outputs()) {
+                    (Some(times), Some(outputs)) => (times.collect::<Vec<f32>>(), outputs),
+                    _ => continue,
+                };
+                let interpolation = match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+
+                if let ReadOutputs::MorphTargetWeights(weights) = outputs {
+                    if node_meshes[node].is_empty() {
+                        continue;
+                    }
+
+                    // Weights come as a flat list, one per target for each value
+                    let weights: Vec<f32> = weights.into_f32().collect();
+                    let value_count = times.len() * interpolation.values_per_key();
+                    if value_count == 0
+                        || weights.is_empty()
+                        || !weights.len().is_multiple_of(value_count)
+                    {
+                        println!(
+                            "Warning: skipping morph weights channel of animation \"{}\", \
+                             {} weights for {} keyframes",
+                            clip.name,
+                            weights.len(),
+                            times.len()
+                        );
+                        continue;
+                    }
+                    let target_count = weights.len() / value_count;
+                    let values = weights
+                        .chunks_exact(target_count)
+                        .map(<[f32]>::to_vec)
+                        .collect();
+
+                    clip.morph_channels.push(MorphChannel {
+                        meshes: node_meshes[node].clone(),
+                        weights: Keyframes::new(interpolation, times, values),
+                    });
+                    continue;
+                }
+
+                // Skins sharing a node each have a joint for it
+                let joints: Vec<usize> = (0..joint_nodes.len())
+                    .filter(|&joint| joint_nodes[joint] == node)
+                    .collect();
+                if joints.is_empty() {
+                    continue;
+                }
+
+                let set_keyframes: Box<dyn Fn(&mut JointChannel)> = match outputs {
+                    ReadOutputs::Translations(values) => {
+                        let values = values.map(TVec3::from).collect();
+                        let keyframes = Keyframes::new(interpolation, times, values);
+                        Box::new(move |c| c.translation = Some(keyframes.clone()))
+                    }
+                    ReadOutputs::Rotations(values) => {
+                        let values = values.into_f32().map(quat_from_xyzw).collect();
+                        let keyframes = Keyframes::new(interpolation, times, values);
+                        Box::new(move |c| c.rotation = Some(keyframes.clone()))
+                    }
+                    ReadOutputs::Scales(values) => {
+                        let values = values.map(TVec3::from).collect();
+                        let keyframes = Keyframes::new(interpolation, times, values);
+                        Box::new(move |c| c.scale = Some(keyframes.clone()))
+                    }
+                    ReadOutputs::MorphTargetWeights(_) => unreachable!(),
+                };
+
+                for joint in joints {
+                    let index = match clip.channels.iter().position(|c| c.joint == joint) {
+                        Some(index) => index,
+                        None => {
+                            clip.channels.push(JointChannel {
+                                joint,
+                                translation: None,
+                                rotation: None,
+                                scale: None,
+                            });
+                            clip.channels.len() - 1
+                        }
+                    };
+                    set_keyframes(&mut clip.channels[index]);
+                }
+            }
+
+            if !clip.channels.is_empty() || !clip.morph_channels.is_empty() {
+                self.animations.push(clip);
+            }
+        }
+    }
+
+    /// Walks the node hierarchy, baking each node's world transform into the vertices of the
+    /// meshes it references.
+    fn load_gltf_node(
+        &mut self,
+        node: &Node,
+        parent_transform: &Mat4,
+        document: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        images: &[Data],
+        options: &ImportOptions,
+        node_meshes: &mut [Vec<usize>],
+    ) {
+        let transform = parent_transform * Mat4::from(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            // Skinned vertices are placed by the joints alone
+            let mesh_transform = if node.skin().is_some() {
+                *IDENTITY_MAT4
+            } else {
+                transform
+            };
+            let joint_offset = node
+                .skin()
+                .map_or(0, |skin| skin_joint_offset(document, &skin));
+
+            for primitive in mesh.primitives() {
+                if primitive.mode() != Mode::Triangles {
+                    println!(
+                        "Skipping primitive {} of mesh \"{}\": only triangles are supported",
+                        primitive.index(),
+                        mesh.name().unwrap_or("")
+                    );
+                    continue;
+                }
+
+                let name = format!("{}#{}", mesh.name().unwrap_or(""), primitive.index());
+                let mesh = self.load_gltf_primitive(
+                    &name,
+                    &primitive,
+                    &mesh_transform,
+                    mesh.weights().unwrap_or(&[]),
+                    joint_offset,
+                    document,
+                    buffers,
+                    images,
+                    options,
+                );
+                node_meshes[node.index()].push(self.meshes.len());
+                self.meshes.push(mesh);
+            }
+        }
+
+        for child in node.children() {
+            self.load_gltf_node(
+                &child,
+                &transform,
+                document,
+                buffers,
+                images,
+                options,
+                node_meshes,
+            );
+        }
+    }
+
+    fn load_gltf_primitive(
+        &mut self,
+        name: &str,
+        primitive: &Primitive,
+        transform: &Mat4,
+        morph_weights: &[f32],
+        joint_offset: u32,
+        document: &gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        images: &[Data],
+        options: &ImportOptions,
+    ) -> Mesh {
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let normal_matrix: Mat3 = glm::mat4_to_mat3(&glm::transpose(&glm::inverse(transform)));
+
+        let material = primitive.material();
+        let pbr = material.pbr_metallic_roughness();
+        let base_color = pbr.base_color_texture();
+        let lightmap = gltf_lightmap(document, &material);
+
+        let positions: Vec<[f32; 3]> = reader
+            .read_positions()
+            .expect("glTF primitive without positions")
+            .collect();
+        let normals: Vec<[f32; 3]> = reader
+            .read_normals()
+            .map(|n| n.collect())
+            .unwrap_or_default();
+        let tangents: Vec<[f32; 4]> = reader
+            .read_tangents()
+            .map(|t| t.collect())
+            .unwrap_or_default();
+
+        // Every TEXCOORD_n set of the primitive is read, the one referenced by the base color
+        // texture ends up in the vertices.
+        let mut tex_coord_sets: Vec<Vec<[f32; 2]>> = Vec::new();
+        while let Some(t) = reader.read_tex_coords(tex_coord_sets.len() as u32) {
+            tex_coord_sets.push(t.into_f32().collect());
+        }
+        let uv_set = base_color.as_ref().map_or(0, |info| info.tex_coord()) as usize;
+        let tex_coords = tex_coord_sets
+            .get(uv_set)
+            .or_else(|| tex_coord_sets.first());
+        // The second UV channel gets the lightmap's set, or else the first other one
+        let tex_coords2 = match &lightmap {
+            Some((_, set)) => tex_coord_sets.get(*set),
+            None => (0..tex_coord_sets.len())
+                .find(|&set| set != uv_set)
+                .map(|set| &tex_coord_sets[set]),
+        };
+        let colors: Vec<[f32; 4]> = reader
+            .read_colors(0)
+            .map(|c| c.into_rgba_f32().collect())
+            .unwrap_or_default();
+        let joints: Vec<[u16; 4]> = reader
+            .read_joints(0)
+            .map(|j| j.into_u16().collect())
+            .unwrap_or_default();
+        let weights: Vec<[f32; 4]> = reader
+            .read_weights(0)
+            .map(|w| w.into_f32().collect())
+            .unwrap_or_default();
+
+        // Deltas go through the node's transform like the vertices, minus the translation
+        let mut morph_targets: Vec<MorphTarget> = reader
+            .read_morph_targets()
+            .map(|(position_deltas, normal_deltas, _)| MorphTarget {
+                position_deltas: match position_deltas {
+                    Some(deltas) => deltas
+                        .map(|d| (transform * glm::vec4(d[0], d[1], d[2], 0.0)).xyz())
+                        .collect(),
+                    None => vec![vec3(0.0, 0.0, 0.0); positions.len()],
+                },
+                normal_deltas: normal_deltas
+                    .map(|deltas| {
+                        deltas
+                            .map(|d| normal_matrix * vec3(d[0], d[1], d[2]))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        let mut indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(positions.len());
+        for (i, p) in positions.iter().enumerate() {
+            let position = transform * glm::vec4(p[0], p[1], p[2], 1.0);
+            let normal = normals
+                .get(i)
+                .map(|n| glm::normalize(&(normal_matrix * vec3(n[0], n[1], n[2]))))
+                .unwrap_or_else(|| vec3(0.0, 0.0, 0.0));
+            let tex_coord = tex_coords
+                .and_then(|t| t.get(i))
+                .map(|t| vec2(t[0], t[1]))
+                .unwrap_or_else(|| vec2(0.0, 0.0));
+            let tex_coord2 = tex_coords2
+                .and_then(|t| t.get(i))
+                .map(|t| vec2(t[0], t[1]))
+                .unwrap_or_else(|| vec2(0.0, 0.0));
+            let color = colors
+                .get(i)
+                .map(|c| glm::vec4(c[0], c[1], c[2], c[3]))
+                .unwrap_or_else(|| glm::vec4(1.0, 1.0, 1.0, 1.0));
+            let (joint, weight) = match (joints.get(i), weights.get(i)) {
+                (Some(j), Some(w)) => (
+                    glm::vec4(j[0] as f32, j[1] as f32, j[2] as f32, j[3] as f32)
+                        .add_scalar(joint_offset as f32),
+                    glm::vec4(w[0], w[1], w[2], w[3]),
+                ),
+                _ => (glm::vec4(0.0, 0.0, 0.0, 0.0), glm::vec4(0.0, 0.0, 0.0, 0.0)),
+            };
+
+            // glTF tangents carry the bitangent handedness in w
+            let (tangent, bitangent) = match tangents.get(i) {
+                Some(t) => {
+                    let tangent =
+                        glm::normalize(&(transform * glm::vec4(t[0], t[1], t[2], 0.0)).xyz());
+                    (tangent, glm::cross(&normal, &tangent) * t[3])
+                }
+                None => (vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0)),
+            };
+
+            vertices.push(Vertex {
+                position: position.xyz(),
+                normal,
+                tex_coords: tex_coord,
+                tangent,
+                bitangent,
+                color,
+                tex_coords2: tex_coord2,
+                joints: joint,
+                weights: weight,
+            });
+        }
+
+        if normals.is_empty() {
+            let sources = generate_normals(&mut vertices, &mut indices, options.crease_angle);
+            for target in &mut morph_targets {
+                target.remap(&sources);
+            }
+        }
+        if normals.is_empty() || tangents.is_empty() {
+            let sources = generate_tangents(&mut vertices, &mut indices);
+            for target in &mut morph_targets {
+                target.remap(&sources);
+            }
+        }
+        // Merging and reordering the vertices would take them away from their morph target
+        // deltas, only the triangles are reordered then
+        if options.optimize && morph_targets.is_empty() {
+            let stats = optimize_mesh(&mut vertices, &mut indices);
+            println!("Optimized mesh \"{}\": {}", name, stats);
+        } else if options.optimize {
+            indices = optimize_triangle_order(&vertices, &indices);
+        }
+
+        let mut textures = Vec::new();
+        if let Some(info) = base_color {
+            let texture = self.load_gltf_texture(info.texture(), images, TextureType::Diffuse);
+            textures.push(texture);
+        }
+        if let Some(info) = material.emissive_texture() {
+            let texture = self.load_gltf_texture(info.texture(), images, TextureType::Emission);
+            textures.push(texture);
+        }
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            let texture =
+                self.load_gltf_texture(info.texture(), images, TextureType::MetallicRoughness);
+            textures.push(texture);
+        }
+        if let Some(occlusion) = material.occlusion_texture() {
+            let texture =
+                self.load_gltf_texture(occlusion.texture(), images, TextureType::Occlusion);
+            textures.push(texture);
+        }
+        if let Some(normal_texture) = material.normal_texture() {
+            let texture =
+                self.load_gltf_texture(normal_texture.texture(), images, TextureType::Normal);
+            textures.push(texture);
+        }
+        if let Some((texture, _)) = lightmap {
+            let texture = self.load_gltf_texture(texture, images, TextureType::Lightmap);
+            textures.push(texture);
+        }
+        let base_color_factor = pbr.base_color_factor();
+        let mut material = Material {
+            name: material.name().unwrap_or("").to_owned(),
+            diffuse: vec3(
+                base_color_factor[0],
+                base_color_factor[1],
+                base_color_factor[2],
+            ),
+            dissolve: base_color_factor[3],
+            pbr: Some(PbrParameters {
+                base_color: base_color_factor.into(),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                occlusion_strength: material.occlusion_texture().map_or(1.0, |o| o.strength()),
+                emissive: material.emissive_factor().into(),
+            }),
+            ..Material::with_textures(textures)
+        };
+        self.add_default_textures(&mut material);
+
+        let mut mesh = Mesh::with_lods(
+            vertices,
+            indices,
+            material,
+            &options.lod_ratios,
+            options.optimize,
+        );
+        if !morph_targets.is_empty() {
+            mesh.set_morph_targets(morph_targets, morph_weights.to_vec());
+        }
+        mesh
+    }
+
+    /// Images are decoded by the importer whether they are embedded in a buffer view or
+    /// referenced by URI, they are cached by URI or by image index for embedded ones.
+    fn load_gltf_texture(
+        &mut self,
+        texture: gltf::Texture,
+        images: &[Data],
+        ty: TextureType,
+    ) -> Texture2D {
+        let image = texture.source();
+        let key = match image.source() {
+            Source::Uri { uri, .. } => uri.to_owned(),
+            Source::View { .. } => format!("#image{}", image.index()),
+        };
+        let data = &images[image.index()];
+
+        self.cached_texture(&key, ty.clone(), || {
+            let (pixels, format) = gltf_pixels(data);
+            Texture2D::from_pixels(&pixels, data.width, data.height, format, &key, ty)
+        })
+    }
+}
+
+/// Index of the first joint of `skin` in the model's skeleton, the joints of the skins before it
+/// coming first
+fn skin_joint_offset(document: &gltf::Document, skin: &gltf::Skin) -> u32 {
+    document
+        .skins()
+        .take(skin.index())
+        .map(|skin| skin.joints().len() as u32)
+        .sum()
+}
+
+/// glTF has no lightmaps, materials can reference one in their extras the way they reference
+/// their other maps: `"extras": { "lightmap": { "index": 2, "texCoord": 1 } }`. Returns the
+/// texture and its UV set.
+fn gltf_lightmap<'a>(
+    document: &'a gltf::Document,
+    material: &gltf::Material,
+) -> Option<(gltf::Texture<'a>, usize)> {
+    let extras: serde_json::Value = serde_json::from_str(material.extras().as_ref()?.get()).ok()?;
+    let info = extras.get("lightmap")?;
+
+    let texture = info
+        .get("index")
+        .and_then(serde_json::Value::as_u64)
+        .and_then(|index| document.textures().nth(index as usize));
+    if texture.is_none() {
+        println!(
+            "Warning: invalid lightmap in material \"{}\": {}",
+            material.name().unwrap_or(""),
+            info
+        );
+    }
+    let tex_coord = info
+        .get("texCoord")
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0);
+
+    Some((texture?, tex_coord as usize))
+}
+
+/// glTF quaternions are stored as (x, y, z, w)
+fn quat_from_xyzw(q: [f32; 4]) -> Qua<f32> {
+    Qua::new(q[3], q[0], q[1], q[2])
+}
+
+/// 8-bit pixels of the image and their format, 16-bit and float images are brought down to
+/// 8 bits per channel since textures are uploaded as unsigned bytes
+fn gltf_pixels(data: &Data) -> (Cow<'_, [u8]>, GLenum) {
+    let format = match data.format {
+        Format::R8 | Format::R16 => GL_RED,
+        Format::R8G8 | Format::R16G16 => GL_RG,
+        Format::R8G8B8 | Format::R16G16B16 | Format::R32G32B32FLOAT => GL_RGB,
+        Format::R8G8B8A8 | Format::R16G16B16A16 | Format::R32G32B32A32FLOAT => GL_RGBA,
+    };
+
+    // the importer keeps the decoded channels in native byte order
+    let pixels = match data.format {
+        Format::R16 | Format::R16G16 | Format::R16G16B16 | Format::R16G16B16A16 => data
+            .pixels
+            .chunks_exact(2)
+            .map(|c| ((u16::from_ne_bytes([c[0], c[1]]) as u32 * 255 + 32767) / 65535) as u8)
+            .collect(),
+        Format::R32G32B32FLOAT | Format::R32G32B32A32FLOAT => data
+            .pixels
+            .chunks_exact(4)
+            .map(|c| {
+                let value = f32::from_ne_bytes([c[0], c[1], c[2], c[3]]);
+                (value.clamp(0.0, 1.0) * 255.0).round() as u8
+            })
+            .collect(),
+        _ => Cow::Borrowed(data.pixels.as_slice()),
+    };
+
+    (pixels, format)
+}
@@ -0,0 +1,28 @@
+#[derive(Clone)]
+pub struct ImportOptions {
+    /// Maximum angle in degrees between two faces for their normals to be smoothed together
+    /// when a mesh has to get its normals generated. `0.0` gives flat shading, `180.0` smooths
+    /// everything.
+    pub crease_angle: f32,
+    /// Triangle ratios of the simplified levels of detail generated for every mesh, from the
+    /// most to the least detailed, such as `[0.5, 0.25, 0.125]`. Empty by default, which
+    /// skips the generation.
+    pub lod_ratios: Vec<f32>,
+    /// Merges duplicate vertices and reorders the meshes for the vertex cache, overdraw and
+    /// vertex fetch, printing the gains for each mesh
+    pub optimize: bool,
+    /// Loads models from the cooked file written next to them after their first import, as
+    /// long as neither the source files nor these options changed since
+    pub use_cooked: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            crease_angle: 60.0,
+            lod_ratios: Vec::new(),
+            optimize: false,
+            use_cooked: true,
+        }
+    }
+}
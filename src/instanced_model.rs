@@ -0,0 +1,138 @@
+use {
+    gl33::{global_loader::*, *},
+    glm::{vec4, Mat4, TVec4},
+    std::mem::size_of,
+};
+
+use crate::{
+    draw::{Draw, DrawOptions},
+    macros::*,
+    model::Model,
+    shader_program::ShaderProgram,
+    utils::IDENTITY_MAT4,
+    vertex_objects::{BufferType, VBO},
+};
+
+/// Per-instance data, read by the shaders through the attributes 10 to 13 (model matrix
+/// columns) and 14 (color multiplied with the material)
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Instance {
+    pub model: Mat4,
+    pub color: TVec4<f32>,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            model: *IDENTITY_MAT4,
+            color: vec4(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Instance {
+    /// Describes the layout of `Instance` to the currently bound VAO, reading from the
+    /// currently bound array buffer and advancing once per instance.
+    pub fn setup_attributes() {
+        unsafe {
+            let stride: i32 = size_of::<Instance>().try_into().unwrap();
+
+            // A mat4 attribute takes four consecutive locations, one per column
+            for column in 0..4 {
+                let location = 10 + column;
+                glEnableVertexAttribArray(location);
+                glVertexAttribPointer(
+                    location,
+                    4,
+                    GL_FLOAT,
+                    0,
+                    stride,
+                    (offset_of!(Instance, model) + column as usize * size_of::<TVec4<f32>>())
+                        as *const _,
+                );
+                glVertexAttribDivisor(location, 1);
+            }
+
+            glEnableVertexAttribArray(14);
+            glVertexAttribPointer(
+                14,
+                4,
+                GL_FLOAT,
+                0,
+                stride,
+                offset_of!(Instance, color) as *const _,
+            );
+            glVertexAttribDivisor(14, 1);
+        }
+    }
+}
+
+/// Draws many copies of a single model in one draw call per mesh, shaders have to check the
+/// `instanced` uniform to use the instance attributes instead of the `model` uniform.
+pub struct InstancedModel {
+    model: Model,
+    instances: Vec<Instance>,
+    instance_vbo: VBO,
+}
+
+impl InstancedModel {
+    pub fn new(model: Model) -> Self {
+        let instanced = Self {
+            model,
+            instances: Vec::new(),
+            instance_vbo: VBO::new(BufferType::Array),
+        };
+
+        for mesh in &instanced.model.meshes {
+            mesh.setup_instance_attributes(&instanced.instance_vbo);
+        }
+
+        instanced
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn instances(&self) -> &[Instance] {
+        &self.instances
+    }
+
+    /// Replaces every instance and uploads them, the buffer is reallocated each time so the
+    /// driver doesn't have to wait for the previous frame to be done with it.
+    pub fn set_instances(&mut self, instances: Vec<Instance>) {
+        self.instances = instances;
+
+        self.instance_vbo.bind();
+        unsafe {
+            glBufferData(
+                GL_ARRAY_BUFFER,
+                (self.instances.len() * size_of::<Instance>())
+                    .try_into()
+                    .unwrap(),
+                self.instances.as_ptr().cast(),
+                GL_STREAM_DRAW,
+            );
+        }
+        VBO::clear_binding();
+    }
+}
+
+impl Draw for InstancedModel {
+    fn draw(&self, shader: &ShaderProgram) {
+        self.draw_with(shader, &DrawOptions::default());
+    }
+
+    fn draw_with(&self, shader: &ShaderProgram, options: &DrawOptions) {
+        if self.instances.is_empty() {
+            return;
+        }
+
+        shader.set_int("instanced", 1);
+        for mesh in &self.model.meshes {
+            mesh.draw_instanced(shader, options, self.instances.len());
+        }
+        shader.set_int("instanced", 0);
+    }
+}
@@ -0,0 +1,263 @@
+use glm::{Qua, TVec3};
+
+/// How values are interpolated between two keyframes, the first three as defined by glTF
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    /// Holds the value of the previous keyframe
+    Step,
+    Linear,
+    /// Cubic Hermite spline, each keyframe has an in-tangent and an out-tangent
+    CubicSpline,
+    /// Cubic Bezier curve, each keyframe has an incoming and an outgoing control point
+    Bezier,
+}
+
+impl Interpolation {
+    /// Values stored for each keyframe, the curves keep theirs around the keyframe's own
+    pub fn values_per_key(self) -> usize {
+        match self {
+            Interpolation::CubicSpline | Interpolation::Bezier => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// Values that keyframes can be interpolated between
+pub trait Interpolate: Clone {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+
+    /// Hermite curve from `v0` to `v1`, the tangents are per second and get scaled by the
+    /// `span` of time between both keyframes
+    fn hermite(
+        v0: &Self,
+        out_tangent: &Self,
+        v1: &Self,
+        in_tangent: &Self,
+        t: f32,
+        span: f32,
+    ) -> Self;
+}
+
+impl Interpolate for TVec3<f32> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        glm::lerp(self, other, t)
+    }
+
+    fn hermite(
+        v0: &Self,
+        out_tangent: &Self,
+        v1: &Self,
+        in_tangent: &Self,
+        t: f32,
+        span: f32,
+    ) -> Self {
+        let [h00, h10, h01, h11] = hermite_basis(t, span);
+        v0 * h00 + out_tangent * h10 + v1 * h01 + in_tangent * h11
+    }
+}
+
+impl Interpolate for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+
+    fn hermite(
+        v0: &Self,
+        out_tangent: &Self,
+        v1: &Self,
+        in_tangent: &Self,
+        t: f32,
+        span: f32,
+    ) -> Self {
+        let [h00, h10, h01, h11] = hermite_basis(t, span);
+        v0 * h00 + out_tangent * h10 + v1 * h01 + in_tangent * h11
+    }
+}
+
+/// Rotations are blended with a normalized lerp along the shortest path, close enough to a
+/// slerp between keyframes and stable when both are opposite.
+impl Interpolate for Qua<f32> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let other = if glm::quat_dot(self, other) < 0.0 {
+            -other
+        } else {
+            *other
+        };
+        glm::quat_normalize(&Qua::from(glm::lerp(&self.coords, &other.coords, t)))
+    }
+
+    fn hermite(
+        v0: &Self,
+        out_tangent: &Self,
+        v1: &Self,
+        in_tangent: &Self,
+        t: f32,
+        span: f32,
+    ) -> Self {
+        let [h00, h10, h01, h11] = hermite_basis(t, span);
+        let coords =
+            v0.coords * h00 + out_tangent.coords * h10 + v1.coords * h01 + in_tangent.coords * h11;
+        glm::quat_normalize(&Qua::from(coords))
+    }
+}
+
+/// Morph target weights, one per target
+impl Interpolate for Vec<f32> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self.iter()
+            .zip(other)
+            .map(|(a, b)| a + (b - a) * t)
+            .collect()
+    }
+
+    fn hermite(
+        v0: &Self,
+        out_tangent: &Self,
+        v1: &Self,
+        in_tangent: &Self,
+        t: f32,
+        span: f32,
+    ) -> Self {
+        let [h00, h10, h01, h11] = hermite_basis(t, span);
+        (0..v0.len())
+            .map(|i| v0[i] * h00 + out_tangent[i] * h10 + v1[i] * h01 + in_tangent[i] * h11)
+            .collect()
+    }
+}
+
+/// Weights of the start value, out-tangent, end value and in-tangent
+fn hermite_basis(t: f32, span: f32) -> [f32; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    [
+        2.0 * t3 - 3.0 * t2 + 1.0,
+        (t3 - 2.0 * t2 + t) * span,
+        -2.0 * t3 + 3.0 * t2,
+        (t3 - t2) * span,
+    ]
+}
+
+/// Values of a property over time, `times` being sorted in seconds
+#[derive(Clone)]
+pub struct Keyframes<T> {
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    /// One value per keyframe, or three for the curves: (in-tangent, value, out-tangent) for
+    /// cubic splines and (in control point, value, out control point) for Bezier curves
+    pub values: Vec<T>,
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    pub fn new(interpolation: Interpolation, times: Vec<f32>, values: Vec<T>) -> Self {
+        assert!(
+            !times.is_empty() && values.len() == times.len() * interpolation.values_per_key(),
+            "{} keyframe values for {} keyframes",
+            values.len(),
+            times.len()
+        );
+
+        Self {
+            interpolation,
+            times,
+            values,
+        }
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        *self.times.last().unwrap()
+    }
+
+    fn value(&self, key: usize) -> &T {
+        let per_key = self.interpolation.values_per_key();
+        &self.values[key * per_key + per_key / 2]
+    }
+
+    /// Values before the first keyframe and after the last one are held
+    pub fn sample(&self, time: f32) -> T {
+        let next = self.times.partition_point(|&t| t <= time);
+        if next == 0 {
+            return self.value(0).clone();
+        }
+        if next == self.times.len() {
+            return self.value(next - 1).clone();
+        }
+
+        let previous = next - 1;
+        let span = self.times[next] - self.times[previous];
+        let t = (time - self.times[previous]) / span;
+
+        match self.interpolation {
+            Interpolation::Step => self.value(previous).clone(),
+            Interpolation::Linear => self.value(previous).lerp(self.value(next), t),
+            Interpolation::CubicSpline => T::hermite(
+                self.value(previous),
+                &self.values[previous * 3 + 2],
+                self.value(next),
+                &self.values[next * 3],
+                t,
+                span,
+            ),
+            Interpolation::Bezier => {
+                // De Casteljau's construction only needs lerps, rotations stay normalized
+                let points = [
+                    self.value(previous),
+                    &self.values[previous * 3 + 2],
+                    &self.values[next * 3],
+                    self.value(next),
+                ];
+                let [a, b, c] = [0, 1, 2].map(|i| points[i].lerp(points[i + 1], t));
+                let (d, e) = (a.lerp(&b, t), b.lerp(&c, t));
+                d.lerp(&e, t)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    /// Keyframes at 1 and 3 seconds going from 0 to 10, with the curves' extra values
+    /// around each of them
+    fn keyframes(interpolation: Interpolation) -> Keyframes<f32> {
+        let values = match interpolation {
+            Interpolation::Step | Interpolation::Linear => vec![0.0, 10.0],
+            // Leaves the first keyframe at 8 per second and reaches the second one flat
+            Interpolation::CubicSpline => vec![0.0, 0.0, 8.0, 0.0, 10.0, 0.0],
+            Interpolation::Bezier => vec![0.0, 0.0, 8.0, 8.0, 10.0, 10.0],
+        };
+        Keyframes::new(interpolation, vec![1.0, 3.0], values)
+    }
+
+    #[test]
+    fn edges_are_held() {
+        for interpolation in [
+            Interpolation::Step,
+            Interpolation::Linear,
+            Interpolation::CubicSpline,
+            Interpolation::Bezier,
+        ] {
+            let keyframes = keyframes(interpolation);
+            assert_close(keyframes.sample(0.0), 0.0);
+            assert_close(keyframes.sample(1.0), 0.0);
+            assert_close(keyframes.sample(3.0), 10.0);
+            assert_close(keyframes.sample(5.0), 10.0);
+            assert_close(keyframes.duration(), 3.0);
+        }
+    }
+
+    #[test]
+    fn midpoints() {
+        assert_close(keyframes(Interpolation::Step).sample(2.0), 0.0);
+        assert_close(keyframes(Interpolation::Linear).sample(2.0), 5.0);
+        assert_close(keyframes(Interpolation::Linear).sample(1.5), 2.5);
+        // 10 * h01 + 8 * h10 * span = 5 + 8 * 0.125 * 2
+        assert_close(keyframes(Interpolation::CubicSpline).sample(2.0), 7.0);
+        // (0 + 3 * 8 + 3 * 8 + 10) / 8
+        assert_close(keyframes(Interpolation::Bezier).sample(2.0), 7.25);
+    }
+}
@@ -1,75 +1,285 @@
-use glm::TVec3;
-
-use crate::shader_program::ShaderProgram;
-
-pub struct DirectionalLight {
-    pub direction: TVec3<f32>,
-    pub ambient: TVec3<f32>,
-    pub diffuse: TVec3<f32>,
-    pub specular: TVec3<f32>,
-}
-
-impl DirectionalLight {
-    pub fn new(
-        direction: TVec3<f32>,
-        ambient: TVec3<f32>,
-        diffuse: TVec3<f32>,
-        specular: TVec3<f32>,
-    ) -> Self {
-        Self {
-            direction,
-            ambient,
-            diffuse,
-            specular,
-        }
-    }
-
-    pub fn set_into_shader(&self, shader: &ShaderProgram, name: &str) {
-        shader.set_vec3(format!("{}{}", name, ".direction").as_str(), self.direction);
-        shader.set_vec3(format!("{}{}", name, ".ambient").as_str(), self.ambient);
-        shader.set_vec3(format!("{}{}", name, ".diffuse").as_str(), self.diffuse);
-        shader.set_vec3(format!("{}{}", name, ".specular").as_str(), self.specular);
-    }
-}
-
-pub struct PointLight {
-    pub position: TVec3<f32>,
-    constant: f32,
-    linear: f32,
-    quadratic: f32,
-    pub ambient: TVec3<f32>,
-    diffuse: TVec3<f32>,
-    specular: TVec3<f32>,
-}
-
-impl PointLight {
-    pub fn new(
-        position: TVec3<f32>,
-        ambient: TVec3<f32>,
-        diffuse: TVec3<f32>,
-        specular: TVec3<f32>,
-        constant: f32,
-        linear: f32,
-        quadratic: f32,
-    ) -> Self {
-        Self {
-            position,
-            ambient,
-            diffuse,
-            specular,
-            constant,
-            linear,
-            quadratic,
-        }
-    }
-
-    pub fn set_into_shader(&self, shader: &ShaderProgram, name: &str) {
-        shader.set_vec3(format!("{}{}", name, ".position").as_str(), self.position);
-        shader.set_float(format!("{}{}", name, ".constant").as_str(), self.constant);
-        shader.set_float(format!("{}{}", name, ".linear").as_str(), self.linear);
-        shader.set_float(format!("{}{}", name, ".quadratic").as_str(), self.quadratic);
-        shader.set_vec3(format!("{}{}", name, ".ambient").as_str(), self.ambient);
-        shader.set_vec3(format!("{}{}", name, ".diffuse").as_str(), self.diffuse);
-        shader.set_vec3(format!("{}{}", name, ".specular").as_str(), self.specular);
-    }
-}
+use glm::TVec3;
+
+use crate::{camera::Camera, shader_program::ShaderProgram, utils::to_radians};
+
+/// Sizes of the light arrays of the lit shaders
+pub const MAX_DIR_LIGHTS: usize = 4;
+pub const MAX_POINT_LIGHTS: usize = 16;
+/// Includes the flashlight
+pub const MAX_SPOT_LIGHTS: usize = 8;
+/// Point lights casting shadows at once, each one takes a texture unit
+pub const MAX_POINT_SHADOWS: usize = 4;
+
+pub struct DirectionalLight {
+    pub direction: TVec3<f32>,
+    pub ambient: TVec3<f32>,
+    pub diffuse: TVec3<f32>,
+    pub specular: TVec3<f32>,
+}
+
+impl DirectionalLight {
+    pub fn new(
+        direction: TVec3<f32>,
+        ambient: TVec3<f32>,
+        diffuse: TVec3<f32>,
+        specular: TVec3<f32>,
+    ) -> Self {
+        Self {
+            direction,
+            ambient,
+            diffuse,
+            specular,
+        }
+    }
+
+    pub fn set_into_shader(&self, shader: &ShaderProgram, name: &str) {
+        shader.set_vec3(format!("{}{}", name, ".direction").as_str(), self.direction);
+        shader.set_vec3(format!("{}{}", name, ".ambient").as_str(), self.ambient);
+        shader.set_vec3(format!("{}{}", name, ".diffuse").as_str(), self.diffuse);
+        shader.set_vec3(format!("{}{}", name, ".specular").as_str(), self.specular);
+    }
+}
+
+pub struct PointLight {
+    pub position: TVec3<f32>,
+    constant: f32,
+    linear: f32,
+    quadratic: f32,
+    pub ambient: TVec3<f32>,
+    diffuse: TVec3<f32>,
+    specular: TVec3<f32>,
+    /// Only the first `MAX_POINT_SHADOWS` lights with it set get a shadow cubemap
+    pub casts_shadows: bool,
+    /// Size of each face of the shadow cubemap
+    pub shadow_resolution: i32,
+}
+
+impl PointLight {
+    pub fn new(
+        position: TVec3<f32>,
+        ambient: TVec3<f32>,
+        diffuse: TVec3<f32>,
+        specular: TVec3<f32>,
+        constant: f32,
+        linear: f32,
+        quadratic: f32,
+    ) -> Self {
+        Self {
+            position,
+            ambient,
+            diffuse,
+            specular,
+            constant,
+            linear,
+            quadratic,
+            casts_shadows: false,
+            shadow_resolution: 512,
+        }
+    }
+
+    pub fn with_shadows(self, resolution: i32) -> Self {
+        Self {
+            casts_shadows: true,
+            shadow_resolution: resolution,
+            ..self
+        }
+    }
+
+    /// Distance past which the light adds less than 5/256 of its brightest color, infinite
+    /// when it doesn't fade at all
+    pub fn radius(&self) -> f32 {
+        let brightest = self
+            .ambient
+            .max()
+            .max(self.diffuse.max())
+            .max(self.specular.max());
+        // solves constant + linear * d + quadratic * d^2 = brightest / (5 / 256)
+        let c = self.constant - brightest * 256.0 / 5.0;
+        if self.quadratic > 0.0 {
+            let discriminant = self.linear * self.linear - 4.0 * self.quadratic * c;
+            ((-self.linear + discriminant.sqrt()) / (2.0 * self.quadratic)).max(0.0)
+        } else if self.linear > 0.0 {
+            (-c / self.linear).max(0.0)
+        } else {
+            f32::INFINITY
+        }
+    }
+
+    pub fn set_into_shader(&self, shader: &ShaderProgram, name: &str) {
+        shader.set_vec3(format!("{}{}", name, ".position").as_str(), self.position);
+        shader.set_float(format!("{}{}", name, ".constant").as_str(), self.constant);
+        shader.set_float(format!("{}{}", name, ".linear").as_str(), self.linear);
+        shader.set_float(format!("{}{}", name, ".quadratic").as_str(), self.quadratic);
+        shader.set_vec3(format!("{}{}", name, ".ambient").as_str(), self.ambient);
+        shader.set_vec3(format!("{}{}", name, ".diffuse").as_str(), self.diffuse);
+        shader.set_vec3(format!("{}{}", name, ".specular").as_str(), self.specular);
+    }
+}
+
+/// Point light restricted to a cone, fully lit inside `inner_cutoff` and fading out until
+/// `outer_cutoff`, both being angles in degrees from `direction`
+#[derive(Clone)]
+pub struct SpotLight {
+    pub position: TVec3<f32>,
+    pub direction: TVec3<f32>,
+    pub inner_cutoff: f32,
+    pub outer_cutoff: f32,
+    constant: f32,
+    linear: f32,
+    quadratic: f32,
+    pub ambient: TVec3<f32>,
+    diffuse: TVec3<f32>,
+    specular: TVec3<f32>,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: TVec3<f32>,
+        direction: TVec3<f32>,
+        inner_cutoff: f32,
+        outer_cutoff: f32,
+        ambient: TVec3<f32>,
+        diffuse: TVec3<f32>,
+        specular: TVec3<f32>,
+        constant: f32,
+        linear: f32,
+        quadratic: f32,
+    ) -> Self {
+        Self {
+            position,
+            direction,
+            inner_cutoff,
+            outer_cutoff,
+            constant,
+            linear,
+            quadratic,
+            ambient,
+            diffuse,
+            specular,
+        }
+    }
+
+    /// White light with a narrow cone, reaching about fifty units
+    pub fn flashlight() -> Self {
+        SpotLight::new(
+            glm::vec3(0.0, 0.0, 0.0),
+            glm::vec3(0.0, 0.0, -1.0),
+            12.5,
+            17.5,
+            glm::vec3(0.0, 0.0, 0.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            glm::vec3(1.0, 1.0, 1.0),
+            1.0,
+            0.09,
+            0.032,
+        )
+    }
+
+    /// Places the light at the camera, pointing where it looks
+    pub fn follow(&mut self, camera: &Camera) {
+        self.position = camera.position;
+        self.direction = camera.front();
+    }
+
+    /// The cutoffs are uploaded as cosines, ready to be compared with dot products
+    pub fn set_into_shader(&self, shader: &ShaderProgram, name: &str) {
+        shader.set_vec3(format!("{}{}", name, ".position").as_str(), self.position);
+        shader.set_vec3(format!("{}{}", name, ".direction").as_str(), self.direction);
+        shader.set_float(
+            format!("{}{}", name, ".cutOff").as_str(),
+            to_radians(self.inner_cutoff).cos(),
+        );
+        shader.set_float(
+            format!("{}{}", name, ".outerCutOff").as_str(),
+            to_radians(self.outer_cutoff).cos(),
+        );
+        shader.set_float(format!("{}{}", name, ".constant").as_str(), self.constant);
+        shader.set_float(format!("{}{}", name, ".linear").as_str(), self.linear);
+        shader.set_float(format!("{}{}", name, ".quadratic").as_str(), self.quadratic);
+        shader.set_vec3(format!("{}{}", name, ".ambient").as_str(), self.ambient);
+        shader.set_vec3(format!("{}{}", name, ".diffuse").as_str(), self.diffuse);
+        shader.set_vec3(format!("{}{}", name, ".specular").as_str(), self.specular);
+    }
+}
+
+/// Every light of the scene, uploaded into the `dirLights`, `pointLights` and `spotLights`
+/// arrays of the lit shaders along with their counts
+pub struct Lights {
+    pub directional: Vec<DirectionalLight>,
+    pub points: Vec<PointLight>,
+    pub spots: Vec<SpotLight>,
+    /// Spotlight following the camera, see `update_flashlight`
+    pub flashlight: SpotLight,
+    pub flashlight_on: bool,
+}
+
+impl Lights {
+    pub fn new() -> Self {
+        Self {
+            directional: Vec::new(),
+            points: Vec::new(),
+            spots: Vec::new(),
+            flashlight: SpotLight::flashlight(),
+            flashlight_on: false,
+        }
+    }
+
+    pub fn toggle_flashlight(&mut self) {
+        self.flashlight_on = !self.flashlight_on;
+    }
+
+    /// Point lights getting a shadow cubemap with their index, in the order of the shadow
+    /// slots of the shaders
+    pub fn shadow_casting_points(&self) -> impl Iterator<Item = (usize, &PointLight)> {
+        self.points
+            .iter()
+            .take(MAX_POINT_LIGHTS)
+            .enumerate()
+            .filter(|(_, light)| light.casts_shadows)
+            .take(MAX_POINT_SHADOWS)
+    }
+
+    /// Index of the point light's cubemap in the shaders' `pointShadowMaps`, -1 without one
+    pub fn shadow_slot(&self, index: usize) -> i32 {
+        self.shadow_casting_points()
+            .position(|(i, _)| i == index)
+            .map_or(-1, |slot| slot as i32)
+    }
+
+    /// To be called every frame once the camera has moved
+    pub fn update_flashlight(&mut self, camera: &Camera) {
+        if self.flashlight_on {
+            self.flashlight.follow(camera);
+        }
+    }
+
+    /// Lights past the size of the shader arrays are left out
+    pub fn set_into_shader(&self, shader: &ShaderProgram) {
+        let directional = &self.directional[..self.directional.len().min(MAX_DIR_LIGHTS)];
+        for (i, light) in directional.iter().enumerate() {
+            light.set_into_shader(shader, format!("dirLights[{}]", i).as_str());
+        }
+        shader.set_int("dirLightCount", directional.len() as i32);
+
+        let points = &self.points[..self.points.len().min(MAX_POINT_LIGHTS)];
+        for (i, light) in points.iter().enumerate() {
+            light.set_into_shader(shader, format!("pointLights[{}]", i).as_str());
+            shader.set_int(
+                format!("pointLights[{}].shadowSlot", i).as_str(),
+                self.shadow_slot(i),
+            );
+        }
+        shader.set_int("pointLightCount", points.len() as i32);
+
+        let flashlight = self.flashlight_on.then_some(&self.flashlight);
+        let spots: Vec<&SpotLight> = flashlight
+            .into_iter()
+            .chain(&self.spots)
+            .take(MAX_SPOT_LIGHTS)
+            .collect();
+        for (i, light) in spots.iter().enumerate() {
+            light.set_into_shader(shader, format!("spotLights[{}]", i).as_str());
+        }
+        shader.set_int("spotLightCount", spots.len() as i32);
+    }
+}
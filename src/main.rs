@@ -1,25 +1,50 @@
+// The GL wrappers keep the API's acronyms and the widgets take every setting at construction
+#![allow(clippy::too_many_arguments, clippy::upper_case_acronyms)]
+
 use draw::Draw;
 use texture::Texture2D;
 
-use crate::{transform::AsVec3Mut, vec3_sliders::Vec3Sliders};
+use crate::vec3_sliders::Vec3Sliders;
 
+mod animated_model;
+mod animation_clip;
+mod bounds;
 mod camera;
+mod cooked;
+mod deferred;
 mod draw;
+mod export;
 mod framebuffer;
+mod frustum;
+mod gltf_loader;
+mod import_options;
+mod instanced_model;
 mod key_state;
+mod keyframes;
 mod lights;
 mod macros;
+mod material;
 mod mesh;
 mod model;
+mod morph;
+mod normals;
+mod optimize;
 mod plane;
 mod portal;
+mod primitives;
 mod quad;
 mod scene_object;
 mod shader_program;
+mod shadow;
+mod simplify;
+mod skeleton;
 mod slider;
 mod static_camera;
+mod tangents;
 mod texture;
 mod transform;
+mod transform_animation;
+mod tween;
 mod utils;
 mod vec3_sliders;
 mod vertex_objects;
@@ -27,22 +52,37 @@ mod vertex_objects;
 extern crate nalgebra_glm as glm;
 
 use {
+    animated_model::AnimatedModel,
+    bounds::Aabb,
     camera::Camera,
+    deferred::{DeferredRenderer, RenderPath},
+    export::PlyFormat,
     framebuffer::Framebuffer,
+    frustum::Frustum,
     gl33::{global_loader::*, *},
     glutin::{
         dpi::{LogicalPosition, LogicalSize},
-        event::{DeviceEvent, Event, VirtualKeyCode, WindowEvent},
+        event::{DeviceEvent, ElementState, Event, VirtualKeyCode, WindowEvent},
         event_loop::{ControlFlow, EventLoop},
         window::Window,
         window::WindowBuilder,
         Api, ContextBuilder, ContextWrapper, GlRequest, PossiblyCurrent,
     },
+    import_options::ImportOptions,
+    instanced_model::{Instance, InstancedModel},
     key_state::MovementState,
+    keyframes::{Interpolation, Keyframes},
+    lights::{DirectionalLight, Lights, PointLight},
+    material::Material,
+    mesh::{Mesh, Vertex},
+    model::Model,
     portal::Portal,
     scene_object::SceneObject,
     shader_program::ShaderProgram,
-    std::time::Instant,
+    shadow::{CascadedShadowMap, PointShadowMaps, ShadowMap},
+    std::{path::Path, time::Instant},
+    transform_animation::{PlaybackMode, TransformAnimation, TransformProperty, TransformTrack},
+    tween::Easing,
     utils::*,
     vertex_objects::VAO,
 };
@@ -50,15 +90,22 @@ use {
 fn init_window(
     width: i32,
     height: i32,
+    visible: bool,
 ) -> (EventLoop<()>, ContextWrapper<PossiblyCurrent, Window>) {
     let el = EventLoop::new();
     let wb = WindowBuilder::new()
         .with_title("OpenGL Test !")
-        .with_inner_size(LogicalSize::new(width, height));
+        .with_inner_size(LogicalSize::new(width, height))
+        .with_visible(visible);
 
     let context = ContextBuilder::new()
         .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
         .with_vsync(true)
+        // shaders work with linear colors, the window encodes them
+        .with_srgb(true)
+        // the deferred renderer copies its depth-stencil buffer into the window's
+        .with_depth_buffer(24)
+        .with_stencil_buffer(8)
         .build_windowed(wb, &el)
         .unwrap();
 
@@ -76,6 +123,7 @@ fn init_opengl(context: &ContextWrapper<PossiblyCurrent, Window>) {
         });
 
         glEnable(GL_DEPTH_TEST);
+        glEnable(GL_FRAMEBUFFER_SRGB);
         glTexParameteri(
             GL_TEXTURE_2D,
             GL_TEXTURE_MIN_FILTER,
@@ -89,12 +137,117 @@ fn init_opengl(context: &ContextWrapper<PossiblyCurrent, Window>) {
     }
 }
 
+/// Draws the object unless it's outside of the frustum, in which case it's counted as culled
+fn draw_visible(object: &SceneObject, shader: &ShaderProgram, frustum: &Frustum, culled: &mut u32) {
+    if object.is_visible(frustum) {
+        object.draw_with_lod(shader, frustum);
+    } else {
+        *culled += 1;
+    }
+}
+
+/// `cook [directory]`: cooks every model under the directory, `ressources/models` by default.
+/// Textures are still loaded while importing, so this needs a (hidden) OpenGL context too.
+fn cook(directory: Option<&String>, options: &ImportOptions) {
+    let (_el, context) = init_window(1, 1, false);
+    init_opengl(&context);
+
+    let root = directory.map_or(Path::new("ressources/models"), Path::new);
+    match cooked::cook_tree(root, options) {
+        Ok(count) => println!("Cooked {} models", count),
+        Err(e) => println!("Couldn't cook \"{}\": {}", root.to_str().unwrap(), e),
+    }
+}
+
+/// Generated meshes `export` accepts as a source, with the sizes of the scene's shapes
+fn primitive(name: &str) -> Option<(Vec<Vertex>, Vec<u32>)> {
+    Some(match name {
+        "cube" => primitives::cube(),
+        "grid" => primitives::grid(10, 10),
+        "uv_sphere" => primitives::uv_sphere(32, 16),
+        "icosphere" => primitives::icosphere(3),
+        "cylinder" => primitives::cylinder(32),
+        "cone" => primitives::cone(32),
+        "torus" => primitives::torus(0.75, 0.25, 32, 16),
+        "capsule" => primitives::capsule(0.5, 2.0, 32, 8),
+        _ => return None,
+    })
+}
+
+/// `export <source> <destination> [ascii]`: imports a model, or generates a primitive when the
+/// source is one of their names, and writes it as OBJ or PLY depending on the destination's
+/// extension, PLY files being binary unless `ascii` is given.
+fn export(args: &[String], options: &ImportOptions) {
+    let (source, destination) = match args {
+        [source, destination, ..] => (source, Path::new(destination)),
+        _ => {
+            println!("Usage: export <source> <destination> [ascii]");
+            return;
+        }
+    };
+
+    let (_el, context) = init_window(1, 1, false);
+    init_opengl(&context);
+
+    let ply_format = match destination.extension().and_then(|e| e.to_str()) {
+        Some("ply") if args.get(2).map(String::as_str) == Some("ascii") => Some(PlyFormat::Ascii),
+        Some("ply") => Some(PlyFormat::BinaryLittleEndian),
+        _ => None,
+    };
+
+    let result = match primitive(source) {
+        Some((vertices, indices)) => {
+            let mesh = Mesh::new(vertices, indices, Material::default());
+            match ply_format {
+                Some(format) => mesh.write_ply(destination, format),
+                None => mesh.write_obj(destination),
+            }
+        }
+        None => {
+            let model = Model::import(Path::new(source), options);
+            match ply_format {
+                Some(format) => model.write_ply(destination, format),
+                None => model.write_obj(destination),
+            }
+        }
+    };
+
+    match result {
+        Ok(()) => println!("Exported \"{}\"", destination.to_str().unwrap()),
+        Err(e) => println!(
+            "Couldn't export \"{}\": {}",
+            destination.to_str().unwrap(),
+            e
+        ),
+    }
+}
+
 fn main() {
+    // `--optimize` can be given to any command, models then go through `optimize_mesh`
+    let mut args: Vec<String> = std::env::args().collect();
+    let import_options = ImportOptions {
+        optimize: args.iter().any(|arg| arg == "--optimize"),
+        ..Default::default()
+    };
+    args.retain(|arg| arg != "--optimize");
+
+    match args.get(1).map(String::as_str) {
+        Some("cook") => {
+            cook(args.get(2), &import_options);
+            return;
+        }
+        Some("export") => {
+            export(&args[2..], &import_options);
+            return;
+        }
+        _ => (),
+    }
+
     // Window and OpenGL initialization
-    let (el, context) = init_window(1600, 1080);
+    let (el, context) = init_window(1600, 1080, true);
 
-    let window_width = context.window().inner_size().width;
-    let window_height = context.window().inner_size().height;
+    let mut window_width = context.window().inner_size().width;
+    let mut window_height = context.window().inner_size().height;
 
     println!("Window created: ({}, {})", window_width, window_height);
 
@@ -103,17 +256,57 @@ fn main() {
     // Actual program starts here
     let shader = ShaderProgram::from_files("model_loading.vs", "model_loading.fs");
     let gui_shader = ShaderProgram::from_files("gui.vs", "gui.fs");
-
-    let mut model = SceneObject::model("backpack.obj");
-    let mut normal_plane = SceneObject::plane(Some(Texture2D::from_texture(
-        "container.jpg",
-        texture::TextureType::Diffuse,
-    )));
+    let shadow_shader = ShaderProgram::from_files("shadow_depth.vs", "shadow_depth.fs");
+    let point_shadow_shader =
+        ShaderProgram::with_geometry("shadow_cube.vs", "shadow_cube.gs", "shadow_cube.fs");
+    let gbuffer_shader = ShaderProgram::from_files("model_loading.vs", "gbuffer.fs");
+    let deferred_shader = ShaderProgram::from_files("deferred_lighting.vs", "deferred_lighting.fs");
+    let light_volume_shader = ShaderProgram::from_files("light_volume.vs", "light_volume.fs");
+    let pbr_shader = ShaderProgram::from_files("pbr.vs", "pbr.fs");
+
+    // simplified versions of the backpack are drawn as it gets smaller on screen, both
+    // backpack loads share these options so they also share the cooked file
+    let backpack_options = ImportOptions {
+        lod_ratios: vec![0.5, 0.25, 0.125],
+        ..import_options.clone()
+    };
+    let mut model = SceneObject::model_with_options("backpack.obj", &backpack_options);
+    let container = Texture2D::from_texture("container.jpg", texture::TextureType::Diffuse);
+    let mut normal_plane = SceneObject::plane(Some(container.clone()));
     let mut back_plane = SceneObject::plane(Some(Texture2D::from_image(
         "ao.jpg",
         "ressources/models/backpack",
         texture::TextureType::Diffuse,
     )));
+    // a line of generated shapes on a floor, left of the starting view
+    let mut shapes = vec![
+        SceneObject::grid(4, 16, Some(container.clone())),
+        SceneObject::cube(Some(container.clone())),
+        SceneObject::uv_sphere(Some(container.clone())),
+        SceneObject::icosphere(Some(container.clone())),
+        SceneObject::cylinder(Some(container.clone())),
+        SceneObject::cone(Some(container.clone())),
+        SceneObject::torus(Some(container.clone())),
+        SceneObject::capsule(Some(container.clone())),
+    ];
+    // metallic-roughness materials from glTF, drawn with the PBR shader
+    let mut spheres = SceneObject::model_with_options("spheres.gltf", &import_options);
+    // two skinned columns swaying, right of the starting view
+    let mut columns = AnimatedModel::new(Model::with_options("columns.gltf", &import_options));
+    columns.play("sway");
+    let mut columns = SceneObject::animated(columns);
+    let mut columns_clip = 0;
+
+    // a row of tinted backpacks sharing a single model
+    let mut backpacks = InstancedModel::new(Model::with_options("backpack.obj", &backpack_options));
+    backpacks.set_instances(
+        (0..5)
+            .map(|i| Instance {
+                model: glm::translation(&glm::vec3(-8.0 + 4.0 * i as f32, -1.75, -15.0)),
+                color: glm::vec4(1.0, 0.5 + 0.125 * i as f32, 1.0 - 0.125 * i as f32, 1.0),
+            })
+            .collect(),
+    );
 
     let mut portal1 = Portal::new(
         window_width.try_into().unwrap(),
@@ -134,6 +327,107 @@ fn main() {
     back_plane.set_position(glm::vec3(0.0, 0.0, 4.0));
     back_plane.set_scale(glm::vec3(5.0, 5.0, 1.0));
 
+    spheres.set_position(glm::vec3(0.0, 1.5, -3.0));
+    columns.set_position(glm::vec3(4.0, -1.75, -2.0));
+
+    shapes[0].set_position(glm::vec3(-6.0, -1.5, -5.0));
+    shapes[0].set_scale(glm::vec3(1.5, 1.0, 8.0));
+    for (i, shape) in shapes.iter_mut().skip(1).enumerate() {
+        shape.set_position(glm::vec3(-6.0, -0.75, 1.0 - 2.0 * i as f32));
+        shape.set_scale(glm::vec3(0.75, 0.75, 0.75));
+    }
+    // the torus swaps the container's dull finish for a polished gold one
+    shapes[6].set_material(Some(Material {
+        diffuse: glm::vec3(1.0, 0.8, 0.4),
+        specular: glm::vec3(1.0, 0.9, 0.6),
+        shininess: 128.0,
+        ..Material::with_textures(vec![container])
+    }));
+
+    // the back plane eases up and down, the control points flatten the curve at both ends
+    let mut back_plane_animation = TransformAnimation::new(
+        vec![TransformTrack {
+            property: TransformProperty::Position,
+            keyframes: Keyframes::new(
+                Interpolation::Bezier,
+                vec![0.0, 2.0],
+                vec![
+                    glm::vec3(0.0, 0.0, 4.0),
+                    glm::vec3(0.0, 0.0, 4.0),
+                    glm::vec3(0.0, 0.0, 4.0),
+                    glm::vec3(0.0, 2.0, 4.0),
+                    glm::vec3(0.0, 2.0, 4.0),
+                    glm::vec3(0.0, 2.0, 4.0),
+                ],
+            ),
+        }],
+        PlaybackMode::PingPong,
+    );
+    back_plane_animation.add_event(2.0, "back plane up");
+
+    // the torus spins on itself
+    let mut torus_animation = TransformAnimation::new(
+        vec![TransformTrack {
+            property: TransformProperty::Angles,
+            keyframes: Keyframes::new(
+                Interpolation::Linear,
+                vec![0.0, 4.0],
+                vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(0.0, 360.0, 0.0)],
+            ),
+        }],
+        PlaybackMode::Loop,
+    );
+
+    // the cube squashes and springs back, once at startup and again on each press of B
+    let mut cube_bounce = TransformAnimation::new(
+        vec![TransformTrack {
+            property: TransformProperty::Scale,
+            keyframes: Keyframes::new(
+                Interpolation::Linear,
+                vec![0.0, 0.15, 0.35, 0.5],
+                vec![
+                    glm::vec3(0.75, 0.75, 0.75),
+                    glm::vec3(0.9, 0.5, 0.9),
+                    glm::vec3(0.7, 0.9, 0.7),
+                    glm::vec3(0.75, 0.75, 0.75),
+                ],
+            ),
+        }],
+        PlaybackMode::Once,
+    );
+    cube_bounce.add_keyframe_events(0);
+
+    // a sun and a warm light between the backpack and the planes
+    let mut lights = Lights::new();
+    lights.directional.push(DirectionalLight::new(
+        glm::vec3(-0.3, -1.0, -0.5),
+        glm::vec3(0.2, 0.2, 0.2),
+        glm::vec3(0.8, 0.8, 0.8),
+        glm::vec3(0.5, 0.5, 0.5),
+    ));
+    lights.points.push(
+        PointLight::new(
+            glm::vec3(0.0, 1.0, 2.0),
+            glm::vec3(0.05, 0.05, 0.05),
+            glm::vec3(1.0, 0.6, 0.3),
+            glm::vec3(1.0, 0.6, 0.3),
+            1.0,
+            0.09,
+            0.032,
+        )
+        .with_shadows(1024),
+    );
+    let mut shadow_map = ShadowMap::new(2048);
+    // the single shadow map covers the whole scene, once cascades get switched on they
+    // replace it in the main view while the portals keep using it
+    let mut cascades = CascadedShadowMap::new(2048, 4);
+    cascades.enabled = false;
+    let mut point_shadows = PointShadowMaps::new();
+
+    // the main view is rendered forward until switched to deferred, the portals always are
+    let mut render_path = RenderPath::Forward;
+    let mut deferred = DeferredRenderer::new(window_width as i32, window_height as i32);
+
     portal1.surface.set_position(glm::vec3(1.0, 1.0, -8.0));
     portal1.surface.set_scale(glm::vec3(5.0, 5.0, 1.0));
 
@@ -142,9 +436,8 @@ fn main() {
     portal2.surface.set_scale(glm::vec3(5.0, 5.0, 1.0));
 
     // GUI setup
-    let sliders = Vec3Sliders::new(
-        //&mut normal_plane.transform.angles,
-        normal_plane.transform.mut_angles(),
+    let mut sliders = Vec3Sliders::new(
+        normal_plane.transform.angles,
         (50.0, 50.0),
         200.0,
         200.0,
@@ -156,15 +449,18 @@ fn main() {
         20.0,
     );
 
-    clear_color(0.25, 0.25, 0.25);
+    // linear, about 0.25 once encoded to sRGB
+    clear_color(0.05, 0.05, 0.05);
 
     let mut camera = Camera::new();
+    let home = camera.position;
+    let mut glide_easing = Easing::CubicInOut;
     let mut mouse_snapback = true;
 
     let mut movement_state = MovementState::new();
 
     let mut last_frame = Instant::now();
-    let start = Instant::now();
+    let mut last_cull_report = Instant::now();
 
     unsafe {
         glEnable(GL_DEPTH_TEST);
@@ -175,9 +471,20 @@ fn main() {
         *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::LoopDestroyed => return,
+            Event::LoopDestroyed => (),
             Event::WindowEvent { event, .. } => match event {
-                WindowEvent::Resized(physical_size) => context.resize(physical_size),
+                WindowEvent::Resized(physical_size) => {
+                    context.resize(physical_size);
+                    // minimized windows have no size to render at
+                    if physical_size.width > 0 && physical_size.height > 0 {
+                        window_width = physical_size.width;
+                        window_height = physical_size.height;
+                        let (width, height) = (window_width as i32, window_height as i32);
+                        deferred.resize(width, height);
+                        portal1.resize(width, height);
+                        portal2.resize(width, height);
+                    }
+                }
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                 _ => (),
             },
@@ -195,6 +502,7 @@ fn main() {
                     }
                 }
                 DeviceEvent::Key(key) => {
+                    let pressed = key.state == ElementState::Pressed;
                     if let Some(key_code) = key.virtual_keycode {
                         match key_code {
                             VirtualKeyCode::W => movement_state.forward = key.state.into(),
@@ -203,6 +511,42 @@ fn main() {
                             VirtualKeyCode::A => movement_state.left = key.state.into(),
                             VirtualKeyCode::C => mouse_snapback = true,
                             VirtualKeyCode::V => mouse_snapback = false,
+                            // glide back home / reset the plane's angles
+                            VirtualKeyCode::H if pressed => {
+                                camera.glide_to(home, 1.5, glide_easing)
+                            }
+                            VirtualKeyCode::R if pressed => {
+                                sliders.animate_to(glm::zero(), 0.75, Easing::BackOut)
+                            }
+                            // next easing for the glide back home
+                            VirtualKeyCode::E if pressed => {
+                                let current = Easing::ALL.iter().position(|&e| e == glide_easing);
+                                glide_easing =
+                                    Easing::ALL[(current.unwrap() + 1) % Easing::ALL.len()];
+                                println!("Glide easing: {:?}", glide_easing);
+                            }
+                            VirtualKeyCode::F if pressed => lights.toggle_flashlight(),
+                            VirtualKeyCode::P if pressed => {
+                                lights.points[0].casts_shadows = !lights.points[0].casts_shadows
+                            }
+                            // cascaded shadows and their debug colors
+                            VirtualKeyCode::K if pressed => {
+                                cascades.enabled = !cascades.enabled;
+                            }
+                            VirtualKeyCode::J if pressed => cascades.debug = !cascades.debug,
+                            VirtualKeyCode::G if pressed => {
+                                render_path = render_path.toggled();
+                                println!("Render path: {:?}", render_path);
+                            }
+                            VirtualKeyCode::B if pressed => cube_bounce.restart(),
+                            // blend the columns into their next clip
+                            VirtualKeyCode::N if pressed => {
+                                let animation = columns.animation_mut().unwrap();
+                                let clips = animation.clip_names();
+                                columns_clip = (columns_clip + 1) % clips.len();
+                                let clip = clips[columns_clip].to_owned();
+                                animation.crossfade(&clip, 0.5);
+                            }
                             //VirtualKeyCode::Right => slider.step_value(1.0),
                             //VirtualKeyCode::Right => {
                             //normal_plane.set_roll(normal_plane.roll() + 10.0)
@@ -222,58 +566,226 @@ fn main() {
 
                 let dt = last_frame.elapsed().as_secs_f32();
                 last_frame = Instant::now();
-                let elapsed = start.elapsed().as_secs_f32();
 
                 // camera handling
                 camera.update_movement(&movement_state, dt);
+                lights.update_flashlight(&camera);
+                sliders.update(dt);
+                normal_plane.transform.angles = sliders.value();
+
+                for event in back_plane_animation.update(dt, &mut back_plane.transform) {
+                    println!("Animation event: {}", event);
+                }
+                torus_animation.update(dt, &mut shapes[6].transform);
+                if !cube_bounce.is_finished() {
+                    for event in cube_bounce.update(dt, &mut shapes[1].transform) {
+                        println!("Animation event: {}", event);
+                    }
+                }
+                columns.update(dt);
 
                 portal1.camera.pos = portal2.surface.position() - camera.position;
                 portal2.camera.pos = portal1.surface.position() - camera.position;
 
-                shader.use_program();
+                let aspect = (window_width as f32) / (window_height as f32);
+                let (fov, near, far) = (to_radians(90.0), 0.1, 100.0);
+                let projection_matrix = glm::perspective(aspect, fov, near, far);
 
-                let projection_matrix = glm::perspective(
-                    (window_width as f32) / (window_height as f32),
-                    to_radians(90.0),
-                    0.1,
-                    100.0,
-                );
+                shader.use_program();
                 shader.set_mat4("projection", &projection_matrix);
 
+                pbr_shader.use_program();
+                pbr_shader.set_mat4("projection", &projection_matrix);
+
+                // shadow pass, the light's view covers every object casting a shadow
+                let casters = [&normal_plane, &back_plane, &model, &spheres, &columns];
+                let draw_casters = |shader: &ShaderProgram| {
+                    for object in casters.into_iter().chain(&shapes) {
+                        object.draw(shader);
+                    }
+                    backpacks.draw(shader);
+                };
+                let viewport = (window_width as i32, window_height as i32);
+                let scene_bounds = backpacks
+                    .instances()
+                    .iter()
+                    .map(|instance| backpacks.model().aabb().transformed(&instance.model))
+                    .chain(
+                        casters
+                            .into_iter()
+                            .chain(&shapes)
+                            .map(|object| object.world_aabb()),
+                    )
+                    .fold(Aabb::empty(), |bounds, aabb| bounds.union(&aabb));
+                shadow_map.fit(&lights.directional[0], &scene_bounds);
+                shadow_map.begin(&shadow_shader);
+                draw_casters(&shadow_shader);
+                shadow_map.end(viewport);
+                if cascades.enabled {
+                    // fitted to the main camera, the portals stick to the shadow map
+                    let view = camera.view_matrix();
+                    cascades.fit(&lights.directional[0], &view, fov, aspect, near, far);
+                    cascades.render(&shadow_shader, viewport, draw_casters);
+                }
+                point_shadows.render(&lights, &point_shadow_shader, viewport, draw_casters);
+
+                // objects culled in each pass: portal 1, portal 2 and main
+                let mut culled = [0; 3];
+
                 // first render for framebuffer 1
+                let view = portal1.camera.view_matrix();
+                let frustum = Frustum::new(&projection_matrix, &view);
                 portal1.bind_framebuffer();
                 gl_clear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
-                shader.set_mat4("view", &portal1.camera.view_matrix());
-                model.draw(&shader);
-                normal_plane.draw(&shader);
-                back_plane.draw(&shader);
+                shader.use_program();
+                shader.set_mat4("view", &view);
+                shader.set_vec3("viewPos", portal1.camera.pos);
+                lights.set_into_shader(&shader);
+                shadow_map.set_into_shader(&shader);
+                cascades.set_into_shader(&shader);
+                // the cascades only cover the main camera's view
+                shader.set_int("cascadesEnabled", 0);
+                point_shadows.set_into_shader(&shader);
+                draw_visible(&normal_plane, &shader, &frustum, &mut culled[0]);
+                draw_visible(&back_plane, &shader, &frustum, &mut culled[0]);
+                for shape in &shapes {
+                    draw_visible(shape, &shader, &frustum, &mut culled[0]);
+                }
+                draw_visible(&columns, &shader, &frustum, &mut culled[0]);
+                draw_visible(&model, &shader, &frustum, &mut culled[0]);
+                backpacks.draw(&shader);
+                pbr_shader.use_program();
+                pbr_shader.set_mat4("view", &view);
+                pbr_shader.set_vec3("viewPos", portal1.camera.pos);
+                lights.set_into_shader(&pbr_shader);
+                shadow_map.set_into_shader(&pbr_shader);
+                cascades.set_into_shader(&pbr_shader);
+                pbr_shader.set_int("cascadesEnabled", 0);
+                point_shadows.set_into_shader(&pbr_shader);
+                draw_visible(&spheres, &pbr_shader, &frustum, &mut culled[0]);
 
                 // then render for framebuffer 2
+                let view = portal2.camera.view_matrix();
+                let frustum = Frustum::new(&projection_matrix, &view);
                 portal2.bind_framebuffer();
                 gl_clear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
-                shader.set_mat4("view", &portal2.camera.view_matrix());
-                model.draw(&shader);
-                normal_plane.draw(&shader);
-                back_plane.draw(&shader);
+                shader.use_program();
+                shader.set_mat4("view", &view);
+                shader.set_vec3("viewPos", portal2.camera.pos);
+                lights.set_into_shader(&shader);
+                shadow_map.set_into_shader(&shader);
+                cascades.set_into_shader(&shader);
+                shader.set_int("cascadesEnabled", 0);
+                point_shadows.set_into_shader(&shader);
+                draw_visible(&normal_plane, &shader, &frustum, &mut culled[1]);
+                draw_visible(&back_plane, &shader, &frustum, &mut culled[1]);
+                for shape in &shapes {
+                    draw_visible(shape, &shader, &frustum, &mut culled[1]);
+                }
+                draw_visible(&columns, &shader, &frustum, &mut culled[1]);
+                draw_visible(&model, &shader, &frustum, &mut culled[1]);
+                backpacks.draw(&shader);
+                pbr_shader.use_program();
+                pbr_shader.set_mat4("view", &view);
+                pbr_shader.set_vec3("viewPos", portal2.camera.pos);
+                lights.set_into_shader(&pbr_shader);
+                shadow_map.set_into_shader(&pbr_shader);
+                cascades.set_into_shader(&pbr_shader);
+                pbr_shader.set_int("cascadesEnabled", 0);
+                point_shadows.set_into_shader(&pbr_shader);
+                draw_visible(&spheres, &pbr_shader, &frustum, &mut culled[1]);
 
                 // then render normal scene
+                let view = camera.view_matrix();
+                let frustum = Frustum::new(&projection_matrix, &view);
                 Framebuffer::clear_binding();
                 gl_clear(GL_COLOR_BUFFER_BIT);
-                shader.set_mat4("view", &camera.view_matrix());
-                model.draw(&shader);
-                normal_plane.draw(&shader);
-                back_plane.draw(&shader);
+                match render_path {
+                    RenderPath::Forward => {
+                        shader.use_program();
+                        shader.set_mat4("view", &view);
+                        shader.set_vec3("viewPos", camera.position);
+                        lights.set_into_shader(&shader);
+                        shadow_map.set_into_shader(&shader);
+                        cascades.set_into_shader(&shader);
+                        point_shadows.set_into_shader(&shader);
+                        draw_visible(&normal_plane, &shader, &frustum, &mut culled[2]);
+                        draw_visible(&back_plane, &shader, &frustum, &mut culled[2]);
+                        for shape in &shapes {
+                            draw_visible(shape, &shader, &frustum, &mut culled[2]);
+                        }
+                        draw_visible(&columns, &shader, &frustum, &mut culled[2]);
+                        draw_visible(&model, &shader, &frustum, &mut culled[2]);
+                        backpacks.draw(&shader);
+                    }
+                    RenderPath::Deferred => {
+                        let gbuffer = &gbuffer_shader;
+                        deferred.begin(gbuffer);
+                        gbuffer.set_mat4("projection", &projection_matrix);
+                        gbuffer.set_mat4("view", &view);
+                        draw_visible(&normal_plane, gbuffer, &frustum, &mut culled[2]);
+                        draw_visible(&back_plane, gbuffer, &frustum, &mut culled[2]);
+                        for shape in &shapes {
+                            draw_visible(shape, gbuffer, &frustum, &mut culled[2]);
+                        }
+                        draw_visible(&columns, gbuffer, &frustum, &mut culled[2]);
+                        draw_visible(&model, gbuffer, &frustum, &mut culled[2]);
+                        backpacks.draw(gbuffer);
+                        deferred.end();
+
+                        deferred_shader.use_program();
+                        deferred_shader.set_vec3("viewPos", camera.position);
+                        lights.set_into_shader(&deferred_shader);
+                        shadow_map.set_into_shader(&deferred_shader);
+                        cascades.set_into_shader(&deferred_shader);
+                        deferred.light_screen(&deferred_shader);
+
+                        light_volume_shader.use_program();
+                        light_volume_shader.set_mat4("projection", &projection_matrix);
+                        light_volume_shader.set_mat4("view", &view);
+                        light_volume_shader.set_vec3("viewPos", camera.position);
+                        point_shadows.set_into_shader(&light_volume_shader);
+                        deferred.light_volumes(&light_volume_shader, &lights);
+                    }
+                }
+                // PBR and portal surfaces are drawn forward on top of either path
+                pbr_shader.use_program();
+                pbr_shader.set_mat4("view", &view);
+                pbr_shader.set_vec3("viewPos", camera.position);
+                lights.set_into_shader(&pbr_shader);
+                shadow_map.set_into_shader(&pbr_shader);
+                cascades.set_into_shader(&pbr_shader);
+                point_shadows.set_into_shader(&pbr_shader);
+                draw_visible(&spheres, &pbr_shader, &frustum, &mut culled[2]);
+
+                shader.use_program();
+                for portal in [&portal1, &portal2] {
+                    if portal.surface.is_visible(&frustum) {
+                        portal.render(&shader);
+                    } else {
+                        culled[2] += 1;
+                    }
+                }
 
-                portal1.render(&shader);
-                portal2.render(&shader);
+                if last_cull_report.elapsed().as_secs_f32() >= 1.0 {
+                    println!(
+                        "Culled objects: portal 1: {}, portal 2: {}, main: {}",
+                        culled[0], culled[1], culled[2]
+                    );
+                    last_cull_report = Instant::now();
+                }
 
-                // Render GUI on top of everything
+                // Render GUI on top of everything, its colors are already sRGB
                 unsafe {
                     glEnable(GL_BLEND);
                     glBlendFunc(GL_SRC_ALPHA, GL_ONE_MINUS_SRC_ALPHA);
+                    glDisable(GL_FRAMEBUFFER_SRGB);
                 }
                 gui_shader.use_program();
                 sliders.draw(&gui_shader);
+                unsafe {
+                    glEnable(GL_FRAMEBUFFER_SRGB);
+                }
 
                 VAO::clear_binding();
 
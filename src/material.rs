@@ -0,0 +1,220 @@
+use {
+    gl33::{global_loader::*, *},
+    glm::{vec3, vec4, TVec3, TVec4},
+    std::collections::HashMap,
+};
+
+use crate::{
+    shader_program::ShaderProgram,
+    shadow::CASCADE_UNIT,
+    texture::{Texture2D, TextureType},
+    utils::usize_to_glenum,
+};
+
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    pub ambient: TVec3<f32>,
+    pub diffuse: TVec3<f32>,
+    pub specular: TVec3<f32>,
+    pub shininess: f32,
+    pub dissolve: f32,
+    pub illumination_model: u8,
+    /// Metallic-roughness parameters, for materials meant to be drawn with the PBR shader
+    pub pbr: Option<PbrParameters>,
+    pub textures: Vec<Texture2D>,
+}
+
+/// Factors of the metallic-roughness model, they get multiplied with the matching textures
+#[derive(Clone)]
+pub struct PbrParameters {
+    pub base_color: TVec4<f32>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion_strength: f32,
+    pub emissive: TVec3<f32>,
+}
+
+impl Default for PbrParameters {
+    fn default() -> Self {
+        Self {
+            base_color: vec4(1.0, 1.0, 1.0, 1.0),
+            metallic: 0.0,
+            roughness: 1.0,
+            occlusion_strength: 1.0,
+            emissive: vec3(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl PbrParameters {
+    pub fn set_into_shader(&self, shader: &ShaderProgram, name: &str) {
+        shader.set_vec4(
+            format!("{}{}", name, ".baseColor").as_str(),
+            self.base_color,
+        );
+        shader.set_float(format!("{}{}", name, ".metallic").as_str(), self.metallic);
+        shader.set_float(format!("{}{}", name, ".roughness").as_str(), self.roughness);
+        shader.set_float(
+            format!("{}{}", name, ".occlusionStrength").as_str(),
+            self.occlusion_strength,
+        );
+        shader.set_vec3(format!("{}{}", name, ".emissive").as_str(), self.emissive);
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            ambient: vec3(1.0, 1.0, 1.0),
+            diffuse: vec3(1.0, 1.0, 1.0),
+            specular: vec3(0.5, 0.5, 0.5),
+            shininess: 32.0,
+            dissolve: 1.0,
+            illumination_model: 2,
+            pbr: None,
+            textures: Vec::new(),
+        }
+    }
+}
+
+impl Material {
+    pub fn from_mtl(material: &tobj::Material, textures: Vec<Texture2D>) -> Self {
+        Self {
+            name: material.name.clone(),
+            ambient: TVec3::from(material.ambient),
+            diffuse: TVec3::from(material.diffuse),
+            specular: TVec3::from(material.specular),
+            shininess: material.shininess,
+            dissolve: material.dissolve,
+            illumination_model: material.illumination_model.unwrap_or(2),
+            pbr: Material::mtl_pbr_parameters(material),
+            textures,
+        }
+    }
+
+    /// Reads the PBR extension of the MTL format (`Pr`, `Pm`, `Ke`, `map_Pr`, `map_Pm`), only
+    /// materials using it get PBR parameters.
+    fn mtl_pbr_parameters(material: &tobj::Material) -> Option<PbrParameters> {
+        let params = &material.unknown_param;
+        if !["Pr", "Pm", "map_Pr", "map_Pm"]
+            .iter()
+            .any(|key| params.contains_key(*key))
+        {
+            return None;
+        }
+
+        let scalar = |key: &str, default: f32| {
+            params
+                .get(key)
+                .and_then(|v| v.split_whitespace().next())
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let emissive = params
+            .get("Ke")
+            .map(|v| {
+                v.split_whitespace()
+                    .filter_map(|c| c.parse::<f32>().ok())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|c| c.len() == 3)
+            .map_or(vec3(0.0, 0.0, 0.0), |c| vec3(c[0], c[1], c[2]));
+
+        Some(PbrParameters {
+            base_color: vec4(
+                material.diffuse[0],
+                material.diffuse[1],
+                material.diffuse[2],
+                material.dissolve,
+            ),
+            metallic: scalar("Pm", 0.0),
+            roughness: scalar("Pr", 1.0),
+            occlusion_strength: 1.0,
+            emissive,
+        })
+    }
+
+    pub fn with_textures(textures: Vec<Texture2D>) -> Self {
+        Self {
+            textures,
+            ..Default::default()
+        }
+    }
+
+    /// Uploads the parameters into the `name` struct uniform and binds the textures to
+    /// consecutive texture units, each sampler being named after its type and rank
+    /// (`texture_diffuse1`, `texture_diffuse2`, `texture_normal1`...).
+    pub fn set_into_shader(&self, shader: &ShaderProgram, name: &str) {
+        shader.set_vec3(format!("{}{}", name, ".ambient").as_str(), self.ambient);
+        shader.set_vec3(format!("{}{}", name, ".diffuse").as_str(), self.diffuse);
+        shader.set_vec3(format!("{}{}", name, ".specular").as_str(), self.specular);
+        shader.set_float(format!("{}{}", name, ".shininess").as_str(), self.shininess);
+        shader.set_float(format!("{}{}", name, ".dissolve").as_str(), self.dissolve);
+        shader.set_int(
+            format!("{}{}", name, ".illum").as_str(),
+            self.illumination_model.into(),
+        );
+        if let Some(pbr) = &self.pbr {
+            pbr.set_into_shader(shader, name);
+        }
+        // Lightmaps replace the ambient lighting so there's no neutral one to default to,
+        // shaders are told whether there is one instead. Meshes built outside of a model
+        // don't get default maps, so the same goes for specular and normal maps.
+        let has_lightmap = self
+            .textures
+            .iter()
+            .any(|t| matches!(t.ty, TextureType::Lightmap));
+        shader.set_int(
+            format!("{}{}", name, ".hasLightmap").as_str(),
+            has_lightmap.into(),
+        );
+        let has_specular_map = self
+            .textures
+            .iter()
+            .any(|t| matches!(t.ty, TextureType::Specular));
+        shader.set_int(
+            format!("{}{}", name, ".hasSpecularMap").as_str(),
+            has_specular_map.into(),
+        );
+        let has_normal_map = self
+            .textures
+            .iter()
+            .any(|t| matches!(t.ty, TextureType::Normal));
+        shader.set_int(
+            format!("{}{}", name, ".hasNormalMap").as_str(),
+            has_normal_map.into(),
+        );
+
+        self.bind_textures(shader);
+    }
+
+    fn bind_textures(&self, shader: &ShaderProgram) {
+        let mut count_by_type: HashMap<String, i32> = HashMap::new();
+
+        assert!(
+            self.textures.len() <= CASCADE_UNIT,
+            "Material has {} textures but only {} texture units are left below the shadow maps'",
+            self.textures.len(),
+            CASCADE_UNIT
+        );
+
+        for (i, texture) in self.textures.iter().enumerate() {
+            unsafe {
+                glActiveTexture(usize_to_glenum(0x84c0 + i));
+            }
+
+            let name = texture.ty.to_string();
+            let number = count_by_type.entry(name.clone()).or_insert(0);
+            *number += 1;
+
+            shader.set_int(&format!("{}{}", name, number), i as i32);
+            texture.bind();
+        }
+
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+        }
+    }
+}
@@ -1,156 +1,404 @@
-use {
-    gl33::{global_loader::*, *},
-    glm::{vec2, vec3},
-    glm::{TVec2, TVec3},
-    std::mem::size_of,
-    std::ptr,
-};
-
-use crate::{
-    draw::Draw,
-    macros::*,
-    shader_program::ShaderProgram,
-    texture::{Texture2D, TextureType},
-    utils::usize_to_glenum,
-    vertex_objects::{BufferType, VAO, VBO},
-};
-
-#[repr(C)]
-pub struct Vertex {
-    pub position: TVec3<f32>,
-    pub normal: TVec3<f32>,
-    pub tex_coords: TVec2<f32>,
-}
-
-impl Default for Vertex {
-    fn default() -> Self {
-        Vertex {
-            position: vec3(0.0, 0.0, 0.0),
-            normal: vec3(0.0, 0.0, 0.0),
-            tex_coords: vec2(0.0, 0.0),
-        }
-    }
-}
-
-pub struct Mesh {
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    textures: Vec<Texture2D>,
-    vao: VAO,
-    vbo: VBO,
-    ebo: VBO,
-}
-
-impl Mesh {
-    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>, textures: Vec<Texture2D>) -> Self {
-        let mut mesh = Mesh {
-            vertices,
-            indices,
-            textures,
-            vao: VAO::new(),
-            vbo: VBO::new(BufferType::Array),
-            ebo: VBO::new(BufferType::ElementArray),
-        };
-
-        mesh.setup_mesh();
-        mesh
-    }
-
-    fn setup_mesh(&mut self) {
-        self.vao.bind();
-
-        self.vbo.bind();
-        unsafe {
-            glBufferData(
-                GL_ARRAY_BUFFER,
-                (self.vertices.len() * size_of::<Vertex>())
-                    .try_into()
-                    .unwrap(),
-                self.vertices.as_ptr().cast(),
-                GL_STATIC_DRAW,
-            );
-        }
-
-        self.ebo.bind();
-        unsafe {
-            glBufferData(
-                GL_ELEMENT_ARRAY_BUFFER,
-                (self.indices.len() * size_of::<u32>()).try_into().unwrap(),
-                self.indices.as_ptr().cast(),
-                GL_STATIC_DRAW,
-            );
-        }
-
-        unsafe {
-            let stride: i32 = size_of::<Vertex>().try_into().unwrap();
-
-            glEnableVertexAttribArray(0);
-            glVertexAttribPointer(0, 3, GL_FLOAT, 0, stride, 0 as *const _);
-
-            glEnableVertexAttribArray(1);
-            glVertexAttribPointer(
-                1,
-                3,
-                GL_FLOAT,
-                0,
-                stride,
-                offset_of!(Vertex, normal) as *const _,
-            );
-
-            glEnableVertexAttribArray(2);
-            glVertexAttribPointer(
-                2,
-                2,
-                GL_FLOAT,
-                0,
-                stride,
-                offset_of!(Vertex, tex_coords) as *const _,
-            );
-        }
-
-        VAO::clear_binding();
-    }
-}
-
-impl Draw for Mesh {
-    fn draw(&self, shader: &ShaderProgram) {
-        let mut diffuse_n = 0;
-        let mut specular_n = 0;
-
-        for (i, texture) in self.textures.iter().enumerate() {
-            unsafe {
-                glActiveTexture(usize_to_glenum(0x84c0 + i));
-            }
-
-            let name = &texture.ty;
-            let number = match name {
-                TextureType::Diffuse => {
-                    diffuse_n += 1;
-                    diffuse_n
-                }
-                TextureType::Specular => {
-                    specular_n += 1;
-                    specular_n
-                }
-                _ => panic!("Unknown texture type"),
-            };
-
-            shader.set_int(&format!("{}{}", name, number), i as i32);
-            texture.bind();
-        }
-
-        self.vao.bind();
-
-        unsafe {
-            glDrawElements(
-                GL_TRIANGLES,
-                self.indices.len().try_into().unwrap(),
-                GL_UNSIGNED_INT,
-                0 as *const _,
-            );
-            glActiveTexture(GL_TEXTURE0);
-        }
-
-        VAO::clear_binding();
-    }
-}
+use {
+    gl33::{global_loader::*, *},
+    glm::{vec2, vec3, vec4},
+    glm::{TVec2, TVec3, TVec4},
+    std::mem::{size_of, size_of_val},
+};
+
+use crate::{
+    bounds::{Aabb, BoundingSphere},
+    draw::{Draw, DrawOptions},
+    instanced_model::Instance,
+    macros::*,
+    material::Material,
+    morph::{apply_morph_targets, MorphTarget},
+    optimize::optimize_triangle_order,
+    shader_program::ShaderProgram,
+    simplify::simplify,
+    vertex_objects::{BufferType, VAO, VBO},
+};
+
+/// Share of the screen height from which objects are drawn with their full detail mesh
+const FULL_DETAIL_SCREEN_SIZE: f32 = 0.5;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Vertex {
+    pub position: TVec3<f32>,
+    pub normal: TVec3<f32>,
+    pub tex_coords: TVec2<f32>,
+    pub tangent: TVec3<f32>,
+    pub bitangent: TVec3<f32>,
+    /// White unless the source has vertex colors
+    pub color: TVec4<f32>,
+    /// Second UV channel, used by lightmaps
+    pub tex_coords2: TVec2<f32>,
+    /// Indices of the skeleton joints moving the vertex, stored as floats like every other
+    /// attribute
+    pub joints: TVec4<f32>,
+    /// Influence of each of the `joints`, all zero for vertices that aren't skinned
+    pub weights: TVec4<f32>,
+}
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Vertex {
+            position: vec3(0.0, 0.0, 0.0),
+            normal: vec3(0.0, 0.0, 0.0),
+            tex_coords: vec2(0.0, 0.0),
+            tangent: vec3(0.0, 0.0, 0.0),
+            bitangent: vec3(0.0, 0.0, 0.0),
+            color: vec4(1.0, 1.0, 1.0, 1.0),
+            tex_coords2: vec2(0.0, 0.0),
+            joints: vec4(0.0, 0.0, 0.0, 0.0),
+            weights: vec4(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl Vertex {
+    /// Location, component count and offset of each attribute, all of them floats
+    pub fn attributes() -> Vec<(u32, i32, usize)> {
+        vec![
+            (0, 3, offset_of!(Vertex, position)),
+            (1, 3, offset_of!(Vertex, normal)),
+            (2, 2, offset_of!(Vertex, tex_coords)),
+            (3, 3, offset_of!(Vertex, tangent)),
+            (4, 3, offset_of!(Vertex, bitangent)),
+            (5, 4, offset_of!(Vertex, color)),
+            (6, 2, offset_of!(Vertex, tex_coords2)),
+            (7, 4, offset_of!(Vertex, joints)),
+            (8, 4, offset_of!(Vertex, weights)),
+        ]
+    }
+
+    /// Describes the layout of `Vertex` to the currently bound VAO, reading from the currently
+    /// bound array buffer.
+    pub fn setup_attributes() {
+        let stride: i32 = size_of::<Vertex>().try_into().unwrap();
+
+        for (location, components, offset) in Vertex::attributes() {
+            unsafe {
+                glEnableVertexAttribArray(location);
+                glVertexAttribPointer(
+                    location,
+                    components,
+                    GL_FLOAT,
+                    0,
+                    stride,
+                    offset as *const _,
+                );
+            }
+        }
+    }
+}
+
+/// Range of the index buffer drawn for a level of detail
+#[derive(Clone, Copy)]
+pub(crate) struct Lod {
+    pub offset: usize,
+    pub count: usize,
+    /// Share of the full mesh's triangles that were aimed for
+    pub ratio: f32,
+}
+
+pub struct Mesh {
+    vertices: Vec<Vertex>,
+    /// Indices of every level of detail, one after the other
+    indices: Vec<u32>,
+    lods: Vec<Lod>,
+    morph_targets: Vec<MorphTarget>,
+    morph_weights: Vec<f32>,
+    pub material: Material,
+    pub aabb: Aabb,
+    pub bounding_sphere: BoundingSphere,
+    vao: VAO,
+    vbo: VBO,
+    ebo: VBO,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>, material: Material) -> Self {
+        Mesh::with_lods(vertices, indices, material, &[], false)
+    }
+
+    /// Also generates a simplified level of detail for each of the triangle `ratios`, levels
+    /// that can't be simplified further than the previous one are left out. With `optimize`,
+    /// the triangles of each level get reordered for the vertex cache and overdraw, the full
+    /// level being expected to be optimized already.
+    pub fn with_lods(
+        vertices: Vec<Vertex>,
+        mut indices: Vec<u32>,
+        material: Material,
+        ratios: &[f32],
+        optimize: bool,
+    ) -> Self {
+        let full_count = indices.len();
+        let mut lods = vec![Lod {
+            offset: 0,
+            count: full_count,
+            ratio: 1.0,
+        }];
+
+        for &ratio in ratios {
+            let mut simplified = simplify(&vertices, &indices[..full_count], ratio);
+            if simplified.len() >= lods.last().unwrap().count {
+                continue;
+            }
+            if optimize {
+                simplified = optimize_triangle_order(&vertices, &simplified);
+            }
+
+            lods.push(Lod {
+                offset: indices.len(),
+                count: simplified.len(),
+                ratio,
+            });
+            indices.extend(simplified);
+        }
+
+        let positions = vertices.iter().map(|v| &v.position);
+        let aabb = Aabb::from_points(positions.clone());
+        let bounding_sphere = BoundingSphere::from_points(positions);
+
+        Mesh::from_parts(vertices, indices, lods, material, aabb, bounding_sphere)
+    }
+
+    /// Uploads a mesh whose levels of detail and bounds are already known, `indices` holding
+    /// every level as described by `lods`
+    pub(crate) fn from_parts(
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+        lods: Vec<Lod>,
+        material: Material,
+        aabb: Aabb,
+        bounding_sphere: BoundingSphere,
+    ) -> Self {
+        let mut mesh = Mesh {
+            vertices,
+            indices,
+            lods,
+            morph_targets: Vec::new(),
+            morph_weights: Vec::new(),
+            material,
+            aabb,
+            bounding_sphere,
+            vao: VAO::new(),
+            vbo: VBO::new(BufferType::Array),
+            ebo: VBO::new(BufferType::ElementArray),
+        };
+
+        mesh.setup_mesh();
+        mesh
+    }
+
+    fn setup_mesh(&mut self) {
+        self.vao.bind();
+
+        self.upload_vertices(&self.vertices, GL_STATIC_DRAW);
+
+        self.ebo.bind();
+        unsafe {
+            glBufferData(
+                GL_ELEMENT_ARRAY_BUFFER,
+                (self.indices.len() * size_of::<u32>()).try_into().unwrap(),
+                self.indices.as_ptr().cast(),
+                GL_STATIC_DRAW,
+            );
+        }
+
+        Vertex::setup_attributes();
+
+        VAO::clear_binding();
+    }
+
+    fn upload_vertices(&self, vertices: &[Vertex], usage: GLenum) {
+        self.vbo.bind();
+        unsafe {
+            glBufferData(
+                GL_ARRAY_BUFFER,
+                size_of_val(vertices).try_into().unwrap(),
+                vertices.as_ptr().cast(),
+                usage,
+            );
+        }
+    }
+
+    /// Morph targets are applied on the CPU, the vertex buffer becomes dynamic so that it can
+    /// be rewritten whenever the weights change.
+    /// Missing weights are zero, the targets are skipped if any of them doesn't match the vertices
+    pub fn set_morph_targets(&mut self, targets: Vec<MorphTarget>, mut weights: Vec<f32>) {
+        let vertex_count = self.vertices.len();
+        if let Some(target) = targets.iter().find(|target| {
+            target.position_deltas.len() != vertex_count
+                || ![0, vertex_count].contains(&target.normal_deltas.len())
+        }) {
+            println!(
+                "Warning: skipping morph targets, one has {} positions and {} normals \
+                 for the {} vertices of the mesh",
+                target.position_deltas.len(),
+                target.normal_deltas.len(),
+                vertex_count
+            );
+            return;
+        }
+
+        weights.resize(targets.len(), 0.0);
+        let morphed = apply_morph_targets(&self.vertices, &targets, &weights);
+        self.morph_targets = targets;
+        self.morph_weights = weights;
+
+        self.upload_vertices(&morphed, GL_DYNAMIC_DRAW);
+        VBO::clear_binding();
+
+        // the bounds cover every weight rather than the ones at hand
+        let positions: Vec<TVec3<f32>> = (0..self.vertices.len())
+            .flat_map(|vertex| self.reachable_positions(vertex))
+            .collect();
+        self.aabb = Aabb::from_points(&positions);
+        self.bounding_sphere = BoundingSphere::from_points(&positions);
+    }
+
+    /// Where the vertex can go with morph target weights between 0 and 1, as the corners of
+    /// the box around all of the combinations, or just the vertex without morph targets
+    pub fn reachable_positions(&self, vertex: usize) -> Vec<TVec3<f32>> {
+        let position = self.vertices[vertex].position;
+        if self.morph_targets.is_empty() {
+            return vec![position];
+        }
+
+        let zero = vec3(0.0, 0.0, 0.0);
+        let mut reach = Aabb {
+            min: position,
+            max: position,
+        };
+        for target in &self.morph_targets {
+            let delta = target.position_deltas[vertex];
+            reach.min += glm::min2(&delta, &zero);
+            reach.max += glm::max2(&delta, &zero);
+        }
+        reach.corners().to_vec()
+    }
+
+    pub fn morph_targets(&self) -> &[MorphTarget] {
+        &self.morph_targets
+    }
+
+    pub fn morph_weights(&self) -> &[f32] {
+        &self.morph_weights
+    }
+
+    /// One weight per morph target, the vertex buffer is only rewritten when they change
+    pub fn set_morph_weights(&mut self, weights: &[f32]) {
+        if weights == self.morph_weights.as_slice() {
+            return;
+        }
+        self.morph_weights = weights.to_vec();
+
+        let morphed = apply_morph_targets(&self.vertices, &self.morph_targets, weights);
+        self.vbo.bind();
+        unsafe {
+            glBufferSubData(
+                GL_ARRAY_BUFFER,
+                0,
+                size_of_val(morphed.as_slice()).try_into().unwrap(),
+                morphed.as_ptr().cast(),
+            );
+        }
+        VBO::clear_binding();
+    }
+
+    /// Vertices of the mesh without any morph target applied
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    /// Indices of the full detail mesh
+    pub fn indices(&self) -> &[u32] {
+        &self.indices[..self.lods[0].count]
+    }
+
+    /// Indices of every level of detail and their ranges
+    pub(crate) fn index_buffer(&self) -> (&[u32], &[Lod]) {
+        (&self.indices, &self.lods)
+    }
+
+    /// The least detailed level keeping the triangles as dense on screen as the full mesh at
+    /// `FULL_DETAIL_SCREEN_SIZE`. The triangles needed follow the projected area, so the
+    /// triangle ratio is the square of the size over that reference: an object half as tall
+    /// on screen gets a quarter of the triangles.
+    fn select_lod(&self, screen_size: Option<f32>) -> &Lod {
+        match screen_size {
+            Some(size) => {
+                let ratio = (size / FULL_DETAIL_SCREEN_SIZE).powi(2);
+                self.lods
+                    .iter()
+                    .rev()
+                    .find(|lod| lod.ratio >= ratio)
+                    .unwrap_or(&self.lods[0])
+            }
+            None => &self.lods[0],
+        }
+    }
+
+    /// Adds the per-instance attributes of `instance_vbo` to the mesh's VAO
+    pub fn setup_instance_attributes(&self, instance_vbo: &VBO) {
+        self.vao.bind();
+        instance_vbo.bind();
+
+        Instance::setup_attributes();
+
+        VAO::clear_binding();
+        VBO::clear_binding();
+    }
+
+    /// Only meant for meshes set up with `setup_instance_attributes`
+    pub fn draw_instanced(&self, shader: &ShaderProgram, options: &DrawOptions, count: usize) {
+        options
+            .material
+            .unwrap_or(&self.material)
+            .set_into_shader(shader, "material");
+
+        let lod = self.select_lod(options.screen_size);
+        self.vao.bind();
+
+        unsafe {
+            glDrawElementsInstanced(
+                GL_TRIANGLES,
+                lod.count.try_into().unwrap(),
+                GL_UNSIGNED_INT,
+                (lod.offset * size_of::<u32>()) as *const _,
+                count.try_into().unwrap(),
+            );
+        }
+
+        VAO::clear_binding();
+    }
+}
+
+impl Draw for Mesh {
+    fn draw(&self, shader: &ShaderProgram) {
+        self.draw_with(shader, &DrawOptions::default());
+    }
+
+    fn draw_with(&self, shader: &ShaderProgram, options: &DrawOptions) {
+        options
+            .material
+            .unwrap_or(&self.material)
+            .set_into_shader(shader, "material");
+
+        let lod = self.select_lod(options.screen_size);
+        self.vao.bind();
+
+        unsafe {
+            glDrawElements(
+                GL_TRIANGLES,
+                lod.count.try_into().unwrap(),
+                GL_UNSIGNED_INT,
+                (lod.offset * size_of::<u32>()) as *const _,
+            );
+        }
+
+        VAO::clear_binding();
+    }
+}
@@ -1,110 +1,359 @@
-use {
-    glm::{vec2, vec3},
-    std::path::Path,
-};
-
-use crate::{
-    draw::Draw,
-    mesh::Mesh,
-    mesh::Vertex,
-    shader_program::ShaderProgram,
-    texture::{Texture2D, TextureType},
-};
-
-#[derive(Default)]
-pub struct Model {
-    pub meshes: Vec<Mesh>,
-    pub textures_loaded: Vec<Texture2D>,
-    directory: String,
-}
-
-impl Model {
-    pub fn new(path: &str) -> Self {
-        let (prefix, _) = path.split_once(".").unwrap();
-        let file = Path::new("ressources")
-            .join("models")
-            .join(prefix)
-            .join(path);
-
-        let mut model = Model::default();
-
-        model.load(file.as_os_str().to_str().unwrap());
-
-        model
-    }
-
-    fn load(&mut self, path: &str) {
-        let path = Path::new(path);
-
-        self.directory = path
-            .parent()
-            .unwrap_or(Path::new(""))
-            .to_str()
-            .unwrap()
-            .into();
-        let obj = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS);
-
-        let (models, materials) =
-            obj.expect(format!("filename: \"{}\"", path.to_str().unwrap()).as_str());
-        let materials = materials.unwrap();
-        for model in models {
-            let mesh = &model.mesh;
-            let num_vertices = mesh.positions.len() / 3;
-
-            let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
-            let indices: Vec<u32> = mesh.indices.clone();
-
-            let p = &mesh.positions;
-            let n = &mesh.normals;
-            let t = &mesh.texcoords;
-
-            for i in 0..num_vertices {
-                vertices.push(Vertex {
-                    position: vec3(p[i * 3], p[i * 3 + 1], p[i * 3 + 2]),
-                    normal: vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2]),
-                    tex_coords: vec2(t[i * 2], t[i * 2 + 1]),
-                });
-                //println!("tex_coords: ({}, {})", t[i * 2], t[i * 2 + 1]);
-            }
-
-            let mut textures = Vec::new();
-            if let Some(material_id) = mesh.material_id {
-                let material = &materials[material_id];
-
-                if !material.diffuse_texture.is_empty() {
-                    let texture =
-                        self.load_material(&material.diffuse_texture, TextureType::Diffuse);
-                    textures.push(texture);
-                }
-                if !material.specular_texture.is_empty() {
-                    let texture =
-                        self.load_material(&material.specular_texture, TextureType::Specular);
-                    textures.push(texture);
-                }
-            }
-
-            self.meshes.push(Mesh::new(vertices, indices, textures));
-        }
-    }
-
-    fn load_material(&mut self, path: &str, ty: TextureType) -> Texture2D {
-        {
-            let texture = self.textures_loaded.iter().find(|t| t.path == path);
-            if let Some(texture) = texture {
-                return texture.clone();
-            }
-        }
-
-        let texture = Texture2D::from_image(path, &self.directory, ty);
-        self.textures_loaded.push(texture.clone());
-        texture
-    }
-}
-
-impl Draw for Model {
-    fn draw(&self, shader: &ShaderProgram) {
-        for mesh in &self.meshes {
-            mesh.draw(shader);
-        }
-    }
-}
+use {
+    glm::{vec2, vec3, vec4},
+    std::path::Path,
+};
+
+use crate::{
+    animation_clip::AnimationClip,
+    bounds::{Aabb, BoundingSphere},
+    cooked::{cooked_path, source_checksum},
+    draw::{Draw, DrawOptions},
+    import_options::ImportOptions,
+    material::Material,
+    mesh::Mesh,
+    mesh::Vertex,
+    normals::generate_normals,
+    optimize::optimize_mesh,
+    shader_program::ShaderProgram,
+    skeleton::Skeleton,
+    tangents::generate_tangents,
+    texture::{Texture2D, TextureType},
+};
+
+#[derive(Default)]
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub textures_loaded: Vec<Texture2D>,
+    /// Only set for skinned models, animations are played through `AnimatedModel`
+    pub skeleton: Option<Skeleton>,
+    pub animations: Vec<AnimationClip>,
+    pub(crate) directory: String,
+}
+
+impl Model {
+    pub fn with_options(path: &str, options: &ImportOptions) -> Self {
+        let (prefix, _) = path.split_once(".").unwrap();
+        let file = Path::new("ressources")
+            .join("models")
+            .join(prefix)
+            .join(path);
+
+        Model::from_file(&file, options)
+    }
+
+    /// Loads the cooked version of the file when it is up to date, otherwise imports the file
+    /// and cooks it for the next time.
+    pub fn from_file(file: &Path, options: &ImportOptions) -> Self {
+        if !options.use_cooked {
+            return Model::import(file, options);
+        }
+
+        let checksum = source_checksum(file, options)
+            .unwrap_or_else(|e| panic!("{}, filename: \"{}\"", e, file.to_str().unwrap()));
+        let cooked = cooked_path(file, options);
+
+        if cooked.exists() {
+            match Model::read_cooked(&cooked, checksum) {
+                Ok(model) => return model,
+                Err(e) => println!(
+                    "Warning: ignoring \"{}\" ({}), importing the model again",
+                    cooked.to_str().unwrap(),
+                    e
+                ),
+            }
+        }
+
+        let model = Model::import(file, options);
+        if let Err(e) = model.write_cooked(&cooked, checksum) {
+            println!(
+                "Warning: couldn't write \"{}\" ({})",
+                cooked.to_str().unwrap(),
+                e
+            );
+        }
+        model
+    }
+
+    /// Imports the source file, ignoring any cooked version
+    pub fn import(file: &Path, options: &ImportOptions) -> Self {
+        let mut model = Model::default();
+
+        let file_path = file.as_os_str().to_str().unwrap();
+        match file.extension().and_then(|e| e.to_str()) {
+            Some("gltf") | Some("glb") => model.load_gltf(file_path, options),
+            _ => model.load_obj(file_path, options),
+        }
+        model.fit_bounds_to_animations();
+
+        model
+    }
+
+    /// Widens the bounds of the meshes to the poses of every animation, sampled 30 times per
+    /// second, so skinned meshes don't get culled once they move out of their bind pose
+    fn fit_bounds_to_animations(&mut self) {
+        let skeleton = match &self.skeleton {
+            Some(skeleton) => skeleton,
+            None => return,
+        };
+
+        let mut poses = vec![skeleton.joint_matrices(&skeleton.rest_pose())];
+        for clip in &self.animations {
+            let duration = clip.duration();
+            let samples = (duration * 30.0).ceil() as usize;
+            for i in 0..=samples {
+                let time = duration * i as f32 / samples.max(1) as f32;
+                poses.push(skeleton.joint_matrices(&clip.sample(skeleton, time)));
+            }
+        }
+
+        for mesh in &mut self.meshes {
+            let mut positions = Vec::new();
+            for (i, vertex) in mesh.vertices().iter().enumerate() {
+                let reachable = mesh.reachable_positions(i);
+                // vertices without weights aren't moved by the skeleton
+                if vertex.weights == vec4(0.0, 0.0, 0.0, 0.0) {
+                    positions.extend(reachable);
+                    continue;
+                }
+
+                for joint_matrices in &poses {
+                    let skin = (0..4)
+                        .map(|j| joint_matrices[vertex.joints[j] as usize] * vertex.weights[j])
+                        .fold(glm::Mat4::zeros(), |skin, m| skin + m);
+                    positions.extend(
+                        reachable
+                            .iter()
+                            .map(|p| (skin * vec4(p.x, p.y, p.z, 1.0)).xyz()),
+                    );
+                }
+            }
+
+            mesh.aabb = Aabb::from_points(&positions);
+            mesh.bounding_sphere = BoundingSphere::from_points(&positions);
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        self.meshes
+            .iter()
+            .fold(Aabb::empty(), |aabb, mesh| aabb.union(&mesh.aabb))
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.meshes
+            .iter()
+            .map(|mesh| mesh.bounding_sphere)
+            .reduce(|sphere, other| sphere.union(&other))
+            .unwrap_or(BoundingSphere {
+                center: glm::vec3(0.0, 0.0, 0.0),
+                radius: 0.0,
+            })
+    }
+
+    fn load_obj(&mut self, path: &str, options: &ImportOptions) {
+        let path = Path::new(path);
+
+        self.directory = path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_str()
+            .unwrap()
+            .into();
+        let obj = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS);
+
+        let (models, materials) =
+            obj.unwrap_or_else(|e| panic!("{}, filename: \"{}\"", e, path.to_str().unwrap()));
+        let materials = materials.unwrap_or_else(|e| {
+            println!(
+                "Warning: couldn't load the materials of \"{}\" ({}), using the default material",
+                path.to_str().unwrap(),
+                e
+            );
+            Vec::new()
+        });
+        for model in models {
+            let mesh = &model.mesh;
+            let num_vertices = mesh.positions.len() / 3;
+
+            let mut vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
+            let mut indices: Vec<u32> = mesh.indices.clone();
+
+            let p = &mesh.positions;
+            let n = &mesh.normals;
+            let t = &mesh.texcoords;
+            let c = &mesh.vertex_color;
+
+            for i in 0..num_vertices {
+                let normal = if n.is_empty() {
+                    vec3(0.0, 0.0, 0.0)
+                } else {
+                    vec3(n[i * 3], n[i * 3 + 1], n[i * 3 + 2])
+                };
+                let tex_coords = if t.is_empty() {
+                    vec2(0.0, 0.0)
+                } else {
+                    vec2(t[i * 2], t[i * 2 + 1])
+                };
+                let color = if c.is_empty() {
+                    vec4(1.0, 1.0, 1.0, 1.0)
+                } else {
+                    vec4(c[i * 3], c[i * 3 + 1], c[i * 3 + 2], 1.0)
+                };
+
+                // OBJ only has one UV set, `map_lightmap` textures have to share it
+                vertices.push(Vertex {
+                    position: vec3(p[i * 3], p[i * 3 + 1], p[i * 3 + 2]),
+                    normal,
+                    tex_coords,
+                    tex_coords2: tex_coords,
+                    color,
+                    ..Default::default()
+                });
+                //println!("tex_coords: ({}, {})", t[i * 2], t[i * 2 + 1]);
+            }
+
+            if n.is_empty() {
+                generate_normals(&mut vertices, &mut indices, options.crease_angle);
+            }
+            generate_tangents(&mut vertices, &mut indices);
+
+            if options.optimize {
+                let stats = optimize_mesh(&mut vertices, &mut indices);
+                println!("Optimized mesh \"{}\": {}", model.name, stats);
+            }
+
+            let mut textures = Vec::new();
+            let mtl = mesh.material_id.and_then(|id| materials.get(id));
+            if let Some(material) = mtl {
+                // tobj only knows map_Bump/bump, map_Ke and norm end up with the unknown params
+                let normal_texture = if material.normal_texture.is_empty() {
+                    mtl_param(material, "norm")
+                } else {
+                    &material.normal_texture
+                };
+                let material_textures = [
+                    (material.diffuse_texture.as_str(), TextureType::Diffuse),
+                    (material.specular_texture.as_str(), TextureType::Specular),
+                    (mtl_param(material, "map_Ke"), TextureType::Emission),
+                    (material.ambient_texture.as_str(), TextureType::Ambient),
+                    (normal_texture, TextureType::Normal),
+                    (mtl_param(material, "map_Pm"), TextureType::Metallic),
+                    (mtl_param(material, "map_Pr"), TextureType::Roughness),
+                    (mtl_param(material, "map_lightmap"), TextureType::Lightmap),
+                ];
+
+                for (path, ty) in material_textures {
+                    if !path.is_empty() {
+                        textures.push(self.load_material(mtl_texture_path(path), ty));
+                    }
+                }
+            }
+            let mut material = match mtl {
+                Some(material) => Material::from_mtl(material, textures),
+                None => Material::with_textures(textures),
+            };
+            self.add_default_textures(&mut material);
+
+            self.meshes.push(Mesh::with_lods(
+                vertices,
+                indices,
+                material,
+                &options.lod_ratios,
+                options.optimize,
+            ));
+        }
+    }
+
+    pub(crate) fn load_material(&mut self, path: &str, ty: TextureType) -> Texture2D {
+        let directory = self.directory.clone();
+        self.cached_texture(path, ty.clone(), || {
+            Texture2D::from_image(path, &directory, ty)
+        })
+    }
+
+    /// Fills in the maps a material doesn't provide with neutral ones so shaders always have
+    /// something to sample, PBR factors are multiplied with white maps.
+    pub(crate) fn add_default_textures(&mut self, material: &mut Material) {
+        let mut defaults = vec![
+            (TextureType::Diffuse, [255, 255, 255, 255]),
+            (TextureType::Normal, [128, 128, 255, 255]),
+        ];
+        if material.pbr.is_some() {
+            defaults.extend([
+                (TextureType::Emission, [255, 255, 255, 255]),
+                (TextureType::Metallic, [255, 255, 255, 255]),
+                (TextureType::Roughness, [255, 255, 255, 255]),
+                (TextureType::MetallicRoughness, [255, 255, 255, 255]),
+                (TextureType::Occlusion, [255, 255, 255, 255]),
+            ]);
+        }
+
+        for (ty, color) in defaults {
+            let name = ty.to_string();
+            if !material.textures.iter().any(|t| t.ty.to_string() == name) {
+                let texture = self.color_texture(color, ty);
+                material.textures.push(texture);
+            }
+        }
+    }
+
+    pub(crate) fn color_texture(&mut self, color: [u8; 4], ty: TextureType) -> Texture2D {
+        self.cached_texture(&format!("#color{:?}", color), ty.clone(), || {
+            Texture2D::from_color(color, ty)
+        })
+    }
+
+    /// Returns the texture already loaded under `key`, or loads it with `load` and keeps it
+    /// around so other meshes of the model can share it. Color and data maps don't share
+    /// textures since only colors are decoded from sRGB.
+    pub(crate) fn cached_texture<F>(&mut self, key: &str, ty: TextureType, load: F) -> Texture2D
+    where
+        F: FnOnce() -> Texture2D,
+    {
+        {
+            let texture = self
+                .textures_loaded
+                .iter()
+                .find(|t| t.path == key && t.ty.is_srgb() == ty.is_srgb());
+            if let Some(texture) = texture {
+                let mut texture = texture.clone();
+                texture.ty = ty;
+                return texture;
+            }
+        }
+
+        let texture = load();
+        self.textures_loaded.push(texture.clone());
+        texture
+    }
+}
+
+impl Draw for Model {
+    fn draw(&self, shader: &ShaderProgram) {
+        self.draw_with(shader, &DrawOptions::default());
+    }
+
+    fn draw_with(&self, shader: &ShaderProgram, options: &DrawOptions) {
+        for mesh in &self.meshes {
+            mesh.draw_with(shader, options);
+        }
+    }
+}
+
+fn mtl_param<'a>(material: &'a tobj::Material, key: &str) -> &'a str {
+    material
+        .unknown_param
+        .get(key)
+        .map(String::as_str)
+        .unwrap_or("")
+}
+
+/// Texture statements can start with options (`map_Bump -bm 0.5 normal.png`), the file name
+/// is what comes last.
+fn mtl_texture_path(value: &str) -> &str {
+    if value.starts_with('-') {
+        value.split_whitespace().last().unwrap_or(value)
+    } else {
+        value
+    }
+}
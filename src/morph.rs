@@ -0,0 +1,57 @@
+use glm::TVec3;
+
+use crate::mesh::Vertex;
+
+/// Offsets added to the vertices of a mesh, scaled by the weight of the target
+#[derive(Clone)]
+pub struct MorphTarget {
+    pub position_deltas: Vec<TVec3<f32>>,
+    /// Empty when the target leaves the normals alone
+    pub normal_deltas: Vec<TVec3<f32>>,
+}
+
+impl MorphTarget {
+    /// Follows the vertices after they got re-indexed, `sources` giving the former index of
+    /// each vertex
+    pub fn remap(&mut self, sources: &[u32]) {
+        let remap = |deltas: &[TVec3<f32>]| sources.iter().map(|&s| deltas[s as usize]).collect();
+
+        self.position_deltas = remap(&self.position_deltas);
+        if !self.normal_deltas.is_empty() {
+            self.normal_deltas = remap(&self.normal_deltas);
+        }
+    }
+}
+
+/// The vertices with every target added according to its weight, normals are renormalized
+/// while tangents are left as they are.
+pub fn apply_morph_targets(
+    vertices: &[Vertex],
+    targets: &[MorphTarget],
+    weights: &[f32],
+) -> Vec<Vertex> {
+    let mut morphed = vertices.to_vec();
+
+    for (target, &weight) in targets.iter().zip(weights) {
+        if weight == 0.0 {
+            continue;
+        }
+
+        for (vertex, delta) in morphed.iter_mut().zip(&target.position_deltas) {
+            vertex.position += delta * weight;
+        }
+        for (vertex, delta) in morphed.iter_mut().zip(&target.normal_deltas) {
+            vertex.normal += delta * weight;
+        }
+    }
+
+    if targets.iter().any(|t| !t.normal_deltas.is_empty()) {
+        for vertex in &mut morphed {
+            if vertex.normal.norm_squared() > 0.0 {
+                vertex.normal.normalize_mut();
+            }
+        }
+    }
+
+    morphed
+}
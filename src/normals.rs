@@ -0,0 +1,156 @@
+use {
+    glm::{cross, dot, vec3, TVec3},
+    std::collections::HashMap,
+};
+
+use crate::{mesh::Vertex, utils::to_radians};
+
+/// Generates normals for an indexed triangle list.
+///
+/// Corners sharing a position are smoothed together as long as the angle between their faces
+/// stays under `crease_angle` degrees, vertices get split where it doesn't. Returns the
+/// vertex each new vertex was made from.
+pub fn generate_normals(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    crease_angle: f32,
+) -> Vec<u32> {
+    let cos_crease = to_radians(crease_angle.clamp(0.0, 180.0)).cos();
+
+    // Left unnormalized so bigger faces weigh more in the smoothed normals
+    let face_normals: Vec<TVec3<f32>> = indices
+        .chunks_exact(3)
+        .map(|t| {
+            let a = vertices[t[0] as usize].position;
+            let b = vertices[t[1] as usize].position;
+            let c = vertices[t[2] as usize].position;
+            cross(&(b - a), &(c - a))
+        })
+        .collect();
+    let unit_normals: Vec<TVec3<f32>> = face_normals.iter().map(safe_normalize).collect();
+
+    // Corners are grouped by position rather than by index since OBJ faces with different
+    // texture coordinates don't share their vertices.
+    let mut corners_at: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (corner, &index) in indices.iter().enumerate() {
+        corners_at
+            .entry(position_key(&vertices[index as usize].position))
+            .or_default()
+            .push(corner);
+    }
+
+    let mut new_vertices: Vec<Vertex> = Vec::with_capacity(vertices.len());
+    let mut new_indices: Vec<u32> = Vec::with_capacity(indices.len());
+    let mut remap: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    let mut sources: Vec<u32> = Vec::with_capacity(vertices.len());
+
+    for (corner, &index) in indices.iter().enumerate() {
+        let face = corner / 3;
+        let vertex = &vertices[index as usize];
+
+        let mut normal = vec3(0.0, 0.0, 0.0);
+        for &other in &corners_at[&position_key(&vertex.position)] {
+            let other_face = other / 3;
+            if dot(&unit_normals[face], &unit_normals[other_face]) >= cos_crease - 1e-5 {
+                normal += face_normals[other_face];
+            }
+        }
+        let normal = if normal.norm_squared() > 0.0 {
+            normal.normalize()
+        } else {
+            unit_normals[face]
+        };
+
+        let new_index = *remap
+            .entry((index, position_key(&normal)))
+            .or_insert_with(|| {
+                new_vertices.push(Vertex { normal, ..*vertex });
+                sources.push(index);
+                (new_vertices.len() - 1) as u32
+            });
+        new_indices.push(new_index);
+    }
+
+    *vertices = new_vertices;
+    *indices = new_indices;
+    sources
+}
+
+fn safe_normalize(v: &TVec3<f32>) -> TVec3<f32> {
+    if v.norm_squared() > 0.0 {
+        v.normalize()
+    } else {
+        vec3(0.0, 1.0, 0.0)
+    }
+}
+
+pub(crate) fn position_key(p: &TVec3<f32>) -> [u32; 3] {
+    // Adding 0.0 turns -0.0 into 0.0 so both end up with the same bits
+    [
+        (p.x + 0.0).to_bits(),
+        (p.y + 0.0).to_bits(),
+        (p.z + 0.0).to_bits(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives;
+
+    /// The cube's corners without their normals, as an OBJ file lacking them would give
+    fn bare_cube() -> (Vec<Vertex>, Vec<u32>) {
+        let (vertices, indices) = primitives::cube();
+        let vertices = vertices
+            .into_iter()
+            .map(|v| Vertex {
+                position: v.position,
+                ..Default::default()
+            })
+            .collect();
+        (vertices, indices)
+    }
+
+    #[test]
+    fn sharp_edges_keep_the_face_normals() {
+        let (mut vertices, mut indices) = bare_cube();
+        let sources = generate_normals(&mut vertices, &mut indices, 60.0);
+
+        assert_eq!(sources.len(), vertices.len());
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| vertices[triangle[k] as usize]);
+            let face = cross(&(b.position - a.position), &(c.position - a.position)).normalize();
+            for vertex in [a, b, c] {
+                assert!((vertex.normal - face).norm() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_corners_average_their_faces() {
+        let (mut vertices, mut indices) = bare_cube();
+        generate_normals(&mut vertices, &mut indices, 180.0);
+
+        // the triangles around a corner weigh by their area, so the normal leans towards the
+        // face with both of its triangles there but stays close to the diagonal
+        let mut normal_at: HashMap<[u32; 3], TVec3<f32>> = HashMap::new();
+        for vertex in &vertices {
+            assert!(dot(&vertex.normal, &vertex.position.normalize()) > 0.9);
+            let shared = normal_at
+                .entry(position_key(&vertex.position))
+                .or_insert(vertex.normal);
+            assert!((*shared - vertex.normal).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn sources_point_at_the_original_vertices() {
+        let (original, _) = bare_cube();
+        let (mut vertices, mut indices) = bare_cube();
+        let sources = generate_normals(&mut vertices, &mut indices, 60.0);
+
+        for (vertex, &source) in vertices.iter().zip(&sources) {
+            assert_eq!(vertex.position, original[source as usize].position);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use {
+    glm::{cross, dot, vec3, TVec3},
+    std::{collections::HashMap, fmt},
+};
+
+use crate::mesh::Vertex;
+
+/// Size of the FIFO post-transform cache simulated for the statistics, a common size for
+/// desktop GPUs
+const STATS_CACHE_SIZE: usize = 16;
+
+/// Cache size assumed by the triangle ordering
+const CACHE_SIZE: usize = 32;
+
+/// Clusters drawn out of order for overdraw may be that much worse than the whole mesh in
+/// terms of vertex cache
+const OVERDRAW_THRESHOLD: f32 = 1.05;
+
+pub struct OptimizationStats {
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+    /// Average cache miss ratio: vertices transformed per triangle
+    pub acmr_before: f32,
+    pub acmr_after: f32,
+}
+
+impl fmt::Display for OptimizationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {} vertices, ACMR {:.3} -> {:.3}",
+            self.vertices_before, self.vertices_after, self.acmr_before, self.acmr_after
+        )
+    }
+}
+
+/// Merges duplicate vertices, reorders the triangles for the post-transform vertex cache and
+/// then for overdraw, and finally reorders the vertices in the order they are first used.
+pub fn optimize_mesh(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>) -> OptimizationStats {
+    let vertices_before = vertices.len();
+    let acmr_before = acmr(indices);
+
+    merge_duplicates(vertices, indices);
+    *indices = optimize_triangle_order(vertices, indices);
+    optimize_vertex_fetch(vertices, indices);
+
+    OptimizationStats {
+        vertices_before,
+        vertices_after: vertices.len(),
+        acmr_before,
+        acmr_after: acmr(indices),
+    }
+}
+
+/// Reorders the triangles for the post-transform vertex cache and then for overdraw, the
+/// vertices are left as they are so every level of detail of a mesh can go through it
+pub fn optimize_triangle_order(vertices: &[Vertex], indices: &[u32]) -> Vec<u32> {
+    let indices = optimize_vertex_cache(indices, vertices.len());
+    optimize_overdraw(vertices, &indices)
+}
+
+/// Average number of vertices transformed per triangle with a FIFO cache, between 0.5 for an
+/// ideal grid and 3.0 when nothing is ever reused
+pub fn acmr(indices: &[u32]) -> f32 {
+    if indices.is_empty() {
+        return 0.0;
+    }
+
+    let mut cache: Vec<u32> = Vec::with_capacity(STATS_CACHE_SIZE);
+    let mut misses = 0;
+    for &index in indices {
+        if !cache.contains(&index) {
+            misses += 1;
+            if cache.len() == STATS_CACHE_SIZE {
+                cache.remove(0);
+            }
+            cache.push(index);
+        }
+    }
+
+    misses as f32 / (indices.len() / 3) as f32
+}
+
+fn merge_duplicates(vertices: &mut Vec<Vertex>, indices: &mut [u32]) {
+    let mut unique: Vec<Vertex> = Vec::with_capacity(vertices.len());
+    let mut index_of: HashMap<Vec<u32>, u32> = HashMap::new();
+
+    let remap: Vec<u32> = vertices
+        .iter()
+        .map(|vertex| {
+            *index_of.entry(vertex_key(vertex)).or_insert_with(|| {
+                unique.push(*vertex);
+                (unique.len() - 1) as u32
+            })
+        })
+        .collect();
+
+    for index in indices.iter_mut() {
+        *index = remap[*index as usize];
+    }
+    *vertices = unique;
+}
+
+fn vertex_key(vertex: &Vertex) -> Vec<u32> {
+    [
+        vertex.position.as_slice(),
+        vertex.normal.as_slice(),
+        vertex.tex_coords.as_slice(),
+        vertex.tangent.as_slice(),
+        vertex.bitangent.as_slice(),
+        vertex.color.as_slice(),
+        vertex.tex_coords2.as_slice(),
+        vertex.joints.as_slice(),
+        vertex.weights.as_slice(),
+    ]
+    .concat()
+    .iter()
+    .map(|c| (c + 0.0).to_bits())
+    .collect()
+}
+
+/// Tom Forsyth's linear-speed vertex cache optimisation: triangles are emitted greedily, the
+/// next one being the best scored among those touching the simulated cache. Vertices score
+/// higher when they are recently used and when few triangles are left to use them.
+fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+
+    let mut triangles_of: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        for &v in triangle {
+            triangles_of[v as usize].push(t);
+        }
+    }
+
+    let mut cache_position: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_scores: Vec<f32> = (0..vertex_count)
+        .map(|v| vertex_score(None, triangles_of[v].len()))
+        .collect();
+    let mut triangle_scores: Vec<f32> = indices
+        .chunks_exact(3)
+        .map(|t| t.iter().map(|&v| vertex_scores[v as usize]).sum())
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+
+    let mut result = Vec::with_capacity(indices.len());
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    // Fallback when nothing around the cache is left, triangles before it are all emitted
+    let mut next_unemitted = 0;
+
+    let mut best =
+        (0..triangle_count).max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+
+    while let Some(triangle) = best {
+        emitted[triangle] = true;
+        let corners = &indices[triangle * 3..triangle * 3 + 3];
+        result.extend_from_slice(corners);
+
+        for &v in corners {
+            triangles_of[v as usize].retain(|&t| t != triangle);
+        }
+
+        // The triangle's vertices move to the front of the cache, the ones pushed out of it
+        // lose their cache bonus
+        let mut new_cache: Vec<u32> = corners.to_vec();
+        new_cache.extend(cache.iter().filter(|v| !corners.contains(v)));
+        let evicted: Vec<u32> = new_cache.split_off(new_cache.len().min(CACHE_SIZE));
+        for &v in &evicted {
+            cache_position[v as usize] = None;
+        }
+        cache = new_cache;
+
+        for (position, &v) in cache.iter().enumerate() {
+            cache_position[v as usize] = Some(position);
+        }
+
+        best = None;
+        let mut best_score = f32::NEG_INFINITY;
+        for &v in cache.iter().chain(evicted.iter()) {
+            let v = v as usize;
+            let score = vertex_score(cache_position[v], triangles_of[v].len());
+            let delta = score - vertex_scores[v];
+            vertex_scores[v] = score;
+
+            for &t in &triangles_of[v] {
+                triangle_scores[t] += delta;
+            }
+        }
+        for &v in &cache {
+            for &t in &triangles_of[v as usize] {
+                if triangle_scores[t] > best_score {
+                    best_score = triangle_scores[t];
+                    best = Some(t);
+                }
+            }
+        }
+
+        if best.is_none() {
+            while next_unemitted < triangle_count && emitted[next_unemitted] {
+                next_unemitted += 1;
+            }
+            if next_unemitted < triangle_count {
+                best = Some(next_unemitted);
+            }
+        }
+    }
+
+    result
+}
+
+fn vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        // The last triangle's vertices get a fixed score so that the next triangle doesn't
+        // reuse them too eagerly, which would only make strips
+        Some(position) if position < 3 => 0.75,
+        Some(position) => {
+            let scale = 1.0 / (CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(1.5)
+        }
+        None => 0.0,
+    };
+    // Bonus for vertices with few triangles left, so that they get finished off
+    let valence_score = 2.0 * (remaining_triangles as f32).powf(-0.5);
+
+    cache_score + valence_score
+}
+
+/// Splits the cache-ordered triangles into clusters and draws the clusters facing outwards
+/// first, so that they hide what's behind them (Sander, Nehab & Barczak). Clusters are cut
+/// wherever the cache restarts and wherever it stays efficient enough to afford a restart.
+fn optimize_overdraw(vertices: &[Vertex], indices: &[u32]) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return Vec::new();
+    }
+
+    let mesh_acmr = acmr(indices);
+    let mut clusters: Vec<(usize, usize)> = Vec::new();
+    let mut cache: Vec<u32> = Vec::with_capacity(STATS_CACHE_SIZE);
+    let mut start = 0;
+    let mut misses = 0;
+
+    for t in 0..triangle_count {
+        let mut triangle_misses = 0;
+        for &v in &indices[t * 3..t * 3 + 3] {
+            if !cache.contains(&v) {
+                triangle_misses += 1;
+                if cache.len() == STATS_CACHE_SIZE {
+                    cache.remove(0);
+                }
+                cache.push(v);
+            }
+        }
+
+        // A triangle missing all its vertices starts over anyway
+        if triangle_misses == 3 && t > start {
+            clusters.push((start, t));
+            start = t;
+            misses = 0;
+        }
+        misses += triangle_misses;
+
+        let cluster_acmr = misses as f32 / (t + 1 - start) as f32;
+        if t + 1 < triangle_count && cluster_acmr <= mesh_acmr * OVERDRAW_THRESHOLD {
+            clusters.push((start, t + 1));
+            start = t + 1;
+            misses = 0;
+            cache.clear();
+        }
+    }
+    if start < triangle_count {
+        clusters.push((start, triangle_count));
+    }
+
+    let position = |i: u32| vertices[i as usize].position;
+    let mut mesh_centroid = vec3(0.0, 0.0, 0.0);
+    for &i in indices {
+        mesh_centroid += position(i);
+    }
+    mesh_centroid /= indices.len() as f32;
+
+    let mut sorted: Vec<(f32, (usize, usize))> = clusters
+        .into_iter()
+        .map(|(start, end)| {
+            let mut centroid: TVec3<f32> = vec3(0.0, 0.0, 0.0);
+            let mut normal: TVec3<f32> = vec3(0.0, 0.0, 0.0);
+            for t in start..end {
+                let [a, b, c] = [0, 1, 2].map(|k| position(indices[t * 3 + k]));
+                centroid += (a + b + c) / 3.0;
+                normal += cross(&(b - a), &(c - a));
+            }
+            centroid /= (end - start) as f32;
+
+            (dot(&(centroid - mesh_centroid), &normal), (start, end))
+        })
+        .collect();
+    sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    sorted
+        .into_iter()
+        .flat_map(|(_, (start, end))| &indices[start * 3..end * 3])
+        .copied()
+        .collect()
+}
+
+/// Renumbers the vertices in the order the triangles use them, unused vertices are dropped
+fn optimize_vertex_fetch(vertices: &mut Vec<Vertex>, indices: &mut [u32]) {
+    let mut remap: Vec<Option<u32>> = vec![None; vertices.len()];
+    let mut reordered: Vec<Vertex> = Vec::with_capacity(vertices.len());
+
+    for index in indices.iter_mut() {
+        let new_index = *remap[*index as usize].get_or_insert_with(|| {
+            reordered.push(vertices[*index as usize]);
+            (reordered.len() - 1) as u32
+        });
+        *index = new_index;
+    }
+
+    *vertices = reordered;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{normals::position_key, primitives};
+
+    /// Triangles rotated to start with their smallest key, which keeps their winding, and
+    /// sorted so that two lists of the same triangles compare equal
+    fn canonical<K: Ord + Copy>(indices: &[u32], key: impl Fn(u32) -> K) -> Vec<[K; 3]> {
+        let mut triangles: Vec<[K; 3]> = indices
+            .chunks_exact(3)
+            .map(|t| {
+                let keys = [0, 1, 2].map(|k| key(t[k]));
+                let first = (0..3).min_by_key(|&k| keys[k]).unwrap();
+                [0, 1, 2].map(|k| keys[(first + k) % 3])
+            })
+            .collect();
+        triangles.sort();
+        triangles
+    }
+
+    /// A grid with its triangles in a cache unfriendly order
+    fn shuffled_grid() -> (Vec<Vertex>, Vec<u32>) {
+        let (vertices, indices) = primitives::grid(16, 16);
+        let triangles: Vec<&[u32]> = indices.chunks_exact(3).collect();
+        let shuffled = (0..triangles.len())
+            .flat_map(|i| triangles[i * 37 % triangles.len()])
+            .copied()
+            .collect();
+        (vertices, shuffled)
+    }
+
+    #[test]
+    fn triangle_order_is_a_permutation() {
+        for (vertices, indices) in [shuffled_grid(), primitives::icosphere(3)] {
+            let optimized = optimize_triangle_order(&vertices, &indices);
+            assert_eq!(canonical(&optimized, |i| i), canonical(&indices, |i| i));
+        }
+    }
+
+    #[test]
+    fn optimized_mesh_keeps_its_triangles() {
+        let (mut vertices, mut indices) = shuffled_grid();
+        let (original_vertices, original_indices) = (vertices.clone(), indices.clone());
+        let stats = optimize_mesh(&mut vertices, &mut indices);
+
+        // vertices get merged and renumbered, the triangles are compared by position
+        assert_eq!(
+            canonical(&indices, |i| position_key(&vertices[i as usize].position)),
+            canonical(&original_indices, |i| position_key(
+                &original_vertices[i as usize].position
+            )),
+        );
+        assert!(indices.iter().all(|&i| (i as usize) < vertices.len()));
+        assert!(stats.vertices_after <= stats.vertices_before);
+        assert!(stats.acmr_after < stats.acmr_before);
+    }
+
+    #[test]
+    fn acmr_bounds() {
+        assert_eq!(acmr(&[]), 0.0);
+        // separate triangles never reuse a vertex
+        assert_eq!(acmr(&(0..30).collect::<Vec<u32>>()), 3.0);
+    }
+}
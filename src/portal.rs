@@ -20,6 +20,11 @@ impl Portal {
         }
     }
 
+    /// The framebuffer is drawn with the window's viewport, it follows the window's size
+    pub fn resize(&mut self, window_width: i32, window_height: i32) {
+        self.framebuffer = Framebuffer::new(window_width, window_height);
+    }
+
     pub fn bind_framebuffer(&self) {
         self.framebuffer.bind()
     }
@@ -28,6 +33,8 @@ impl Portal {
         active_texture(GL_TEXTURE0);
         self.framebuffer.bind_texture();
         shader.set_int("texture_diffuse1", 0);
-        self.surface.draw(&shader);
+        shader.set_int("unlit", 1);
+        self.surface.draw(shader);
+        shader.set_int("unlit", 0);
     }
 }
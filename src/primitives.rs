@@ -0,0 +1,570 @@
+use {
+    glm::{vec2, vec3, TVec2, TVec3},
+    std::{collections::HashMap, f32::consts::PI},
+};
+
+use crate::{mesh::Vertex, tangents::generate_tangents};
+
+// Procedurally generated shapes, all centered on the origin and fitting in the same
+// `[-1, 1]` box as the unit plane. Faces are counter-clockwise seen from the outside.
+
+pub fn cube() -> (Vec<Vertex>, Vec<u32>) {
+    // (normal, u axis, v axis) of each face, u x v = normal
+    let faces = [
+        (
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, -1.0),
+            vec3(0.0, 1.0, 0.0),
+        ),
+        (
+            vec3(-1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 1.0, 0.0),
+        ),
+        (
+            vec3(0.0, 1.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, -1.0),
+        ),
+        (
+            vec3(0.0, -1.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+        ),
+        (
+            vec3(0.0, 0.0, 1.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        ),
+        (
+            vec3(0.0, 0.0, -1.0),
+            vec3(-1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        ),
+    ];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+
+    for (normal, u, v) in faces {
+        let first = vertices.len() as u32;
+        for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            vertices.push(vertex(
+                normal + u * su + v * sv,
+                normal,
+                vec2((su + 1.0) / 2.0, (sv + 1.0) / 2.0),
+            ));
+        }
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    finish(vertices, indices)
+}
+
+/// Flat grid in the XZ plane facing up, `x_subdivisions` by `z_subdivisions` quads
+pub fn grid(x_subdivisions: u32, z_subdivisions: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let x_subdivisions = x_subdivisions.max(1);
+    let z_subdivisions = z_subdivisions.max(1);
+
+    let mut vertices = Vec::new();
+    for j in 0..=z_subdivisions {
+        for i in 0..=x_subdivisions {
+            let u = i as f32 / x_subdivisions as f32;
+            let v = j as f32 / z_subdivisions as f32;
+            vertices.push(vertex(
+                vec3(-1.0 + 2.0 * u, 0.0, 1.0 - 2.0 * v),
+                vec3(0.0, 1.0, 0.0),
+                vec2(u, v),
+            ));
+        }
+    }
+
+    let mut indices = Vec::new();
+    for j in 0..z_subdivisions {
+        for i in 0..x_subdivisions {
+            push_quad(&mut indices, x_subdivisions + 1, i, j);
+        }
+    }
+
+    finish(vertices, indices)
+}
+
+pub fn uv_sphere(segments: u32, rings: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let segments = segments.max(3);
+    let rings = rings.max(2);
+
+    let mut vertices = Vec::new();
+    for r in 0..=rings {
+        let phi = PI * r as f32 / rings as f32;
+        for s in 0..=segments {
+            let theta = 2.0 * PI * s as f32 / segments as f32;
+            let normal = vec3(theta.cos() * phi.sin(), phi.cos(), theta.sin() * phi.sin());
+            vertices.push(vertex(
+                normal,
+                normal,
+                vec2(
+                    1.0 - s as f32 / segments as f32,
+                    1.0 - r as f32 / rings as f32,
+                ),
+            ));
+        }
+    }
+
+    let row = segments + 1;
+    let mut indices = Vec::new();
+    for r in 0..rings {
+        for s in 0..segments {
+            let a = r * row + s;
+            let b = a + 1;
+            let c = a + row + 1;
+            let d = a + row;
+            // the triangles touching the poles would be degenerate
+            if r != 0 {
+                indices.extend([a, b, c]);
+            }
+            if r != rings - 1 {
+                indices.extend([a, c, d]);
+            }
+        }
+    }
+
+    finish(vertices, indices)
+}
+
+/// Subdivided icosahedron, each subdivision splits every triangle in four
+pub fn icosphere(subdivisions: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    let mut positions: Vec<TVec3<f32>> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&(x, y, z)| vec3(x, y, z).normalize())
+    .collect();
+
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, positions: &mut Vec<TVec3<f32>>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let p = (positions[a as usize] + positions[b as usize]).normalize();
+                positions.push(p);
+                (positions.len() - 1) as u32
+            })
+        };
+
+        let mut subdivided = Vec::with_capacity(triangles.len() * 4);
+        for [a, b, c] in triangles {
+            let ab = midpoint(a, b, &mut positions);
+            let bc = midpoint(b, c, &mut positions);
+            let ca = midpoint(c, a, &mut positions);
+            subdivided.extend([[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        triangles = subdivided;
+    }
+
+    // Spherical UV mapping. Triangles crossing the seam get their own copies of the
+    // vertices on the u = 0 side, and pole vertices take the u of the rest of their triangle.
+    let spherical_uv = |p: &TVec3<f32>| {
+        vec2(
+            0.5 - p.z.atan2(p.x) / (2.0 * PI),
+            0.5 + p.y.clamp(-1.0, 1.0).asin() / PI,
+        )
+    };
+    let mut vertices: Vec<Vertex> = positions
+        .iter()
+        .map(|p| vertex(*p, *p, spherical_uv(p)))
+        .collect();
+
+    let mut indices = Vec::with_capacity(triangles.len() * 3);
+    for triangle in triangles {
+        let uvs: Vec<TVec2<f32>> = triangle
+            .iter()
+            .map(|&i| vertices[i as usize].tex_coords)
+            .collect();
+        let crosses_seam = uvs.iter().map(|uv| uv.x).fold(0.0, f32::max)
+            - uvs.iter().map(|uv| uv.x).fold(1.0, f32::min)
+            > 0.5;
+
+        for (corner, &index) in triangle.iter().enumerate() {
+            let mut v = vertices[index as usize];
+            let is_pole = v.position.x.abs() < 1e-6 && v.position.z.abs() < 1e-6;
+
+            if is_pole {
+                let others: Vec<f32> = (0..3).filter(|&c| c != corner).map(|c| uvs[c].x).collect();
+                v.tex_coords.x = (others[0] + others[1]) / 2.0;
+            } else if crosses_seam && v.tex_coords.x < 0.5 {
+                v.tex_coords.x += 1.0;
+            } else {
+                indices.push(index);
+                continue;
+            }
+
+            vertices.push(v);
+            indices.push((vertices.len() - 1) as u32);
+        }
+    }
+
+    finish(vertices, indices)
+}
+
+/// Cylinder of radius 1 along the Y axis, with caps
+pub fn cylinder(segments: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let segments = segments.max(3);
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    let row = segments + 1;
+    for (v, y) in [(0.0, -1.0), (1.0, 1.0)] {
+        for s in 0..=segments {
+            let u = s as f32 / segments as f32;
+            let theta = 2.0 * PI * u;
+            let normal = vec3(theta.cos(), 0.0, -theta.sin());
+            vertices.push(vertex(vec3(normal.x, y, normal.z), normal, vec2(u, v)));
+        }
+    }
+    for s in 0..segments {
+        push_quad(&mut indices, row, s, 0);
+    }
+
+    push_cap(&mut vertices, &mut indices, segments, 1.0, 1.0);
+    push_cap(&mut vertices, &mut indices, segments, -1.0, 1.0);
+
+    finish(vertices, indices)
+}
+
+/// Cone of base radius 1 along the Y axis, apex at the top
+pub fn cone(segments: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let segments = segments.max(3);
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    // The slant rises 2 units over 1 unit of radius
+    let slope = 1.0 / 5.0_f32.sqrt();
+    for s in 0..segments {
+        let u0 = s as f32 / segments as f32;
+        let u1 = (s + 1) as f32 / segments as f32;
+        let um = (u0 + u1) / 2.0;
+        let side = |u: f32| {
+            let theta = 2.0 * PI * u;
+            vec3(theta.cos(), 0.0, -theta.sin())
+        };
+        let normal = |u: f32| {
+            let d = side(u);
+            vec3(d.x * 2.0 * slope, slope, d.z * 2.0 * slope)
+        };
+
+        // The apex gets a vertex per segment so its normal follows the face
+        let first = vertices.len() as u32;
+        let base0 = side(u0);
+        let base1 = side(u1);
+        vertices.push(vertex(
+            vec3(base0.x, -1.0, base0.z),
+            normal(u0),
+            vec2(u0, 0.0),
+        ));
+        vertices.push(vertex(
+            vec3(base1.x, -1.0, base1.z),
+            normal(u1),
+            vec2(u1, 0.0),
+        ));
+        vertices.push(vertex(vec3(0.0, 1.0, 0.0), normal(um), vec2(um, 1.0)));
+        indices.extend([first, first + 1, first + 2]);
+    }
+
+    push_cap(&mut vertices, &mut indices, segments, -1.0, 1.0);
+
+    finish(vertices, indices)
+}
+
+/// Torus around the Y axis, `major_radius` from the center to the middle of the tube
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    segments: u32,
+    sides: u32,
+) -> (Vec<Vertex>, Vec<u32>) {
+    let segments = segments.max(3);
+    let sides = sides.max(3);
+
+    let mut vertices = Vec::new();
+    for j in 0..=sides {
+        let v = j as f32 / sides as f32;
+        let phi = 2.0 * PI * v;
+        for i in 0..=segments {
+            let u = i as f32 / segments as f32;
+            let theta = 2.0 * PI * u;
+            let around = vec3(theta.cos(), 0.0, -theta.sin());
+            let normal = around * phi.cos() + vec3(0.0, phi.sin(), 0.0);
+            vertices.push(vertex(
+                around * major_radius + normal * minor_radius,
+                normal,
+                vec2(u, v),
+            ));
+        }
+    }
+
+    let mut indices = Vec::new();
+    for j in 0..sides {
+        for i in 0..segments {
+            push_quad(&mut indices, segments + 1, i, j);
+        }
+    }
+
+    finish(vertices, indices)
+}
+
+/// Capsule along the Y axis: a cylinder of `radius` capped by two hemispheres, `height` being
+/// the total height including the caps
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let segments = segments.max(3);
+    // rings per hemisphere
+    let rings = rings.max(1);
+    let half_cylinder = (height / 2.0 - radius).max(0.0);
+
+    // latitudes from the top pole to the bottom one, the equator row is doubled so the
+    // cylinder part gets its own band
+    let mut latitudes: Vec<(f32, f32)> = Vec::new();
+    for r in 0..=rings {
+        latitudes.push((PI / 2.0 * r as f32 / rings as f32, half_cylinder));
+    }
+    let first_bottom_ring = if half_cylinder > 0.0 { 0 } else { 1 };
+    for r in first_bottom_ring..=rings {
+        latitudes.push((
+            PI / 2.0 + PI / 2.0 * r as f32 / rings as f32,
+            -half_cylinder,
+        ));
+    }
+
+    let total_height = 2.0 * (half_cylinder + radius);
+    let mut vertices = Vec::new();
+    for &(phi, offset) in &latitudes {
+        for s in 0..=segments {
+            let u = s as f32 / segments as f32;
+            let theta = 2.0 * PI * u;
+            let normal = vec3(theta.cos() * phi.sin(), phi.cos(), -theta.sin() * phi.sin());
+            let position = normal * radius + vec3(0.0, offset, 0.0);
+            let v = (position.y + total_height / 2.0) / total_height;
+            vertices.push(vertex(position, normal, vec2(u, v)));
+        }
+    }
+
+    let row = segments + 1;
+    let mut indices = Vec::new();
+    let last_band = latitudes.len() as u32 - 1;
+    for r in 0..last_band {
+        for s in 0..segments {
+            let top = r * row + s;
+            let bottom = top + row;
+            // top-left, top-right, bottom-right, bottom-left seen from the outside
+            let (a, b, c, d) = (top, top + 1, bottom + 1, bottom);
+            // the triangles touching the poles would be degenerate
+            if r != last_band - 1 {
+                indices.extend([a, d, c]);
+            }
+            if r != 0 {
+                indices.extend([a, c, b]);
+            }
+        }
+    }
+
+    finish(vertices, indices)
+}
+
+fn vertex(position: TVec3<f32>, normal: TVec3<f32>, tex_coords: TVec2<f32>) -> Vertex {
+    Vertex {
+        position,
+        normal,
+        tex_coords,
+        ..Default::default()
+    }
+}
+
+/// Two triangles for the quad whose bottom-left corner is `(i, j)` in a row-major grid of
+/// vertices `row` wide, u growing with `i` and v with `j`
+fn push_quad(indices: &mut Vec<u32>, row: u32, i: u32, j: u32) {
+    let a = j * row + i;
+    let b = a + 1;
+    let c = a + row + 1;
+    let d = a + row;
+    indices.extend([a, b, c, a, c, d]);
+}
+
+/// Disc of the given radius at height `y`, facing up for a positive `y` and down otherwise
+fn push_cap(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    segments: u32,
+    y: f32,
+    radius: f32,
+) {
+    let normal = vec3(0.0, y.signum(), 0.0);
+    let center = vertices.len() as u32;
+    vertices.push(vertex(vec3(0.0, y, 0.0), normal, vec2(0.5, 0.5)));
+
+    for s in 0..=segments {
+        let theta = 2.0 * PI * s as f32 / segments as f32;
+        let (x, z) = (theta.cos(), -theta.sin());
+        vertices.push(vertex(
+            vec3(x * radius, y, z * radius),
+            normal,
+            vec2(0.5 + x / 2.0, 0.5 - normal.y * z / 2.0),
+        ));
+    }
+
+    for s in 0..segments {
+        let (a, b) = (center + 1 + s, center + 2 + s);
+        if y > 0.0 {
+            indices.extend([center, a, b]);
+        } else {
+            indices.extend([center, b, a]);
+        }
+    }
+}
+
+fn finish(mut vertices: Vec<Vertex>, mut indices: Vec<u32>) -> (Vec<Vertex>, Vec<u32>) {
+    generate_tangents(&mut vertices, &mut indices);
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use glm::{cross, dot};
+
+    use super::*;
+
+    type Shape = (Vec<Vertex>, Vec<u32>);
+
+    fn shapes() -> Vec<(&'static str, Shape)> {
+        vec![
+            ("cube", cube()),
+            ("grid", grid(4, 3)),
+            ("uv_sphere", uv_sphere(16, 8)),
+            ("icosphere", icosphere(2)),
+            ("cylinder", cylinder(16)),
+            ("cone", cone(16)),
+            ("torus", torus(0.75, 0.25, 16, 8)),
+            ("capsule", capsule(0.5, 2.0, 16, 4)),
+        ]
+    }
+
+    /// Cross product of the edges, sum of the vertex normals and center of each triangle,
+    /// skipping the degenerate ones
+    fn faces((vertices, indices): &Shape) -> Vec<[TVec3<f32>; 3]> {
+        indices
+            .chunks_exact(3)
+            .map(|t| [0, 1, 2].map(|i| vertices[t[i] as usize]))
+            .map(|[a, b, c]| {
+                [
+                    cross(&(b.position - a.position), &(c.position - a.position)),
+                    a.normal + b.normal + c.normal,
+                    (a.position + b.position + c.position) / 3.0,
+                ]
+            })
+            .filter(|[face, ..]| face.norm() > 1e-6)
+            .collect()
+    }
+
+    #[test]
+    fn vertex_and_index_counts() {
+        let counts = |(vertices, indices): Shape| (vertices.len(), indices.len());
+
+        assert_eq!(counts(cube()), (24, 36));
+        assert_eq!(counts(grid(4, 3)), (5 * 4, 6 * 4 * 3));
+        // the triangles touching the poles are left out
+        assert_eq!(counts(uv_sphere(16, 8)), (17 * 9, 3 * 16 * (2 * 8 - 2)));
+        assert_eq!(counts(cylinder(16)), (2 * 17 + 2 * 18, 12 * 16));
+        assert_eq!(counts(cone(16)), (3 * 16 + 18, 6 * 16));
+        assert_eq!(counts(torus(0.75, 0.25, 16, 8)), (17 * 9, 6 * 16 * 8));
+        assert_eq!(counts(capsule(0.5, 2.0, 16, 4)), (2 * 5 * 17, 12 * 16 * 4));
+
+        // the vertices along the UV seam and at the poles are duplicated
+        let (vertices, indices) = icosphere(2);
+        assert_eq!(indices.len(), 3 * 20 * 4 * 4);
+        assert!(vertices.len() > 10 * 4 * 4 + 2);
+    }
+
+    #[test]
+    fn indices_are_in_range() {
+        for (name, (vertices, indices)) in shapes() {
+            assert_eq!(indices.len() % 3, 0, "{}", name);
+            assert!(
+                indices.iter().all(|&i| (i as usize) < vertices.len()),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn normals_are_unit_length() {
+        for (name, (vertices, _)) in shapes() {
+            for vertex in &vertices {
+                assert!(
+                    (vertex.normal.norm() - 1.0).abs() < 1e-4,
+                    "{}: normal {:?} at {:?}",
+                    name,
+                    vertex.normal,
+                    vertex.position
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn faces_wind_counter_clockwise_from_outside() {
+        for (name, shape) in shapes() {
+            for [face, normal, center] in faces(&shape) {
+                assert!(
+                    dot(&face, &normal) > 0.0,
+                    "{}: face {:?} against normal {:?}",
+                    name,
+                    face,
+                    normal
+                );
+                // the convex shapes also face away from the origin
+                if !matches!(name, "grid" | "torus") {
+                    assert!(
+                        dot(&face, &center) > 0.0,
+                        "{}: face {:?} at {:?}",
+                        name,
+                        face,
+                        center
+                    );
+                }
+            }
+        }
+    }
+}
@@ -97,7 +97,7 @@ impl Quad {
             let stride: i32 = size_of::<Vertex2D>().try_into().unwrap();
 
             glEnableVertexAttribArray(0);
-            glVertexAttribPointer(0, 2, GL_FLOAT, 0, stride, 0 as *const _);
+            glVertexAttribPointer(0, 2, GL_FLOAT, 0, stride, std::ptr::null());
         }
     }
 }
@@ -109,7 +109,7 @@ impl Draw for Quad {
         shader.set_vec4("color", self.color);
 
         unsafe {
-            glDrawElements(GL_TRIANGLES, 6, GL_UNSIGNED_INT, 0 as *const _);
+            glDrawElements(GL_TRIANGLES, 6, GL_UNSIGNED_INT, std::ptr::null());
         }
 
         VAO::clear_binding()
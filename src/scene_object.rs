@@ -1,72 +1,205 @@
-use crate::{
-    draw::Draw, model::Model, plane::Plane, shader_program::ShaderProgram, texture::Texture2D,
-    transform::Transform,
-};
-
-use glm::{Mat4, TVec3};
-
-pub struct SceneObject {
-    object: Box<dyn Draw>,
-    pub transform: Transform,
-}
-
-impl SceneObject {
-    pub fn model(path: &str) -> Self {
-        Self {
-            object: Box::new(Model::new(path)),
-            transform: Transform::new(),
-        }
-    }
-
-    pub fn plane(texture: Option<Texture2D>) -> Self {
-        Self {
-            object: Box::new(Plane::new(texture)),
-            transform: Transform::new(),
-        }
-    }
-
-    pub fn position(&self) -> TVec3<f32> {
-        self.transform.position
-    }
-
-    pub fn set_position(&mut self, pos: TVec3<f32>) {
-        self.transform.position = pos
-    }
-
-    pub fn roll(&self) -> f32 {
-        self.transform.angles.x
-    }
-
-    pub fn set_roll(&mut self, value: f32) {
-        self.transform.angles.x = value
-    }
-
-    pub fn pitch(&self) -> f32 {
-        self.transform.angles.y
-    }
-
-    pub fn set_pitch(&mut self, value: f32) {
-        self.transform.angles.y = value
-    }
-
-    pub fn yaw(&self) -> f32 {
-        self.transform.angles.z
-    }
-
-    pub fn set_yaw(&mut self, value: f32) {
-        self.transform.angles.z = value
-    }
-
-    pub fn set_scale(&mut self, scale: TVec3<f32>) {
-        self.transform.scale = scale
-    }
-
-    pub fn model_matrix(&self) -> Mat4 {
-        self.transform.matrix()
-    }
-
-    pub fn draw(&self, shader: &ShaderProgram) {
-        shader.set_mat4("model", &self.model_matrix());
-        self.object.draw(shader)
-    }
-}
+use crate::{
+    animated_model::AnimatedModel,
+    bounds::{Aabb, BoundingSphere},
+    draw::{Draw, DrawOptions},
+    frustum::Frustum,
+    import_options::ImportOptions,
+    material::Material,
+    mesh::{Mesh, Vertex},
+    model::Model,
+    plane::Plane,
+    primitives,
+    shader_program::ShaderProgram,
+    texture::Texture2D,
+    transform::Transform,
+};
+
+use glm::{Mat4, TVec3};
+
+/// Animated models are kept apart so their playback can still be controlled
+enum Object {
+    Static(Box<dyn Draw>),
+    Animated(Box<AnimatedModel>),
+}
+
+impl Object {
+    fn draw_with(&self, shader: &ShaderProgram, options: &DrawOptions) {
+        match self {
+            Object::Static(object) => object.draw_with(shader, options),
+            Object::Animated(model) => model.draw_with(shader, options),
+        }
+    }
+}
+
+pub struct SceneObject {
+    object: Object,
+    pub transform: Transform,
+    /// Drawn instead of the object's own materials when set
+    pub material: Option<Material>,
+    /// Bounds of the object before its transform is applied
+    aabb: Aabb,
+    bounding_sphere: BoundingSphere,
+}
+
+impl SceneObject {
+    pub fn model_with_options(path: &str, options: &ImportOptions) -> Self {
+        let model = Model::with_options(path, options);
+
+        Self {
+            aabb: model.aabb(),
+            bounding_sphere: model.bounding_sphere(),
+            object: Object::Static(Box::new(model)),
+            transform: Transform::new(),
+            material: None,
+        }
+    }
+
+    /// The model's bounds already cover its animations, see `Model::import`
+    pub fn animated(model: AnimatedModel) -> Self {
+        Self {
+            aabb: model.model().aabb(),
+            bounding_sphere: model.model().bounding_sphere(),
+            object: Object::Animated(Box::new(model)),
+            transform: Transform::new(),
+            material: None,
+        }
+    }
+
+    pub fn plane(texture: Option<Texture2D>) -> Self {
+        let plane = Plane::new(texture);
+        let aabb = plane.aabb();
+
+        Self {
+            aabb,
+            bounding_sphere: BoundingSphere::from_points(&aabb.corners()),
+            object: Object::Static(Box::new(plane)),
+            transform: Transform::new(),
+            material: None,
+        }
+    }
+
+    /// Builds an object out of generated geometry, see `primitives`
+    pub fn mesh((vertices, indices): (Vec<Vertex>, Vec<u32>), texture: Option<Texture2D>) -> Self {
+        let material = Material::with_textures(texture.into_iter().collect());
+        let mesh = Mesh::new(vertices, indices, material);
+
+        Self {
+            aabb: mesh.aabb,
+            bounding_sphere: mesh.bounding_sphere,
+            object: Object::Static(Box::new(mesh)),
+            transform: Transform::new(),
+            material: None,
+        }
+    }
+
+    pub fn cube(texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::cube(), texture)
+    }
+
+    pub fn grid(x_subdivisions: u32, z_subdivisions: u32, texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::grid(x_subdivisions, z_subdivisions), texture)
+    }
+
+    pub fn uv_sphere(texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::uv_sphere(32, 16), texture)
+    }
+
+    pub fn icosphere(texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::icosphere(3), texture)
+    }
+
+    pub fn cylinder(texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::cylinder(32), texture)
+    }
+
+    pub fn cone(texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::cone(32), texture)
+    }
+
+    pub fn torus(texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::torus(0.75, 0.25, 32, 16), texture)
+    }
+
+    pub fn capsule(texture: Option<Texture2D>) -> Self {
+        SceneObject::mesh(primitives::capsule(0.5, 2.0, 32, 8), texture)
+    }
+
+    pub fn animation_mut(&mut self) -> Option<&mut AnimatedModel> {
+        match &mut self.object {
+            Object::Animated(model) => Some(model),
+            Object::Static(_) => None,
+        }
+    }
+
+    /// Plays the object's own animations, if it has any
+    pub fn update(&mut self, delta: f32) {
+        if let Some(model) = self.animation_mut() {
+            model.update(delta)
+        }
+    }
+
+    pub fn position(&self) -> TVec3<f32> {
+        self.transform.position
+    }
+
+    pub fn set_position(&mut self, pos: TVec3<f32>) {
+        self.transform.position = pos
+    }
+
+    pub fn set_roll(&mut self, value: f32) {
+        self.transform.angles.x = value
+    }
+
+    pub fn set_pitch(&mut self, value: f32) {
+        self.transform.angles.y = value
+    }
+
+    pub fn set_scale(&mut self, scale: TVec3<f32>) {
+        self.transform.scale = scale
+    }
+
+    pub fn set_material(&mut self, material: Option<Material>) {
+        self.material = material
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        self.transform.matrix()
+    }
+
+    pub fn world_aabb(&self) -> Aabb {
+        self.aabb.transformed(&self.model_matrix())
+    }
+
+    pub fn world_bounding_sphere(&self) -> BoundingSphere {
+        self.bounding_sphere.transformed(&self.model_matrix())
+    }
+
+    /// Cheap sphere test first, then the tighter box
+    pub fn is_visible(&self, frustum: &Frustum) -> bool {
+        frustum.intersects_sphere(&self.world_bounding_sphere())
+            && frustum.intersects_aabb(&self.world_aabb())
+    }
+
+    pub fn draw(&self, shader: &ShaderProgram) {
+        shader.set_mat4("model", &self.model_matrix());
+        self.object.draw_with(
+            shader,
+            &DrawOptions {
+                material: self.material.as_ref(),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Draws the level of detail matching the object's size on screen
+    pub fn draw_with_lod(&self, shader: &ShaderProgram, frustum: &Frustum) {
+        shader.set_mat4("model", &self.model_matrix());
+        self.object.draw_with(
+            shader,
+            &DrawOptions {
+                material: self.material.as_ref(),
+                screen_size: Some(frustum.projected_size(&self.world_bounding_sphere())),
+            },
+        )
+    }
+}
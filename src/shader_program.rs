@@ -1,182 +1,267 @@
-use gl33::global_loader::*;
-use gl33::*;
-
-use glm::{Mat4, Vec3, Vec4};
-
-use std::ffi::CString;
-use std::fs;
-use std::path::Path;
-
-enum ShaderType {
-    VertexShader,
-    FragmentShader,
-}
-
-impl From<&ShaderType> for gl33::ShaderType {
-    fn from(t: &ShaderType) -> Self {
-        match t {
-            ShaderType::VertexShader => GL_VERTEX_SHADER,
-            ShaderType::FragmentShader => GL_FRAGMENT_SHADER,
-        }
-    }
-}
-
-struct Shader {
-    id: u32,
-}
-
-impl Shader {
-    fn from_source(source: &str, shader_type: ShaderType) -> Self {
-        let id = glCreateShader((&shader_type).into());
-        if id == 0 {
-            panic!("Couldn't create shader");
-        } else {
-            unsafe {
-                glShaderSource(
-                    id,
-                    1,
-                    &(source.as_bytes().as_ptr().cast()),
-                    &(source.len().try_into().unwrap()),
-                );
-            }
-            Self { id }
-        }
-    }
-
-    fn from_file(filename: &str, shader_type: ShaderType) -> Self {
-        let path = Path::new("ressources").join("shaders").join(filename);
-
-        let file_content =
-            fs::read_to_string(path).expect(format!("filename: \"{}\"\n", filename).as_str());
-        Shader::from_source(file_content.as_str(), shader_type)
-    }
-
-    fn compile(&self) {
-        glCompileShader(self.id);
-
-        if self.check_compilation_status().is_err() {
-            panic!("Shader Compilation Error: {}", self.error_log());
-        }
-    }
-
-    fn delete(&self) {
-        glDeleteShader(self.id);
-    }
-
-    fn check_compilation_status(&self) -> Result<(), ()> {
-        unsafe {
-            let mut success = 0;
-            glGetShaderiv(self.id, GL_COMPILE_STATUS, &mut success);
-            if success == 0 {
-                Err(())
-            } else {
-                Ok(())
-            }
-        }
-    }
-
-    fn error_log(&self) -> String {
-        unsafe {
-            let mut needed_len = 0;
-            glGetShaderiv(self.id, GL_INFO_LOG_LENGTH, &mut needed_len);
-            let mut v: Vec<u8> = Vec::with_capacity(needed_len.try_into().unwrap());
-            let mut written_len = 0_i32;
-            glGetShaderInfoLog(self.id, needed_len, &mut written_len, v.as_mut_ptr().cast());
-            v.set_len(written_len.try_into().unwrap());
-            String::from_utf8_lossy(&v).to_string()
-        }
-    }
-}
-
-pub struct ShaderProgram {
-    id: u32,
-}
-
-impl ShaderProgram {
-    pub fn from_files(vertex_filename: &str, fragment_filename: &str) -> Self {
-        let id = glCreateProgram();
-        let vertex_shader = Shader::from_file(vertex_filename, ShaderType::VertexShader);
-        glAttachShader(id, vertex_shader.id);
-        let fragment_shader = Shader::from_file(fragment_filename, ShaderType::FragmentShader);
-        glAttachShader(id, fragment_shader.id);
-
-        let shader = Self { id };
-
-        shader.link(vertex_shader, fragment_shader);
-        shader
-    }
-
-    fn link(&self, vertex_shader: Shader, fragment_shader: Shader) {
-        vertex_shader.compile();
-        fragment_shader.compile();
-        glLinkProgram(self.id);
-
-        if self.check_linking_status().is_err() {
-            panic!("Shader Program Linking Error: {}", self.error_log());
-        }
-
-        vertex_shader.delete();
-        fragment_shader.delete();
-    }
-
-    pub fn use_program(&self) {
-        glUseProgram(self.id);
-    }
-
-    pub fn get_uniform_location(&self, name: &str) -> i32 {
-        let c_name = CString::new(name).unwrap();
-        unsafe { glGetUniformLocation(self.id, c_name.as_ptr().cast()) }
-    }
-
-    pub fn set_int(&self, name: &str, v0: i32) {
-        unsafe {
-            glUniform1i(self.get_uniform_location(name), v0);
-        }
-    }
-
-    pub fn set_float(&self, name: &str, v0: f32) {
-        unsafe {
-            glUniform1f(self.get_uniform_location(name), v0);
-        }
-    }
-
-    pub fn set_vec3(&self, name: &str, v0: Vec3) {
-        unsafe {
-            glUniform3fv(self.get_uniform_location(name), 1, v0.as_ptr().cast());
-        }
-    }
-
-    pub fn set_vec4(&self, name: &str, v0: Vec4) {
-        unsafe {
-            glUniform4fv(self.get_uniform_location(name), 1, v0.as_ptr().cast());
-        }
-    }
-
-    pub fn set_mat4(&self, name: &str, v0: &Mat4) {
-        unsafe {
-            glUniformMatrix4fv(self.get_uniform_location(name), 1, 0, v0.as_ptr().cast());
-        }
-    }
-
-    fn check_linking_status(&self) -> Result<(), ()> {
-        unsafe {
-            let mut success = 0;
-            glGetProgramiv(self.id, GL_LINK_STATUS, &mut success);
-            if success == 0 {
-                Err(())
-            } else {
-                Ok(())
-            }
-        }
-    }
-
-    fn error_log(&self) -> String {
-        unsafe {
-            let mut v: Vec<u8> = Vec::with_capacity(1024);
-            let mut log_len = 0_i32;
-            glGetProgramInfoLog(self.id, 1024, &mut log_len, v.as_mut_ptr().cast());
-            v.set_len(log_len.try_into().unwrap());
-            String::from_utf8_lossy(&v).to_string()
-        }
-    }
-}
+use gl33::global_loader::*;
+use gl33::*;
+
+use glm::{Mat4, Vec2, Vec3, Vec4};
+
+use std::ffi::CString;
+use std::fs;
+use std::path::Path;
+
+enum ShaderType {
+    Vertex,
+    Geometry,
+    Fragment,
+}
+
+impl From<&ShaderType> for gl33::ShaderType {
+    fn from(t: &ShaderType) -> Self {
+        match t {
+            ShaderType::Vertex => GL_VERTEX_SHADER,
+            ShaderType::Geometry => GL_GEOMETRY_SHADER,
+            ShaderType::Fragment => GL_FRAGMENT_SHADER,
+        }
+    }
+}
+
+struct Shader {
+    id: u32,
+}
+
+impl Shader {
+    fn from_source(source: &str, shader_type: ShaderType) -> Self {
+        let id = glCreateShader((&shader_type).into());
+        if id == 0 {
+            panic!("Couldn't create shader");
+        } else {
+            unsafe {
+                glShaderSource(
+                    id,
+                    1,
+                    &(source.as_bytes().as_ptr().cast()),
+                    &(source.len().try_into().unwrap()),
+                );
+            }
+            Self { id }
+        }
+    }
+
+    fn from_file(filename: &str, shader_type: ShaderType) -> Self {
+        Shader::from_source(read_source(filename).as_str(), shader_type)
+    }
+
+    fn compile(&self) {
+        glCompileShader(self.id);
+
+        if self.check_compilation_status().is_err() {
+            panic!("Shader Compilation Error: {}", self.error_log());
+        }
+    }
+
+    fn delete(&self) {
+        glDeleteShader(self.id);
+    }
+
+    fn check_compilation_status(&self) -> Result<(), ()> {
+        unsafe {
+            let mut success = 0;
+            glGetShaderiv(self.id, GL_COMPILE_STATUS, &mut success);
+            if success == 0 {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn error_log(&self) -> String {
+        unsafe {
+            let mut needed_len = 0;
+            glGetShaderiv(self.id, GL_INFO_LOG_LENGTH, &mut needed_len);
+            let mut v: Vec<u8> = Vec::with_capacity(needed_len.try_into().unwrap());
+            let mut written_len = 0_i32;
+            glGetShaderInfoLog(self.id, needed_len, &mut written_len, v.as_mut_ptr().cast());
+            v.set_len(written_len.try_into().unwrap());
+            String::from_utf8_lossy(&v).to_string()
+        }
+    }
+}
+
+/// Reads a shader from the shaders directory. GLSL has no includes, so lines of the form
+/// `#include "file"` are replaced with the content of that file, read the same way.
+fn read_source(filename: &str) -> String {
+    let path = Path::new("ressources").join("shaders").join(filename);
+
+    let file_content =
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("{}, filename: \"{}\"", e, filename));
+    file_content
+        .lines()
+        .map(|line| {
+            match line
+                .trim()
+                .strip_prefix("#include")
+                .map(|included| included.trim().trim_matches('"'))
+            {
+                Some(included) => read_source(included),
+                None => line.to_owned(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub struct ShaderProgram {
+    id: u32,
+}
+
+impl ShaderProgram {
+    pub fn from_files(vertex_filename: &str, fragment_filename: &str) -> Self {
+        let id = glCreateProgram();
+        let vertex_shader = Shader::from_file(vertex_filename, ShaderType::Vertex);
+        glAttachShader(id, vertex_shader.id);
+        let fragment_shader = Shader::from_file(fragment_filename, ShaderType::Fragment);
+        glAttachShader(id, fragment_shader.id);
+
+        let shader = Self { id };
+
+        shader.link(&[vertex_shader, fragment_shader]);
+        shader
+    }
+
+    /// Program with a geometry shader between the vertex and fragment stages
+    pub fn with_geometry(
+        vertex_filename: &str,
+        geometry_filename: &str,
+        fragment_filename: &str,
+    ) -> Self {
+        let id = glCreateProgram();
+        let vertex_shader = Shader::from_file(vertex_filename, ShaderType::Vertex);
+        glAttachShader(id, vertex_shader.id);
+        let geometry_shader = Shader::from_file(geometry_filename, ShaderType::Geometry);
+        glAttachShader(id, geometry_shader.id);
+        let fragment_shader = Shader::from_file(fragment_filename, ShaderType::Fragment);
+        glAttachShader(id, fragment_shader.id);
+
+        let shader = Self { id };
+
+        shader.link(&[vertex_shader, geometry_shader, fragment_shader]);
+        shader
+    }
+
+    fn link(&self, shaders: &[Shader]) {
+        for shader in shaders {
+            shader.compile();
+        }
+        glLinkProgram(self.id);
+
+        if self.check_linking_status().is_err() {
+            panic!("Shader Program Linking Error: {}", self.error_log());
+        }
+
+        for shader in shaders {
+            shader.delete();
+        }
+    }
+
+    pub fn use_program(&self) {
+        glUseProgram(self.id);
+    }
+
+    pub fn get_uniform_location(&self, name: &str) -> i32 {
+        let c_name = CString::new(name).unwrap();
+        unsafe { glGetUniformLocation(self.id, c_name.as_ptr().cast()) }
+    }
+
+    pub fn set_int(&self, name: &str, v0: i32) {
+        unsafe {
+            glUniform1i(self.get_uniform_location(name), v0);
+        }
+    }
+
+    pub fn set_float(&self, name: &str, v0: f32) {
+        unsafe {
+            glUniform1f(self.get_uniform_location(name), v0);
+        }
+    }
+
+    pub fn set_vec2(&self, name: &str, v0: Vec2) {
+        unsafe {
+            glUniform2fv(self.get_uniform_location(name), 1, v0.as_ptr().cast());
+        }
+    }
+
+    pub fn set_vec3(&self, name: &str, v0: Vec3) {
+        unsafe {
+            glUniform3fv(self.get_uniform_location(name), 1, v0.as_ptr().cast());
+        }
+    }
+
+    pub fn set_vec4(&self, name: &str, v0: Vec4) {
+        unsafe {
+            glUniform4fv(self.get_uniform_location(name), 1, v0.as_ptr().cast());
+        }
+    }
+
+    pub fn set_mat4(&self, name: &str, v0: &Mat4) {
+        unsafe {
+            glUniformMatrix4fv(self.get_uniform_location(name), 1, 0, v0.as_ptr().cast());
+        }
+    }
+
+    /// Sets the `name` array from its first element
+    pub fn set_mat4_array(&self, name: &str, v0: &[Mat4]) {
+        unsafe {
+            glUniformMatrix4fv(
+                self.get_uniform_location(name),
+                v0.len().try_into().unwrap(),
+                0,
+                v0.as_ptr().cast(),
+            );
+        }
+    }
+
+    fn check_linking_status(&self) -> Result<(), ()> {
+        unsafe {
+            let mut success = 0;
+            glGetProgramiv(self.id, GL_LINK_STATUS, &mut success);
+            if success == 0 {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn error_log(&self) -> String {
+        unsafe {
+            let mut v: Vec<u8> = Vec::with_capacity(1024);
+            let mut log_len = 0_i32;
+            glGetProgramInfoLog(self.id, 1024, &mut log_len, v.as_mut_ptr().cast());
+            v.set_len(log_len.try_into().unwrap());
+            String::from_utf8_lossy(&v).to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes_are_expanded() {
+        for filename in ["model_loading.vs", "shadow_depth.vs", "shadow_cube.vs"] {
+            let source = read_source(filename);
+            assert!(source.starts_with("#version 330 core"), "{}", filename);
+            assert!(!source.contains("#include"), "{}", filename);
+            assert!(
+                source.contains("mat4 skin(mat4 modelMatrix)"),
+                "{}",
+                filename
+            );
+        }
+        for filename in ["model_loading.fs", "pbr.fs"] {
+            let source = read_source(filename);
+            assert!(!source.contains("#include"), "{}", filename);
+            assert!(source.contains("float pointShadow("), "{}", filename);
+        }
+    }
+}
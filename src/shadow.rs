@@ -0,0 +1,394 @@
+use gl33::{global_loader::*, *};
+use glm::{vec3, vec4, Mat4, TVec3};
+
+use crate::{
+    bounds::Aabb,
+    framebuffer::Framebuffer,
+    lights::{DirectionalLight, Lights, MAX_POINT_SHADOWS},
+    shader_program::ShaderProgram,
+    utils::{to_radians, usize_to_glenum, IDENTITY_MAT4},
+};
+
+/// Texture unit the shadow map is bound to, the last of the 16 units OpenGL 3.3 guarantees.
+/// The other shadow textures are packed below it and the materials' textures start from unit 0.
+pub const SHADOW_MAP_UNIT: usize = 15;
+/// The point light cubemaps take the units right below the shadow map's
+pub const POINT_SHADOW_FIRST_UNIT: usize = SHADOW_MAP_UNIT - MAX_POINT_SHADOWS;
+/// Then comes the texture array of the cascades, the lowest reserved unit. Materials can
+/// use every unit below it.
+pub const CASCADE_UNIT: usize = POINT_SHADOW_FIRST_UNIT - 1;
+/// Size of the cascade arrays of the lit shaders
+pub const MAX_CASCADES: usize = 4;
+
+/// Depth of the scene as seen from a directional light. The lit shaders compare fragments
+/// against it to darken the first directional light where something stands in its way.
+pub struct ShadowMap {
+    framebuffer: Framebuffer,
+    resolution: i32,
+    /// Projection and view of the light, fitted to the scene by `fit`
+    light_space: Mat4,
+    /// Depth offset applied to every fragment
+    pub bias: f32,
+    /// Extra offset growing with the slope of the surface relative to the light
+    pub slope_bias: f32,
+    /// Texels sampled on each side of the fragment, 1 averages a 3x3 area
+    pub pcf_radius: i32,
+    /// Lets the lit shaders know whether to sample the map
+    pub enabled: bool,
+}
+
+impl ShadowMap {
+    pub fn new(resolution: i32) -> Self {
+        Self {
+            framebuffer: Framebuffer::depth_only(resolution, resolution),
+            resolution,
+            light_space: *IDENTITY_MAT4,
+            bias: 0.0005,
+            slope_bias: 0.002,
+            pcf_radius: 1,
+            enabled: true,
+        }
+    }
+
+    /// Points an orthographic view along the light's direction, just large enough for
+    /// `bounds` to be inside of it
+    pub fn fit(&mut self, light: &DirectionalLight, bounds: &Aabb) {
+        if bounds.is_empty() {
+            return;
+        }
+
+        let direction = light.direction.normalize();
+        let center = bounds.center();
+        let radius = bounds.half_extents().norm().max(0.001);
+        // look_at can't use an up vector parallel to the direction
+        let up = if direction.y.abs() > 0.99 {
+            vec3(0.0, 0.0, 1.0)
+        } else {
+            vec3(0.0, 1.0, 0.0)
+        };
+        let view = glm::look_at(&(center - direction * radius), &center, &up);
+
+        let corners = bounds.corners().map(|corner| {
+            let p = view * vec4(corner.x, corner.y, corner.z, 1.0);
+            vec3(p.x, p.y, p.z)
+        });
+        let Aabb { min, max } = Aabb::from_points(&corners);
+
+        // the view looks down -z, the near and far planes are distances along it
+        let projection = glm::ortho(min.x, max.x, min.y, max.y, -max.z, -min.z);
+        self.light_space = projection * view;
+    }
+
+    /// Binds the shadow map as the render target, objects drawn with `shader` until `end`
+    /// only write their depth
+    pub fn begin(&self, shader: &ShaderProgram) {
+        self.framebuffer.bind();
+        unsafe {
+            glViewport(0, 0, self.resolution, self.resolution);
+            glClear(GL_DEPTH_BUFFER_BIT);
+        }
+        shader.use_program();
+        shader.set_mat4("lightSpaceMatrix", &self.light_space);
+    }
+
+    /// Goes back to the default framebuffer, `viewport` being the window's size
+    pub fn end(&self, viewport: (i32, i32)) {
+        Framebuffer::clear_binding();
+        unsafe {
+            glViewport(0, 0, viewport.0, viewport.1);
+        }
+    }
+
+    /// Binds the depth texture to `SHADOW_MAP_UNIT` and uploads the light's matrix and the
+    /// filtering parameters
+    pub fn set_into_shader(&self, shader: &ShaderProgram) {
+        unsafe {
+            glActiveTexture(usize_to_glenum(0x84c0 + SHADOW_MAP_UNIT));
+        }
+        self.framebuffer.bind_texture();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+        }
+
+        shader.set_int("shadowMap", SHADOW_MAP_UNIT as i32);
+        shader.set_int("shadowsEnabled", self.enabled.into());
+        shader.set_mat4("lightSpaceMatrix", &self.light_space);
+        shader.set_float("shadowBias", self.bias);
+        shader.set_float("shadowSlopeBias", self.slope_bias);
+        shader.set_int("shadowPcfRadius", self.pcf_radius);
+    }
+}
+
+/// Directional light shadows split along the camera's view, each cascade covering a further
+/// and larger slice of the frustum with the same resolution
+pub struct CascadedShadowMap {
+    /// One layer per cascade
+    framebuffer: Framebuffer,
+    resolution: i32,
+    cascade_count: usize,
+    /// Blend between uniform (0) and logarithmic (1) splits, the practical split scheme
+    pub split_lambda: f32,
+    /// How far behind each cascade, towards the light, objects still cast shadows
+    pub caster_distance: f32,
+    /// View space distance where each cascade ends
+    splits: Vec<f32>,
+    light_spaces: Vec<Mat4>,
+    pub bias: f32,
+    pub slope_bias: f32,
+    pub pcf_radius: i32,
+    /// Lets the lit shaders know whether to sample the cascades
+    pub enabled: bool,
+    /// Tints the scene with the color of the cascade each fragment falls in
+    pub debug: bool,
+}
+
+impl CascadedShadowMap {
+    pub fn new(resolution: i32, cascade_count: usize) -> Self {
+        assert!(
+            (1..=MAX_CASCADES).contains(&cascade_count),
+            "{} cascades requested but the shaders support from 1 to {}",
+            cascade_count,
+            MAX_CASCADES
+        );
+
+        Self {
+            framebuffer: Framebuffer::depth_array(resolution, resolution, cascade_count as i32),
+            resolution,
+            cascade_count,
+            split_lambda: 0.75,
+            caster_distance: 50.0,
+            splits: Vec::new(),
+            light_spaces: Vec::new(),
+            bias: 0.0005,
+            slope_bias: 0.002,
+            pcf_radius: 1,
+            enabled: true,
+            debug: false,
+        }
+    }
+
+    /// Splits the camera frustum described by `view` and its perspective parameters and fits
+    /// a light view around each slice
+    pub fn fit(
+        &mut self,
+        light: &DirectionalLight,
+        view: &Mat4,
+        fov_y: f32,
+        aspect: f32,
+        near: f32,
+        far: f32,
+    ) {
+        let count = self.cascade_count as f32;
+        self.splits = (1..=self.cascade_count)
+            .map(|i| {
+                let ratio = i as f32 / count;
+                let logarithmic = near * (far / near).powf(ratio);
+                let uniform = near + (far - near) * ratio;
+                self.split_lambda * logarithmic + (1.0 - self.split_lambda) * uniform
+            })
+            .collect();
+
+        let direction = light.direction.normalize();
+        let up = if direction.y.abs() > 0.99 {
+            vec3(0.0, 0.0, 1.0)
+        } else {
+            vec3(0.0, 1.0, 0.0)
+        };
+
+        let mut slice_near = near;
+        self.light_spaces = Vec::with_capacity(self.cascade_count);
+        for &slice_far in &self.splits {
+            let projection = glm::perspective(aspect, fov_y, slice_near, slice_far);
+            let inverse = glm::inverse(&(projection * view));
+            let corners = Aabb {
+                min: vec3(-1.0, -1.0, -1.0),
+                max: vec3(1.0, 1.0, 1.0),
+            }
+            .corners()
+            .map(|corner| {
+                let p = inverse * vec4(corner.x, corner.y, corner.z, 1.0);
+                p.xyz() / p.w
+            });
+
+            // A sphere keeps the same size however the camera turns, rounding its radius
+            // keeps it from changing with float errors
+            let center = corners.iter().sum::<TVec3<f32>>() / 8.0;
+            let radius = corners
+                .iter()
+                .map(|corner| (corner - center).norm())
+                .fold(0.0, f32::max);
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let eye = center - direction * (radius + self.caster_distance);
+            let light_view = glm::look_at(&eye, &center, &up);
+            let mut light_projection = glm::ortho(
+                -radius,
+                radius,
+                -radius,
+                radius,
+                0.0,
+                2.0 * radius + self.caster_distance,
+            );
+
+            // Moves the projection by less than a texel so that the world origin lands on a
+            // texel corner, the shadows' edges then don't shimmer as the camera moves
+            let origin = light_projection * light_view * vec4(0.0, 0.0, 0.0, 1.0);
+            let texel_origin = origin.xy() * self.resolution as f32 / 2.0;
+            let offset =
+                (texel_origin.map(f32::round) - texel_origin) * 2.0 / self.resolution as f32;
+            light_projection[(0, 3)] += offset.x;
+            light_projection[(1, 3)] += offset.y;
+
+            self.light_spaces.push(light_projection * light_view);
+            slice_near = slice_far;
+        }
+    }
+
+    /// Renders every cascade with `shader`, `draw` having to draw the objects casting shadows
+    pub fn render<F>(&self, shader: &ShaderProgram, viewport: (i32, i32), draw: F)
+    where
+        F: Fn(&ShaderProgram),
+    {
+        shader.use_program();
+        for (layer, light_space) in self.light_spaces.iter().enumerate() {
+            self.framebuffer.bind_layer(layer as i32);
+            unsafe {
+                glViewport(0, 0, self.resolution, self.resolution);
+                glClear(GL_DEPTH_BUFFER_BIT);
+            }
+            shader.set_mat4("lightSpaceMatrix", light_space);
+            draw(shader);
+        }
+
+        Framebuffer::clear_binding();
+        unsafe {
+            glViewport(0, 0, viewport.0, viewport.1);
+        }
+    }
+
+    /// Binds the texture array to `CASCADE_UNIT` and uploads the splits, the matrices and the
+    /// filtering parameters
+    pub fn set_into_shader(&self, shader: &ShaderProgram) {
+        unsafe {
+            glActiveTexture(usize_to_glenum(0x84c0 + CASCADE_UNIT));
+        }
+        self.framebuffer.bind_texture();
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+        }
+
+        shader.set_int("cascadeShadowMap", CASCADE_UNIT as i32);
+        shader.set_int("cascadesEnabled", self.enabled.into());
+        shader.set_int("cascadeDebug", self.debug.into());
+        shader.set_int("cascadeCount", self.light_spaces.len() as i32);
+        for (i, (split, light_space)) in self.splits.iter().zip(&self.light_spaces).enumerate() {
+            shader.set_float(format!("cascadeSplits[{}]", i).as_str(), *split);
+            shader.set_mat4(format!("cascadeLightSpaces[{}]", i).as_str(), light_space);
+        }
+        shader.set_float("cascadeBias", self.bias);
+        shader.set_float("cascadeSlopeBias", self.slope_bias);
+        shader.set_int("cascadePcfRadius", self.pcf_radius);
+    }
+}
+
+/// Depth cubemaps of the point lights casting shadows, they store the distance to the light
+/// divided by `far_plane`
+pub struct PointShadowMaps {
+    /// Resolution and cubemap of each shadow slot
+    maps: Vec<(i32, Framebuffer)>,
+    /// Objects further than this from a light don't cast shadows
+    pub far_plane: f32,
+    /// Distance the fragments are moved towards the light before the comparison
+    pub bias: f32,
+}
+
+impl PointShadowMaps {
+    pub fn new() -> Self {
+        Self {
+            maps: Vec::new(),
+            far_plane: 25.0,
+            bias: 0.05,
+        }
+    }
+
+    /// Projection and view of each face of a cubemap centered on `position`, in the order of
+    /// the `GL_TEXTURE_CUBE_MAP_*` faces
+    fn face_matrices(&self, position: TVec3<f32>) -> [Mat4; 6] {
+        let projection = glm::perspective(1.0, to_radians(90.0), 0.1, self.far_plane);
+        let faces = [
+            (vec3(1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+            (vec3(-1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+            (vec3(0.0, 1.0, 0.0), vec3(0.0, 0.0, 1.0)),
+            (vec3(0.0, -1.0, 0.0), vec3(0.0, 0.0, -1.0)),
+            (vec3(0.0, 0.0, 1.0), vec3(0.0, -1.0, 0.0)),
+            (vec3(0.0, 0.0, -1.0), vec3(0.0, -1.0, 0.0)),
+        ];
+        faces.map(|(direction, up)| {
+            projection * glm::look_at(&position, &(position + direction), &up)
+        })
+    }
+
+    /// Renders the cubemap of every shadow casting light with `shader`, `draw` having to draw
+    /// the objects casting shadows. Cubemaps are recreated when a light's resolution changes.
+    pub fn render<F>(
+        &mut self,
+        lights: &Lights,
+        shader: &ShaderProgram,
+        viewport: (i32, i32),
+        draw: F,
+    ) where
+        F: Fn(&ShaderProgram),
+    {
+        let mut count = 0;
+        for (slot, (_, light)) in lights.shadow_casting_points().enumerate() {
+            let resolution = light.shadow_resolution;
+            if slot == self.maps.len() {
+                self.maps
+                    .push((resolution, Framebuffer::depth_cubemap(resolution)));
+            } else if self.maps[slot].0 != resolution {
+                self.maps[slot] = (resolution, Framebuffer::depth_cubemap(resolution));
+            }
+
+            self.maps[slot].1.bind();
+            unsafe {
+                glViewport(0, 0, resolution, resolution);
+                glClear(GL_DEPTH_BUFFER_BIT);
+            }
+            shader.use_program();
+            shader.set_mat4_array("shadowMatrices", &self.face_matrices(light.position));
+            shader.set_vec3("lightPos", light.position);
+            shader.set_float("farPlane", self.far_plane);
+            draw(shader);
+            count += 1;
+        }
+        // lights that stopped casting shadows give their cubemap back
+        self.maps.truncate(count);
+
+        Framebuffer::clear_binding();
+        unsafe {
+            glViewport(0, 0, viewport.0, viewport.1);
+        }
+    }
+
+    /// Binds the cubemaps to the units from `POINT_SHADOW_FIRST_UNIT`. Every sampler gets its
+    /// own unit even without a cubemap, cube and 2D samplers can't share one.
+    pub fn set_into_shader(&self, shader: &ShaderProgram) {
+        for slot in 0..MAX_POINT_SHADOWS {
+            let unit = POINT_SHADOW_FIRST_UNIT + slot;
+            unsafe {
+                glActiveTexture(usize_to_glenum(0x84c0 + unit));
+                match self.maps.get(slot) {
+                    Some((_, framebuffer)) => framebuffer.bind_texture(),
+                    None => glBindTexture(GL_TEXTURE_CUBE_MAP, 0),
+                }
+            }
+            shader.set_int(format!("pointShadowMaps[{}]", slot).as_str(), unit as i32);
+        }
+        unsafe {
+            glActiveTexture(GL_TEXTURE0);
+        }
+
+        shader.set_float("pointShadowFarPlane", self.far_plane);
+        shader.set_float("pointShadowBias", self.bias);
+    }
+}
@@ -0,0 +1,348 @@
+use {
+    glm::{cross, dot, vec3, TVec3},
+    std::collections::{HashMap, HashSet},
+};
+
+use crate::{mesh::Vertex, normals::position_key};
+
+/// Simplifies an indexed triangle list down to about `target_ratio` of its triangles, collapsing
+/// edges in order of their quadric error (Garland & Heckbert).
+///
+/// Vertices only get moved onto existing ones so the result still indexes `vertices`. Vertices
+/// on open borders stay in place and texture or normal seams can only collapse along themselves,
+/// so meshes made of many separate pieces may not reach the target.
+pub fn simplify(vertices: &[Vertex], indices: &[u32], target_ratio: f32) -> Vec<u32> {
+    let target = ((indices.len() / 3) as f32 * target_ratio.clamp(0.0, 1.0)) as usize;
+
+    // Vertices split at seams are welded back together by position, collapses work on these
+    // groups and carry every vertex of the group along.
+    let mut group_of_position: HashMap<[u32; 3], usize> = HashMap::new();
+    let group: Vec<usize> = vertices
+        .iter()
+        .map(|v| {
+            let next = group_of_position.len();
+            *group_of_position
+                .entry(position_key(&v.position))
+                .or_insert(next)
+        })
+        .collect();
+
+    let mut positions = vec![vec3(0.0, 0.0, 0.0); group_of_position.len()];
+    for (i, vertex) in vertices.iter().enumerate() {
+        positions[group[i]] = vertex.position;
+    }
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| positions[group[triangle[i] as usize]]);
+        let quadric = Quadric::from_triangle(&a, &b, &c);
+        for &i in triangle {
+            quadrics[group[i as usize]].add(&quadric);
+        }
+    }
+
+    let indices = without_degenerates(indices.to_vec(), |i| group[i as usize]);
+    let mut simplifier = Simplifier {
+        group,
+        positions,
+        quadrics,
+        indices,
+    };
+
+    while simplifier.indices.len() / 3 > target {
+        if simplifier.collapse_pass(target) == 0 {
+            break;
+        }
+    }
+
+    simplifier.indices
+}
+
+struct Simplifier {
+    /// Position group of each vertex
+    group: Vec<usize>,
+    positions: Vec<TVec3<f32>>,
+    quadrics: Vec<Quadric>,
+    indices: Vec<u32>,
+}
+
+impl Simplifier {
+    /// Collapses as many independent edges as possible, cheapest first, and returns how many
+    /// were collapsed
+    fn collapse_pass(&mut self, target: usize) -> usize {
+        let groups = self.positions.len();
+        let triangle_count = self.indices.len() / 3;
+
+        let mut triangles_of: Vec<Vec<usize>> = vec![Vec::new(); groups];
+        let mut edge_uses: HashMap<(usize, usize), u32> = HashMap::new();
+        for (t, triangle) in self.indices.chunks_exact(3).enumerate() {
+            for corner in 0..3 {
+                let a = self.group[triangle[corner] as usize];
+                let b = self.group[triangle[(corner + 1) % 3] as usize];
+                triangles_of[a].push(t);
+                *edge_uses.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+
+        // Edges used once are borders and more than twice are non-manifold, both get locked
+        let mut locked = vec![false; groups];
+        for (&(a, b), &uses) in &edge_uses {
+            if uses != 2 {
+                locked[a] = true;
+                locked[b] = true;
+            }
+        }
+
+        let mut candidates: Vec<(f64, usize, usize)> = Vec::with_capacity(edge_uses.len() * 2);
+        for &(a, b) in edge_uses.keys() {
+            for (from, to) in [(a, b), (b, a)] {
+                if !locked[from] {
+                    let mut quadric = self.quadrics[from].clone();
+                    quadric.add(&self.quadrics[to]);
+                    candidates.push((quadric.error(&self.positions[to]), from, to));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // Each collapse removes two triangles
+        let max_collapses = (triangle_count - target).div_ceil(2);
+        let mut remap: Vec<u32> = (0..self.group.len() as u32).collect();
+        let mut touched = vec![false; groups];
+        let mut collapses = 0;
+
+        for (_, from, to) in candidates {
+            if collapses >= max_collapses {
+                break;
+            }
+            if touched[from] || touched[to] {
+                continue;
+            }
+
+            let triangles = &triangles_of[from];
+            if !self.keeps_manifold(from, to, &triangles_of) || self.flips(from, to, triangles) {
+                continue;
+            }
+            let partners = match self.seam_partners(from, to, triangles) {
+                Some(partners) => partners,
+                None => continue,
+            };
+
+            for (vertex, partner) in partners {
+                remap[vertex as usize] = partner;
+            }
+            let quadric = self.quadrics[from].clone();
+            self.quadrics[to].add(&quadric);
+
+            // The whole neighbourhood is frozen until the next pass so that collapses in the
+            // same pass never see each other's triangles
+            for &t in triangles {
+                for &i in &self.indices[t * 3..t * 3 + 3] {
+                    touched[self.group[i as usize]] = true;
+                }
+            }
+            collapses += 1;
+        }
+
+        let group = &self.group;
+        self.indices = without_degenerates(
+            self.indices.iter().map(|&i| remap[i as usize]).collect(),
+            |i| group[i as usize],
+        );
+
+        collapses
+    }
+
+    /// The vertex each vertex of `from` turns into, found through the edges they share. Seam
+    /// vertices need a partner on the same side of the seam, collapses that can't find one
+    /// (or find several) are refused.
+    fn seam_partners(
+        &self,
+        from: usize,
+        to: usize,
+        triangles: &[usize],
+    ) -> Option<Vec<(u32, u32)>> {
+        let mut partners: HashMap<u32, Option<u32>> = HashMap::new();
+
+        for &t in triangles {
+            let triangle = &self.indices[t * 3..t * 3 + 3];
+            let vertex = *triangle.iter().find(|&&i| self.group[i as usize] == from)?;
+            let partner = triangle
+                .iter()
+                .find(|&&i| self.group[i as usize] == to)
+                .copied();
+
+            let entry = partners.entry(vertex).or_insert(None);
+            match (*entry, partner) {
+                (Some(current), Some(partner)) if current != partner => return None,
+                (None, Some(partner)) => *entry = Some(partner),
+                _ => (),
+            }
+        }
+
+        partners
+            .into_iter()
+            .map(|(vertex, partner)| partner.map(|p| (vertex, p)))
+            .collect()
+    }
+
+    /// Only the two vertices opposite to the collapsed edge may be shared by both ends,
+    /// otherwise the collapse would pinch the surface
+    fn keeps_manifold(&self, from: usize, to: usize, triangles_of: &[Vec<usize>]) -> bool {
+        let neighbours = |g: usize| -> HashSet<usize> {
+            triangles_of[g]
+                .iter()
+                .flat_map(|&t| &self.indices[t * 3..t * 3 + 3])
+                .map(|&i| self.group[i as usize])
+                .filter(|&n| n != g)
+                .collect()
+        };
+
+        neighbours(from).intersection(&neighbours(to)).count() <= 2
+    }
+
+    /// Whether moving `from` onto `to` turns any of the remaining triangles around
+    fn flips(&self, from: usize, to: usize, triangles: &[usize]) -> bool {
+        triangles.iter().any(|&t| {
+            let groups = [0, 1, 2].map(|c| self.group[self.indices[t * 3 + c] as usize]);
+            if groups.contains(&to) {
+                return false;
+            }
+
+            let before = groups.map(|g| self.positions[g]);
+            let after = groups.map(|g| self.positions[if g == from { to } else { g }]);
+            let normal_before = cross(&(before[1] - before[0]), &(before[2] - before[0]));
+            let normal_after = cross(&(after[1] - after[0]), &(after[2] - after[0]));
+
+            dot(&normal_before, &normal_after) <= 0.2 * normal_before.norm() * normal_after.norm()
+        })
+    }
+}
+
+/// Symmetric 4x4 matrix measuring the squared distance to a set of planes
+#[derive(Clone, Default)]
+struct Quadric {
+    // a², ab, ac, ad, b², bc, bd, c², cd, d²
+    coefficients: [f64; 10],
+}
+
+impl Quadric {
+    /// Plane of the triangle, weighted by its area so that small triangles matter less
+    fn from_triangle(a: &TVec3<f32>, b: &TVec3<f32>, c: &TVec3<f32>) -> Self {
+        let normal = cross(&(b - a), &(c - a));
+        let length = normal.norm();
+        if length <= 0.0 {
+            return Quadric::default();
+        }
+
+        let area = (length / 2.0) as f64;
+        let n = normal / length;
+        let d = -dot(&n, a) as f64;
+        let (a, b, c) = (n.x as f64, n.y as f64, n.z as f64);
+
+        Self {
+            coefficients: [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|q| q * area),
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (q, o) in self.coefficients.iter_mut().zip(other.coefficients) {
+            *q += o;
+        }
+    }
+
+    fn error(&self, p: &TVec3<f32>) -> f64 {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.coefficients;
+        let (x, y, z) = (p.x as f64, p.y as f64, p.z as f64);
+
+        aa * x * x
+            + bb * y * y
+            + cc * z * z
+            + 2.0 * (ab * x * y + ac * x * z + bc * y * z)
+            + 2.0 * (ad * x + bd * y + cd * z)
+            + dd
+    }
+}
+
+/// Drops the triangles with two corners in the same group
+fn without_degenerates<F: Fn(u32) -> usize>(indices: Vec<u32>, group: F) -> Vec<u32> {
+    indices
+        .chunks_exact(3)
+        .filter(|t| {
+            let [a, b, c] = [group(t[0]), group(t[1]), group(t[2])];
+            a != b && b != c && a != c
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bounds::Aabb, primitives};
+
+    fn assert_valid(vertices: &[Vertex], indices: &[u32]) {
+        assert_eq!(indices.len() % 3, 0);
+        for triangle in indices.chunks_exact(3) {
+            assert!(triangle.iter().all(|&i| (i as usize) < vertices.len()));
+            let [a, b, c] =
+                [0, 1, 2].map(|k| position_key(&vertices[triangle[k] as usize].position));
+            assert!(
+                a != b && b != c && a != c,
+                "degenerate triangle {:?}",
+                triangle
+            );
+        }
+    }
+
+    #[test]
+    fn closed_shapes_reach_the_target() {
+        for (vertices, indices) in [
+            primitives::icosphere(3),
+            primitives::uv_sphere(32, 16),
+            primitives::torus(0.75, 0.25, 32, 16),
+        ] {
+            for ratio in [0.5, 0.25, 0.1] {
+                let target = ((indices.len() / 3) as f32 * ratio) as usize;
+                let simplified = simplify(&vertices, &indices, ratio);
+
+                let triangles = simplified.len() / 3;
+                assert!(triangles <= target && triangles + 1 >= target);
+                assert_valid(&vertices, &simplified);
+            }
+        }
+    }
+
+    #[test]
+    fn open_borders_stay_in_place() {
+        let (vertices, indices) = primitives::grid(16, 16);
+        let simplified = simplify(&vertices, &indices, 0.1);
+
+        assert!(simplified.len() < indices.len() / 4);
+        assert_valid(&vertices, &simplified);
+        let bounds = |indices: &[u32]| {
+            Aabb::from_points(indices.iter().map(|&i| &vertices[i as usize].position))
+        };
+        let (before, after) = (bounds(&indices), bounds(&simplified));
+        assert_eq!((before.min, before.max), (after.min, after.max));
+    }
+
+    #[test]
+    fn full_ratio_keeps_every_triangle() {
+        let (vertices, indices) = primitives::icosphere(2);
+        assert_eq!(simplify(&vertices, &indices, 1.0), indices);
+    }
+}
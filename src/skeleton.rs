@@ -0,0 +1,127 @@
+use glm::{Mat4, Qua, TVec3};
+
+use crate::{keyframes::Interpolate, utils::IDENTITY_MAT4};
+
+/// Size of the `jointMatrices` uniform array of the skinning shaders
+pub const MAX_JOINTS: usize = 128;
+
+/// Local transform of a joint relative to its parent
+#[derive(Clone)]
+pub struct JointTransform {
+    pub translation: TVec3<f32>,
+    pub rotation: Qua<f32>,
+    pub scale: TVec3<f32>,
+}
+
+impl Default for JointTransform {
+    fn default() -> Self {
+        Self {
+            translation: glm::vec3(0.0, 0.0, 0.0),
+            rotation: glm::quat_identity(),
+            scale: glm::vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl JointTransform {
+    pub fn matrix(&self) -> Mat4 {
+        glm::translation(&self.translation)
+            * glm::quat_to_mat4(&self.rotation)
+            * glm::scaling(&self.scale)
+    }
+
+    pub fn blend(&self, other: &JointTransform, weight: f32) -> JointTransform {
+        // Quaternions have an inherent lerp that doesn't normalize, the trait's is wanted
+        JointTransform {
+            translation: Interpolate::lerp(&self.translation, &other.translation, weight),
+            rotation: Interpolate::lerp(&self.rotation, &other.rotation, weight),
+            scale: Interpolate::lerp(&self.scale, &other.scale, weight),
+        }
+    }
+}
+
+pub struct Joint {
+    pub parent: Option<usize>,
+    /// Transform of the nodes that aren't joints between the joint and its parent, or above
+    /// the joint for a root
+    pub offset: Mat4,
+    /// Transform of the joint when no animation is playing
+    pub rest: JointTransform,
+    /// Brings the vertices from the mesh's space into the joint's space
+    pub inverse_bind_matrix: Mat4,
+}
+
+/// Joints in the order the vertices refer to them
+pub struct Skeleton {
+    pub joints: Vec<Joint>,
+    /// Joint indices sorted so that parents come before their children
+    order: Vec<usize>,
+}
+
+impl Skeleton {
+    pub fn new(joints: Vec<Joint>) -> Self {
+        assert!(
+            joints.len() <= MAX_JOINTS,
+            "Skeleton has {} joints but the shaders only support {}",
+            joints.len(),
+            MAX_JOINTS
+        );
+
+        let depth = |mut joint: usize| {
+            let mut depth = 0;
+            while let Some(parent) = joints[joint].parent {
+                joint = parent;
+                depth += 1;
+            }
+            depth
+        };
+        let mut order: Vec<usize> = (0..joints.len()).collect();
+        order.sort_by_key(|&joint| depth(joint));
+
+        Self { joints, order }
+    }
+
+    pub fn rest_pose(&self) -> Pose {
+        Pose {
+            joints: self.joints.iter().map(|j| j.rest.clone()).collect(),
+        }
+    }
+
+    /// Skinning matrix of each joint, bringing the vertices from the bind pose to `pose`
+    pub fn joint_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+        let mut globals = vec![*IDENTITY_MAT4; self.joints.len()];
+        for &joint in &self.order {
+            let parent = match self.joints[joint].parent {
+                Some(parent) => globals[parent],
+                None => *IDENTITY_MAT4,
+            };
+            globals[joint] = parent * self.joints[joint].offset * pose.joints[joint].matrix();
+        }
+
+        globals
+            .iter()
+            .zip(&self.joints)
+            .map(|(global, joint)| global * joint.inverse_bind_matrix)
+            .collect()
+    }
+}
+
+/// Local transform of every joint of a skeleton
+#[derive(Clone)]
+pub struct Pose {
+    pub joints: Vec<JointTransform>,
+}
+
+impl Pose {
+    /// `weight` goes from 0 for this pose to 1 for `other`
+    pub fn blend(&self, other: &Pose, weight: f32) -> Pose {
+        Pose {
+            joints: self
+                .joints
+                .iter()
+                .zip(&other.joints)
+                .map(|(a, b)| a.blend(b, weight))
+                .collect(),
+        }
+    }
+}
@@ -1,9 +1,15 @@
 use glm::TVec4;
 
-use crate::{draw::Draw, quad::Quad, utils::*};
+use crate::{
+    draw::Draw,
+    quad::Quad,
+    tween::{Easing, Tween},
+    utils::*,
+};
 
-pub struct Slider<'a> {
-    target: &'a mut f32,
+/// Horizontal bar showing a value between `min` and `max`, read back with `value`
+pub struct Slider {
+    value: f32,
     position: (f32, f32),
     inner_quad: Quad,
     outer_quad: Quad,
@@ -15,11 +21,13 @@ pub struct Slider<'a> {
     inner_color: TVec4<f32>,
     min: f32,
     max: f32,
+    /// Running `animate_to` transition
+    tween: Option<Tween<f32>>,
 }
 
-impl<'a> Slider<'a> {
+impl Slider {
     pub fn new(
-        target: &'a mut f32,
+        value: f32,
         position: (f32, f32),
         width: f32,
         height: f32,
@@ -31,16 +39,14 @@ impl<'a> Slider<'a> {
         window_height: f32,
         margin: f32,
     ) -> Self {
-        let value = *target;
         Self {
-            target,
+            value,
             position,
             inner_quad: Slider::generate_inner_quad(
                 position,
                 margin,
                 value,
                 max,
-                min,
                 width,
                 height,
                 window_width,
@@ -51,8 +57,8 @@ impl<'a> Slider<'a> {
                 vec2_from_tuple(pixels_to_coords(
                     position.0,
                     position.1,
-                    window_width as f32,
-                    window_height as f32,
+                    window_width,
+                    window_height,
                 )),
                 width / window_width,
                 height / window_height,
@@ -66,6 +72,7 @@ impl<'a> Slider<'a> {
             inner_color,
             min,
             max,
+            tween: None,
         }
     }
 
@@ -74,7 +81,6 @@ impl<'a> Slider<'a> {
         margin: f32,
         value: f32,
         max: f32,
-        min: f32,
         width: f32,
         height: f32,
         window_width: f32,
@@ -98,19 +104,18 @@ impl<'a> Slider<'a> {
 
     pub fn set_value(&mut self, value: f32) {
         if value < self.min {
-            *self.target = self.min;
+            self.value = self.min;
         } else if value > self.max {
-            *self.target = self.max;
+            self.value = self.max;
         } else {
-            *self.target = value;
+            self.value = value;
         }
 
         self.inner_quad = Slider::generate_inner_quad(
             self.position,
             self.margin,
-            *self.target,
+            self.value,
             self.max,
-            self.min,
             self.width,
             self.height,
             self.window_width,
@@ -119,12 +124,28 @@ impl<'a> Slider<'a> {
         )
     }
 
-    pub fn step_value(&mut self, increment: f32) {
-        self.set_value(*self.target + increment)
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Moves the slider to `value` over `duration` seconds, as `update` gets called
+    pub fn animate_to(&mut self, value: f32, duration: f32, easing: Easing) {
+        let value = value.clamp(self.min, self.max);
+        self.tween = Some(Tween::new(self.value, value, duration, easing));
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        if let Some(tween) = &mut self.tween {
+            let value = tween.update(delta);
+            if tween.is_finished() {
+                self.tween = None;
+            }
+            self.set_value(value);
+        }
     }
 }
 
-impl<'a> Draw for Slider<'a> {
+impl Draw for Slider {
     fn draw(&self, shader: &crate::shader_program::ShaderProgram) {
         self.inner_quad.draw(shader);
         self.outer_quad.draw(shader);
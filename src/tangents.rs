@@ -0,0 +1,124 @@
+use glm::{cross, dot, vec3, TVec3};
+
+use crate::mesh::Vertex;
+
+/// Computes per-vertex tangents and bitangents from the texture coordinates. Triangle
+/// contributions are accumulated per vertex weighted by their corner angle, tangents are
+/// orthogonalized against the normal and the bitangent is rebuilt as
+/// `sign * cross(normal, tangent)` so the shader can trust the basis handedness. This is not
+/// MikkTSpace, normal maps baked against it can show slight seams.
+///
+/// Vertices shared by triangles of opposite handedness, like along the seam of mirrored UVs,
+/// get split so each side keeps its own basis. Returns the vertex each vertex was made from.
+pub fn generate_tangents(vertices: &mut Vec<Vertex>, indices: &mut [u32]) -> Vec<u32> {
+    let mut sources: Vec<u32> = (0..vertices.len() as u32).collect();
+    // The handedness each vertex was first used with and its copy with the other one
+    let mut handedness: Vec<Option<bool>> = vec![None; vertices.len()];
+    let mut mirrored: Vec<Option<u32>> = vec![None; vertices.len()];
+
+    let mut tangents = vec![vec3(0.0, 0.0, 0.0); vertices.len()];
+    let mut bitangents = vec![vec3(0.0, 0.0, 0.0); vertices.len()];
+
+    for triangle in indices.chunks_exact_mut(3) {
+        let v = [
+            vertices[triangle[0] as usize],
+            vertices[triangle[1] as usize],
+            vertices[triangle[2] as usize],
+        ];
+
+        let edge1 = v[1].position - v[0].position;
+        let edge2 = v[2].position - v[0].position;
+        let duv1 = v[1].tex_coords - v[0].tex_coords;
+        let duv2 = v[2].tex_coords - v[0].tex_coords;
+
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let r = 1.0 / det;
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) * r;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) * r;
+
+        let weights: Vec<f32> = (0..3)
+            .map(|corner| {
+                let p = v[corner].position;
+                let a = v[(corner + 1) % 3].position - p;
+                let b = v[(corner + 2) % 3].position - p;
+                corner_angle(&a, &b)
+            })
+            .collect();
+        let right_handed: Vec<bool> = v
+            .iter()
+            .map(|v| dot(&cross(&v.normal, &tangent), &bitangent) >= 0.0)
+            .collect();
+
+        for corner in 0..3 {
+            let mut index = triangle[corner] as usize;
+            match handedness[index] {
+                None => handedness[index] = Some(right_handed[corner]),
+                Some(h) if h != right_handed[corner] => {
+                    let copy = match mirrored[index] {
+                        Some(copy) => copy,
+                        None => {
+                            vertices.push(vertices[index]);
+                            sources.push(sources[index]);
+                            handedness.push(Some(right_handed[corner]));
+                            mirrored.push(None);
+                            tangents.push(vec3(0.0, 0.0, 0.0));
+                            bitangents.push(vec3(0.0, 0.0, 0.0));
+                            let copy = (vertices.len() - 1) as u32;
+                            mirrored[index] = Some(copy);
+                            copy
+                        }
+                    };
+                    triangle[corner] = copy;
+                    index = copy as usize;
+                }
+                Some(_) => {}
+            }
+
+            tangents[index] += tangent * weights[corner];
+            bitangents[index] += bitangent * weights[corner];
+        }
+    }
+
+    for (i, vertex) in vertices.iter_mut().enumerate() {
+        let n = vertex.normal;
+
+        // Gram-Schmidt, falling back on any vector orthogonal to the normal for vertices
+        // without usable texture coordinates
+        let mut t = tangents[i] - n * dot(&n, &tangents[i]);
+        if t.norm_squared() < f32::EPSILON {
+            t = any_orthogonal(&n);
+        }
+        let t = t.normalize();
+
+        let sign = if dot(&cross(&n, &t), &bitangents[i]) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        vertex.tangent = t;
+        vertex.bitangent = cross(&n, &t) * sign;
+    }
+
+    sources
+}
+
+fn corner_angle(a: &TVec3<f32>, b: &TVec3<f32>) -> f32 {
+    let len = a.norm() * b.norm();
+    if len <= 0.0 {
+        return 0.0;
+    }
+    (dot(a, b) / len).clamp(-1.0, 1.0).acos()
+}
+
+fn any_orthogonal(n: &TVec3<f32>) -> TVec3<f32> {
+    let axis = if n.x.abs() < 0.9 {
+        vec3(1.0, 0.0, 0.0)
+    } else {
+        vec3(0.0, 1.0, 0.0)
+    };
+    axis - n * dot(n, &axis)
+}
@@ -1,106 +1,163 @@
-use image::io::Reader as ImageReader;
-
-use derive_more::Display;
-use gl33::{global_loader::*, *};
-use std::path::Path;
-
-use super::glenum_to_i32;
-
-#[derive(Display, Clone)]
-pub enum TextureType {
-    Diffuse,
-    Specular,
-    Emission,
-    Ambient,
-    Normal,
-}
-
-#[derive(Clone)]
-pub struct Texture2D {
-    id: u32,
-    pub ty: TextureType,
-    pub path: String,
-}
-
-impl Texture2D {
-    pub fn from_texture(filename: &str, ty: TextureType) -> Self {
-        Texture2D::from_image(
-            filename,
-            Path::new("ressources").join("textures").to_str().unwrap(),
-            ty,
-        )
-    }
-
-    pub fn from_image(path: &str, directory: &str, ty: TextureType) -> Self {
-        let filename = Path::new(directory).join(path);
-        let filename_copy = filename.clone();
-
-        let image = ImageReader::open(filename)
-            .expect(format!("filename: \"{}\"", filename_copy.to_str().unwrap()).as_str())
-            .decode()
-            .unwrap();
-        //.flipv();
-        let format = match image {
-            image::DynamicImage::ImageLuma8(_) => GL_RED,
-            image::DynamicImage::ImageLumaA8(_) => GL_RG,
-            image::DynamicImage::ImageRgb8(_) => GL_RGB,
-            image::DynamicImage::ImageRgba8(_) => GL_RGBA,
-            _ => panic!("Unsupported image format"),
-        };
-
-        let mut ret = Self {
-            id: 0,
-            ty,
-            path: path.to_owned(),
-        };
-
-        let mut id = 0;
-        unsafe {
-            glGenTextures(1, &mut id);
-            assert_ne!(id, 0);
-
-            glBindTexture(GL_TEXTURE_2D, id);
-            glTexImage2D(
-                GL_TEXTURE_2D,
-                0,
-                glenum_to_i32(format),
-                image.width().try_into().unwrap(),
-                image.height().try_into().unwrap(),
-                0,
-                format,
-                GL_UNSIGNED_BYTE,
-                image.as_bytes().as_ptr().cast(),
-            );
-            glGenerateMipmap(GL_TEXTURE_2D);
-
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, glenum_to_i32(GL_REPEAT));
-            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, glenum_to_i32(GL_REPEAT));
-            glTexParameteri(
-                GL_TEXTURE_2D,
-                GL_TEXTURE_MIN_FILTER,
-                glenum_to_i32(GL_LINEAR_MIPMAP_LINEAR),
-            );
-            glTexParameteri(
-                GL_TEXTURE_2D,
-                GL_TEXTURE_MAG_FILTER,
-                glenum_to_i32(GL_LINEAR),
-            );
-        }
-
-        ret.id = id;
-
-        ret
-    }
-
-    pub fn bind(&self) {
-        unsafe {
-            glBindTexture(GL_TEXTURE_2D, self.id);
-        }
-    }
-
-    pub fn clear_binding() {
-        unsafe {
-            glBindTexture(GL_TEXTURE_2D, 0);
-        }
-    }
-}
+use image::io::Reader as ImageReader;
+
+use derive_more::Display;
+use gl33::{global_loader::*, *};
+use std::path::Path;
+
+use super::glenum_to_i32;
+
+/// Displays as the prefix of the sampler uniforms the texture is bound to in the shaders.
+///
+/// The PBR shader reads `Diffuse` as its base color, the remaining metallic-roughness maps have
+/// their own variants. Color maps are stored in sRGB, the others hold linear data.
+#[derive(Display, Clone)]
+pub enum TextureType {
+    #[display(fmt = "texture_diffuse")]
+    Diffuse,
+    #[display(fmt = "texture_specular")]
+    Specular,
+    #[display(fmt = "texture_emission")]
+    Emission,
+    #[display(fmt = "texture_ambient")]
+    Ambient,
+    #[display(fmt = "texture_normal")]
+    Normal,
+    #[display(fmt = "texture_metallic")]
+    Metallic,
+    #[display(fmt = "texture_roughness")]
+    Roughness,
+    /// glTF packing: roughness in the green channel, metallic in the blue one
+    #[display(fmt = "texture_metallic_roughness")]
+    MetallicRoughness,
+    #[display(fmt = "texture_occlusion")]
+    Occlusion,
+    /// Baked lighting, sampled with the second UV channel
+    #[display(fmt = "texture_lightmap")]
+    Lightmap,
+}
+
+impl TextureType {
+    /// Whether the texels are sRGB encoded colors, which the GPU decodes to linear values
+    /// when sampling
+    pub fn is_srgb(&self) -> bool {
+        matches!(self, TextureType::Diffuse | TextureType::Emission)
+    }
+}
+
+#[derive(Clone)]
+pub struct Texture2D {
+    id: u32,
+    pub ty: TextureType,
+    pub path: String,
+}
+
+impl Texture2D {
+    pub fn from_texture(filename: &str, ty: TextureType) -> Self {
+        Texture2D::from_image(
+            filename,
+            Path::new("ressources").join("textures").to_str().unwrap(),
+            ty,
+        )
+    }
+
+    pub fn from_image(path: &str, directory: &str, ty: TextureType) -> Self {
+        let filename = Path::new(directory).join(path);
+
+        let image = ImageReader::open(&filename)
+            .unwrap_or_else(|e| panic!("{}, filename: \"{}\"", e, filename.to_str().unwrap()))
+            .decode()
+            .unwrap();
+        //.flipv();
+        let format = match image {
+            image::DynamicImage::ImageLuma8(_) => GL_RED,
+            image::DynamicImage::ImageLumaA8(_) => GL_RG,
+            image::DynamicImage::ImageRgb8(_) => GL_RGB,
+            image::DynamicImage::ImageRgba8(_) => GL_RGBA,
+            _ => panic!("Unsupported image format"),
+        };
+
+        Texture2D::from_pixels(
+            image.as_bytes(),
+            image.width(),
+            image.height(),
+            format,
+            path,
+            ty,
+        )
+    }
+
+    /// 1x1 texture of a single color, stands in for the maps a material doesn't provide
+    pub fn from_color(color: [u8; 4], ty: TextureType) -> Self {
+        Texture2D::from_pixels(&color, 1, 1, GL_RGBA, &format!("#color{:?}", color), ty)
+    }
+
+    pub fn from_pixels(
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        format: GLenum,
+        path: &str,
+        ty: TextureType,
+    ) -> Self {
+        // there are no sRGB formats with less than three channels
+        let internal_format = match format {
+            GL_RGB if ty.is_srgb() => GL_SRGB8,
+            GL_RGBA if ty.is_srgb() => GL_SRGB8_ALPHA8,
+            _ => format,
+        };
+        let mut ret = Self {
+            id: 0,
+            ty,
+            path: path.to_owned(),
+        };
+
+        let mut id = 0;
+        unsafe {
+            glGenTextures(1, &mut id);
+            assert_ne!(id, 0);
+
+            glBindTexture(GL_TEXTURE_2D, id);
+            glTexImage2D(
+                GL_TEXTURE_2D,
+                0,
+                glenum_to_i32(internal_format),
+                width.try_into().unwrap(),
+                height.try_into().unwrap(),
+                0,
+                format,
+                GL_UNSIGNED_BYTE,
+                pixels.as_ptr().cast(),
+            );
+            glGenerateMipmap(GL_TEXTURE_2D);
+
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, glenum_to_i32(GL_REPEAT));
+            glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, glenum_to_i32(GL_REPEAT));
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_MIN_FILTER,
+                glenum_to_i32(GL_LINEAR_MIPMAP_LINEAR),
+            );
+            glTexParameteri(
+                GL_TEXTURE_2D,
+                GL_TEXTURE_MAG_FILTER,
+                glenum_to_i32(GL_LINEAR),
+            );
+        }
+
+        ret.id = id;
+
+        ret
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, self.id);
+        }
+    }
+
+    pub fn clear_binding() {
+        unsafe {
+            glBindTexture(GL_TEXTURE_2D, 0);
+        }
+    }
+}
@@ -33,28 +33,4 @@ impl Transform {
         let translation_matrix = translate(&scale_matrix, &self.position);
         self.rotation() * translation_matrix
     }
-
-    pub fn mut_angles(&mut self) -> Vec3Mut {
-        self.angles.as_vec3_mut()
-    }
-}
-
-pub struct Vec3Mut<'a> {
-    pub x: &'a mut f32,
-    pub y: &'a mut f32,
-    pub z: &'a mut f32,
-}
-
-pub trait AsVec3Mut {
-    fn as_vec3_mut(&mut self) -> Vec3Mut;
-}
-
-impl AsVec3Mut for TVec3<f32> {
-    fn as_vec3_mut(&mut self) -> Vec3Mut {
-        Vec3Mut {
-            x: &mut self.x,
-            y: &mut self.y,
-            z: &mut self.z,
-        }
-    }
 }
@@ -0,0 +1,174 @@
+use glm::TVec3;
+
+use crate::{keyframes::Keyframes, transform::Transform};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TransformProperty {
+    Position,
+    /// Euler angles in degrees, like `Transform::angles`
+    Angles,
+    Scale,
+}
+
+impl TransformProperty {
+    fn get_mut(self, transform: &mut Transform) -> &mut TVec3<f32> {
+        match self {
+            TransformProperty::Position => &mut transform.position,
+            TransformProperty::Angles => &mut transform.angles,
+            TransformProperty::Scale => &mut transform.scale,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TransformTrack {
+    pub property: TransformProperty,
+    pub keyframes: Keyframes<TVec3<f32>>,
+}
+
+/// Named marker reported by `TransformAnimation::update` when playback goes over it
+#[derive(Clone)]
+pub struct AnimationEvent {
+    pub time: f32,
+    pub name: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PlaybackMode {
+    /// Stops on the last keyframe
+    Once,
+    /// Jumps back to the start after the last keyframe
+    Loop,
+    /// Plays forward then backward, forever
+    PingPong,
+}
+
+/// Keyframe tracks driving the position, angles and scale of a `Transform`, properties
+/// without a track are left alone
+pub struct TransformAnimation {
+    tracks: Vec<TransformTrack>,
+    /// Sorted by time
+    events: Vec<AnimationEvent>,
+    pub mode: PlaybackMode,
+    /// Multiplies the time given to `update`, can't be negative
+    pub speed: f32,
+    time: f32,
+    forward: bool,
+    /// Whether events sitting exactly on the current time still have to be reported
+    at_start: bool,
+    finished: bool,
+}
+
+impl TransformAnimation {
+    pub fn new(tracks: Vec<TransformTrack>, mode: PlaybackMode) -> Self {
+        Self {
+            tracks,
+            events: Vec::new(),
+            mode,
+            speed: 1.0,
+            time: 0.0,
+            forward: true,
+            at_start: true,
+            finished: false,
+        }
+    }
+
+    pub fn add_event(&mut self, time: f32, name: &str) {
+        let index = self.events.partition_point(|event| event.time <= time);
+        self.events.insert(
+            index,
+            AnimationEvent {
+                time,
+                name: name.to_string(),
+            },
+        );
+    }
+
+    /// Adds an event on every keyframe of `track`, named after the track's index and the
+    /// keyframe's
+    pub fn add_keyframe_events(&mut self, track: usize) {
+        let times = self.tracks[track].keyframes.times.clone();
+        for (i, time) in times.into_iter().enumerate() {
+            self.add_event(time, format!("track{}_key{}", track, i).as_str());
+        }
+    }
+
+    /// Time of the last keyframe of all tracks
+    pub fn duration(&self) -> f32 {
+        self.tracks
+            .iter()
+            .map(|track| track.keyframes.duration())
+            .fold(0.0, f32::max)
+    }
+
+    /// Only `PlaybackMode::Once` animations ever finish
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.forward = true;
+        self.at_start = true;
+        self.finished = false;
+    }
+
+    /// Advances the animation by `delta` seconds, writes the tracks' values into `transform`
+    /// and returns the names of the events that were passed, in order
+    pub fn update(&mut self, delta: f32, transform: &mut Transform) -> Vec<&str> {
+        let duration = self.duration();
+        let mut remaining = delta * self.speed;
+        let mut passed = Vec::new();
+
+        while !self.finished && remaining > 0.0 && duration > 0.0 {
+            // Whatever goes past either end is carried over to the next pass instead of
+            // being subtracted, rounding could otherwise leave a remainder too small to move
+            let start = self.time;
+            let end = if self.forward {
+                start + remaining
+            } else {
+                start - remaining
+            };
+            self.time = end.clamp(0.0, duration);
+            remaining = (end - self.time).abs();
+
+            if self.forward {
+                passed.extend(self.events.iter().filter(|event| {
+                    (event.time > start || (self.at_start && event.time == start))
+                        && event.time <= self.time
+                }));
+            } else {
+                passed.extend(
+                    self.events
+                        .iter()
+                        .filter(|event| event.time < start && event.time >= self.time)
+                        .rev(),
+                );
+            }
+            self.at_start = false;
+
+            if self.forward && self.time >= duration {
+                match self.mode {
+                    PlaybackMode::Once => self.finished = true,
+                    PlaybackMode::Loop => {
+                        self.time = 0.0;
+                        self.at_start = true;
+                    }
+                    PlaybackMode::PingPong => self.forward = false,
+                }
+            } else if !self.forward && self.time <= 0.0 {
+                self.forward = true;
+            }
+        }
+
+        self.apply(transform);
+        passed.iter().map(|event| event.name.as_str()).collect()
+    }
+
+    /// Writes the values of the tracks at the current time into `transform`
+    pub fn apply(&self, transform: &mut Transform) {
+        for track in &self.tracks {
+            *track.property.get_mut(transform) = track.keyframes.sample(self.time);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::f32::consts::PI;
+
+use glm::TVec3;
+
+use crate::keyframes::Interpolate;
+
+/// Shapes the progress of a tween, see https://easings.net
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    /// Overshoots the end value a bit before settling on it
+    BackOut,
+    ElasticOut,
+    BounceOut,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 15] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::SineIn,
+        Easing::SineOut,
+        Easing::SineInOut,
+        Easing::ExpoIn,
+        Easing::ExpoOut,
+        Easing::BackOut,
+        Easing::ElasticOut,
+        Easing::BounceOut,
+    ];
+
+    /// Maps `t` from 0 to 1 onto the curve, which starts at 0 and ends at 1
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => -((t * PI).cos() - 1.0) / 2.0,
+            Easing::ExpoIn if t == 0.0 => 0.0,
+            Easing::ExpoIn => 2.0f32.powf(10.0 * t - 10.0),
+            Easing::ExpoOut if t == 1.0 => 1.0,
+            Easing::ExpoOut => 1.0 - 2.0f32.powf(-10.0 * t),
+            Easing::BackOut => {
+                let c1 = 1.70158;
+                1.0 + (c1 + 1.0) * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+            Easing::ElasticOut if t == 0.0 || t == 1.0 => t,
+            Easing::ElasticOut => {
+                2.0f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+            }
+            Easing::BounceOut => {
+                let (n1, d1) = (7.5625, 2.75);
+                if t < 1.0 / d1 {
+                    n1 * t * t
+                } else if t < 2.0 / d1 {
+                    let t = t - 1.5 / d1;
+                    n1 * t * t + 0.75
+                } else if t < 2.5 / d1 {
+                    let t = t - 2.25 / d1;
+                    n1 * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / d1;
+                    n1 * t * t + 0.984375
+                }
+            }
+        }
+    }
+}
+
+/// Something a tween can read its start value from and write its values into
+pub trait TweenTarget<T> {
+    fn get(&self) -> T;
+    fn set(&mut self, value: T);
+}
+
+impl TweenTarget<f32> for f32 {
+    fn get(&self) -> f32 {
+        *self
+    }
+
+    fn set(&mut self, value: f32) {
+        *self = value
+    }
+}
+
+impl TweenTarget<TVec3<f32>> for TVec3<f32> {
+    fn get(&self) -> TVec3<f32> {
+        *self
+    }
+
+    fn set(&mut self, value: TVec3<f32>) {
+        *self = value
+    }
+}
+
+/// Eases a value from `from` to `to` over `duration` seconds
+#[derive(Clone)]
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    pub easing: Easing,
+    elapsed: f32,
+}
+
+impl<T: Interpolate> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Starts from the current value of `target`
+    pub fn to(target: &impl TweenTarget<T>, to: T, duration: f32, easing: Easing) -> Self {
+        Tween::new(target.get(), to, duration, easing)
+    }
+
+    pub fn value(&self) -> T {
+        if self.duration <= 0.0 {
+            return self.to.clone();
+        }
+        let t = self.easing.apply(self.elapsed / self.duration);
+        self.from.lerp(&self.to, t)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Advances the tween by `delta` seconds and returns its new value
+    pub fn update(&mut self, delta: f32) -> T {
+        self.elapsed = (self.elapsed + delta).min(self.duration.max(0.0));
+        self.value()
+    }
+
+    /// Advances the tween by `delta` seconds and writes its new value into `target`
+    pub fn drive(&mut self, delta: f32, target: &mut impl TweenTarget<T>) {
+        target.set(self.update(delta));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easings_start_at_0_and_end_at_1() {
+        for easing in Easing::ALL {
+            assert!(
+                easing.apply(0.0).abs() < 1e-5,
+                "{:?} starts at {}",
+                easing,
+                easing.apply(0.0)
+            );
+            assert!(
+                (easing.apply(1.0) - 1.0).abs() < 1e-5,
+                "{:?} ends at {}",
+                easing,
+                easing.apply(1.0)
+            );
+            assert_eq!(easing.apply(-1.0), easing.apply(0.0), "{:?}", easing);
+            assert_eq!(easing.apply(2.0), easing.apply(1.0), "{:?}", easing);
+        }
+    }
+}
@@ -1,79 +1,76 @@
-use glm::TVec2;
-
-use {
-    gl33::{global_loader::*, *},
-    glm::Mat4,
-    lazy_static::lazy_static,
-    std::f32::consts::PI,
-};
-
-lazy_static! {
-    pub static ref IDENTITY_MAT4: Mat4 =
-        glm::mat4(1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,);
-}
-
-pub fn to_radians(e: f32) -> f32 {
-    e * PI / 180.0
-}
-
-pub fn clear_color(r: f32, g: f32, b: f32) {
-    unsafe {
-        glClearColor(r, g, b, 1.0);
-    }
-}
-
-pub fn gl_clear(bits: GLbitfield) {
-    unsafe {
-        glClear(bits);
-    }
-}
-
-pub fn active_texture(tex: GLenum) {
-    unsafe {
-        glActiveTexture(tex);
-    }
-}
-
-pub fn glenum_to_i32(e: GLenum) -> i32 {
-    match e {
-        GL_RED => 0x1903,
-        GL_RGB => 0x1907,
-        GL_NEAREST => 0x2600,
-        GL_LINEAR => 0x2601,
-        GL_LINEAR_MIPMAP_LINEAR => 0x2703,
-        GL_REPEAT => 0x2901,
-        GL_CLAMP_TO_EDGE => 0x812F,
-        _ => panic!("Don't call for GLenum variant {:?}", e),
-    }
-}
-
-pub fn usize_to_glenum(e: usize) -> GLenum {
-    match e {
-        0x84c0 => GL_TEXTURE0,
-        0x84c1 => GL_TEXTURE1,
-        0x84c2 => GL_TEXTURE2,
-        0x84c3 => GL_TEXTURE3,
-        0x84c4 => GL_TEXTURE4,
-        0x84c5 => GL_TEXTURE5,
-        0x84c6 => GL_TEXTURE6,
-        0x84c7 => GL_TEXTURE7,
-        0x84c8 => GL_TEXTURE8,
-        0x84c9 => GL_TEXTURE9,
-        _ => unimplemented!(),
-    }
-}
-
-pub fn scale_with_negative(v: f32, scale: f32) -> f32 {
-    (v / (scale / 2.0)) - 1.0
-}
-
-pub fn pixels_to_coords(x: f32, y: f32, window_width: f32, window_height: f32) -> (f32, f32) {
-    (
-        scale_with_negative(x, window_width),
-        -scale_with_negative(y, window_height),
-    )
-}
-
-pub fn vec2_from_tuple(v: (f32, f32)) -> TVec2<f32> {
-    glm::vec2(v.0, v.1)
-}
+use glm::TVec2;
+
+use {
+    gl33::{global_loader::*, *},
+    glm::Mat4,
+    lazy_static::lazy_static,
+    std::f32::consts::PI,
+};
+
+lazy_static! {
+    pub static ref IDENTITY_MAT4: Mat4 =
+        glm::mat4(1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,);
+}
+
+pub fn to_radians(e: f32) -> f32 {
+    e * PI / 180.0
+}
+
+pub fn clear_color(r: f32, g: f32, b: f32) {
+    unsafe {
+        glClearColor(r, g, b, 1.0);
+    }
+}
+
+pub fn gl_clear(bits: GLbitfield) {
+    unsafe {
+        glClear(bits);
+    }
+}
+
+pub fn active_texture(tex: GLenum) {
+    unsafe {
+        glActiveTexture(tex);
+    }
+}
+
+pub fn glenum_to_i32(e: GLenum) -> i32 {
+    match e {
+        GL_RED => 0x1903,
+        GL_RG => 0x8227,
+        GL_RGB => 0x1907,
+        GL_RGBA => 0x1908,
+        GL_SRGB8 => 0x8C41,
+        GL_SRGB8_ALPHA8 => 0x8C43,
+        GL_NEAREST => 0x2600,
+        GL_LINEAR => 0x2601,
+        GL_LINEAR_MIPMAP_LINEAR => 0x2703,
+        GL_REPEAT => 0x2901,
+        GL_CLAMP_TO_EDGE => 0x812F,
+        GL_CLAMP_TO_BORDER => 0x812D,
+        GL_DEPTH_COMPONENT24 => 0x81A6,
+        GL_RGBA8 => 0x8058,
+        GL_RGBA16F => 0x881A,
+        GL_RGBA32F => 0x8814,
+        _ => panic!("Don't call for GLenum variant {:?}", e),
+    }
+}
+
+pub fn usize_to_glenum(e: usize) -> GLenum {
+    GLenum(e.try_into().unwrap())
+}
+
+pub fn scale_with_negative(v: f32, scale: f32) -> f32 {
+    (v / (scale / 2.0)) - 1.0
+}
+
+pub fn pixels_to_coords(x: f32, y: f32, window_width: f32, window_height: f32) -> (f32, f32) {
+    (
+        scale_with_negative(x, window_width),
+        -scale_with_negative(y, window_height),
+    )
+}
+
+pub fn vec2_from_tuple(v: (f32, f32)) -> TVec2<f32> {
+    glm::vec2(v.0, v.1)
+}
@@ -1,17 +1,18 @@
-use glm::TVec4;
+use glm::{TVec3, TVec4};
 
-use crate::{draw::Draw, shader_program::ShaderProgram, slider::Slider, transform::Vec3Mut};
+use crate::{draw::Draw, shader_program::ShaderProgram, slider::Slider, tween::Easing};
 
-pub struct Vec3Sliders<'a> {
-    x_slider: Slider<'a>,
-    y_slider: Slider<'a>,
-    z_slider: Slider<'a>,
+/// Three sliders editing the components of a vector, whoever owns the vector copies `value`
+/// back into it
+pub struct Vec3Sliders {
+    x_slider: Slider,
+    y_slider: Slider,
+    z_slider: Slider,
 }
 
-impl<'a> Vec3Sliders<'a> {
+impl Vec3Sliders {
     pub fn new(
-        //target: &'a mut TVec3<f32>,
-        target: Vec3Mut<'a>,
+        value: TVec3<f32>,
         position: (f32, f32),
         width: f32,
         total_height: f32,
@@ -25,8 +26,7 @@ impl<'a> Vec3Sliders<'a> {
         let height = (total_height - 2.0 * spacing) / 3.0;
         Self {
             x_slider: Slider::new(
-                //target.data.0.position,
-                target.x,
+                value.x,
                 position,
                 width,
                 height,
@@ -39,8 +39,7 @@ impl<'a> Vec3Sliders<'a> {
                 margin,
             ),
             y_slider: Slider::new(
-                //&mut target.y,
-                target.y,
+                value.y,
                 (position.0, position.1 + height + spacing),
                 width,
                 height,
@@ -53,8 +52,7 @@ impl<'a> Vec3Sliders<'a> {
                 margin,
             ),
             z_slider: Slider::new(
-                //&mut target.z,
-                target.z,
+                value.z,
                 (position.0, position.1 + 2.0 * (height + spacing)),
                 width,
                 height,
@@ -68,9 +66,29 @@ impl<'a> Vec3Sliders<'a> {
             ),
         }
     }
+
+    pub fn value(&self) -> TVec3<f32> {
+        glm::vec3(
+            self.x_slider.value(),
+            self.y_slider.value(),
+            self.z_slider.value(),
+        )
+    }
+
+    pub fn animate_to(&mut self, value: TVec3<f32>, duration: f32, easing: Easing) {
+        self.x_slider.animate_to(value.x, duration, easing);
+        self.y_slider.animate_to(value.y, duration, easing);
+        self.z_slider.animate_to(value.z, duration, easing);
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.x_slider.update(delta);
+        self.y_slider.update(delta);
+        self.z_slider.update(delta);
+    }
 }
 
-impl<'a> Draw for Vec3Sliders<'a> {
+impl Draw for Vec3Sliders {
     fn draw(&self, shader: &ShaderProgram) {
         self.x_slider.draw(shader);
         self.y_slider.draw(shader);